@@ -1,6 +1,9 @@
 use actix_web::{web, HttpResponse};
 use common::SuccessPayload;
-use domains::{account::controller_mock, data_source::DataSource};
+use domains::{
+    account::{controller_db, controller_mock},
+    data_source::DataSource,
+};
 use errors::AppError;
 
 use crate::middlewares::auth::JwtMiddleware;
@@ -19,9 +22,11 @@ pub async fn fetch_auth_account(
                     data_source,
                 ))
             },
-            |_data_source| unimplemented!(),
+            |data_source| Box::pin(controller_db::select_one(account_id, data_source)),
         )
         .await?;
 
-    Ok(HttpResponse::Ok().json(SuccessPayload { data: account }))
+    Ok(HttpResponse::Ok().json(SuccessPayload {
+        data: account.secure(),
+    }))
 }
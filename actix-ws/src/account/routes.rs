@@ -8,3 +8,54 @@ pub const SCOPE: &str = "/accounts";
 pub fn routes_config(cfg: &mut ServiceConfig) {
     cfg.service(web::scope(SCOPE).route("/me/", web::get().to(handlers::fetch_auth_account)));
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use actix_web::{http::header, test, App};
+    use chrono::Utc;
+    use domains::{
+        account::models::{Account, AccountId},
+        data_source::{DataSource, MockData, MockSource},
+    };
+    use setup::config::{auth_config::AuthConfig, server_config::ServerConfig};
+
+    use super::*;
+
+    const ACCOUNT_ID: &str = "b8213d90-bfa5-43bd-a2d2-df94641f4176";
+
+    #[actix_web::test]
+    async fn test_fetch_auth_account_is_secure() {
+        // Arrange
+        let data = MockSource::default().set(MockData::Account(vec![Account {
+            id: AccountId::from_str(ACCOUNT_ID).unwrap(),
+            email: "test@mail.com".into(),
+            password: "$argon2id$v=19$m=4096,t=3,p=1$1t71JZJtA4E2y1+U0d6fNw$sJhlb1FYypxQ/268xg8V5JBsX0uGXFhWdu+WPRj7jz0".into(), // Pass:12345
+            role: "member".into(),
+            verified: false,
+            creation_time: Utc::now(),
+            last_modification_time: None,
+        }]));
+        let auth_config = AuthConfig::new("test_secret", "actix_web", &ServerConfig::default());
+        let token = auth_config.encode_token(ACCOUNT_ID.into()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(DataSource::mock(Some(data))))
+                .app_data(web::Data::new(auth_config))
+                .configure(routes_config),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(format!("{}/me/", SCOPE).as_str())
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request();
+
+        // Act
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert
+        assert_eq!(resp["data"]["email"], "test@mail.com");
+        assert!(resp["data"].get("password").is_none());
+    }
+}
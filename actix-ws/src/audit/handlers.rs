@@ -0,0 +1,59 @@
+use actix_web::{web, HttpResponse};
+use common::SuccessPayload;
+use domains::{
+    account,
+    audit::{self, models::AuditFilter},
+    data_source::DataSource,
+};
+use errors::{AppError, ClientError, Errors};
+
+use crate::middlewares::auth::JwtMiddleware;
+
+/// Query the audit log, newest entries first (admins only)
+pub async fn fetch_all(
+    data: web::Data<DataSource>,
+    jwt: JwtMiddleware,
+    filter: web::Query<AuditFilter>,
+) -> Result<HttpResponse, AppError> {
+    let account = data
+        .exec_controller(
+            |data_source| {
+                Box::pin(account::controller_mock::select_one(
+                    jwt.account_id,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(account::controller_db::select_one(
+                    jwt.account_id,
+                    data_source,
+                ))
+            },
+        )
+        .await?;
+    if !account.is_admin() {
+        return Err(AppError::new(Errors::Client(ClientError::Forbidden {
+            reason: "The audit log is restricted to admins.".into(),
+        })));
+    }
+
+    let filter = filter.into_inner();
+    let entries = data
+        .exec_controller(
+            |data_source| {
+                Box::pin(audit::controller_mock::select_all(
+                    filter.clone(),
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(audit::controller_db::select_all(
+                    filter.clone(),
+                    data_source,
+                ))
+            },
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessPayload { data: entries }))
+}
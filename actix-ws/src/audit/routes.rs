@@ -0,0 +1,170 @@
+use actix_web::web::{self, ServiceConfig};
+
+use super::handlers;
+
+pub const SCOPE: &str = "/audit";
+
+// routes
+pub fn routes_config(cfg: &mut ServiceConfig) {
+    cfg.service(web::scope(SCOPE).route("/", web::get().to(handlers::fetch_all)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use actix_web::{http::StatusCode, test, web, App};
+    use chrono::Utc;
+    use common::SuccessPayload;
+    use domains::{
+        account::models::{Account, AccountId, ADMIN_ROLE},
+        audit::models::AuditEntry,
+        cat::models::{Cat, CatId, NewCat},
+        data_source::{DataSource, MockData, MockSource},
+    };
+    use setup::config::{auth_config::AuthConfig, server_config::ServerConfig};
+
+    use super::*;
+
+    const ADMIN_ID: &str = "b8213d90-bfa5-43bd-a2d2-df94641f4176";
+    const MEMBER_ID: &str = "6c9f4a1e-5d2b-4f7e-9a3c-1e2d3f4a5b6c";
+
+    fn test_account(id: &str, role: &str) -> Account {
+        Account {
+            id: AccountId::from_str(id).unwrap(),
+            email: format!("{role}@mail.com"),
+            password: "".into(),
+            role: role.into(),
+            verified: true,
+            creation_time: Utc::now(),
+            last_modification_time: None,
+        }
+    }
+
+    fn test_data_mock() -> web::Data<DataSource> {
+        let data = MockSource::default()
+            .set(MockData::Account(vec![
+                test_account(ADMIN_ID, ADMIN_ROLE),
+                test_account(MEMBER_ID, "member"),
+            ]))
+            .set(MockData::Cat(vec![Cat {
+                id: CatId("1".into()),
+                name: "A".into(),
+                age: 1,
+                weight: None,
+                creation_time: Utc::now(),
+                version: 1,
+                deletion_time: None,
+            }]));
+        web::Data::new(DataSource::mock(Some(data)))
+    }
+
+    fn bearer(auth_config: &AuthConfig, account_id: &str) -> (&'static str, String) {
+        let token = auth_config.encode_token(account_id.into()).unwrap();
+        ("Authorization", format!("Bearer {token}"))
+    }
+
+    #[actix_web::test]
+    async fn test_mutations_are_audited_for_admins() {
+        // Arrange
+        let auth_config = AuthConfig::new("test_secret", "actix_web", &ServerConfig::default());
+        let admin = bearer(&auth_config, ADMIN_ID);
+        let member = bearer(&auth_config, MEMBER_ID);
+        let app = test::init_service(
+            App::new()
+                .app_data(test_data_mock())
+                .app_data(web::Data::new(auth_config))
+                .configure(crate::cat::routes::routes_config)
+                .configure(routes_config),
+        )
+        .await;
+        let create = test::TestRequest::post()
+            .uri("/cats/")
+            .insert_header(admin.clone())
+            .set_json(NewCat {
+                name: "B".into(),
+                age: 2,
+                weight: None,
+            })
+            .to_request();
+        let delete = test::TestRequest::delete().uri("/cats/1/").to_request();
+        test::call_service(&app, create).await;
+        test::call_service(&app, delete).await;
+
+        // Act
+        let deleted = test::TestRequest::get()
+            .uri(&format!("{SCOPE}/?resource=cats&action=delete"))
+            .insert_header(admin.clone())
+            .to_request();
+        let deleted: SuccessPayload<Vec<AuditEntry>> =
+            test::call_and_read_body_json(&app, deleted).await;
+        let created = test::TestRequest::get()
+            .uri(&format!("{SCOPE}/?actor={ADMIN_ID}"))
+            .insert_header(admin)
+            .to_request();
+        let created: SuccessPayload<Vec<AuditEntry>> =
+            test::call_and_read_body_json(&app, created).await;
+        let forbidden = test::TestRequest::get()
+            .uri(&format!("{SCOPE}/"))
+            .insert_header(member)
+            .to_request();
+        let forbidden = test::call_service(&app, forbidden).await;
+
+        // Assert
+        assert_eq!(deleted.data.len(), 1);
+        assert_eq!(deleted.data[0].actor, None);
+        assert_eq!(deleted.data[0].resource_id, "1");
+        assert_eq!(deleted.data[0].diff["before"]["name"], "A");
+        assert_eq!(created.data.len(), 1);
+        assert_eq!(created.data[0].action, "create");
+        assert_eq!(created.data[0].diff["after"]["name"], "B");
+        assert_eq!(forbidden.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_purge_is_restricted_to_admins() {
+        // Arrange
+        let auth_config = AuthConfig::new("test_secret", "actix_web", &ServerConfig::default());
+        let admin = bearer(&auth_config, ADMIN_ID);
+        let member = bearer(&auth_config, MEMBER_ID);
+        let app = test::init_service(
+            App::new()
+                .app_data(test_data_mock())
+                .app_data(web::Data::new(auth_config))
+                .configure(crate::cat::routes::routes_config)
+                .configure(routes_config),
+        )
+        .await;
+        let delete = test::TestRequest::delete().uri("/cats/1/").to_request();
+        test::call_service(&app, delete).await;
+        let purge = |bearer: (&'static str, String)| {
+            test::TestRequest::delete()
+                .uri("/cats/trash/1/")
+                .insert_header(bearer)
+                .to_request()
+        };
+
+        // Act
+        let forbidden = test::call_service(&app, purge(member)).await;
+        let purged = test::call_service(&app, purge(admin.clone())).await;
+        let trash = test::TestRequest::get().uri("/cats/trash/").to_request();
+        let trash: SuccessPayload<Vec<Cat>> = test::call_and_read_body_json(&app, trash).await;
+        let purges = test::TestRequest::get()
+            .uri(&format!("{SCOPE}/?action=purge"))
+            .insert_header(admin)
+            .to_request();
+        let purges: SuccessPayload<Vec<AuditEntry>> =
+            test::call_and_read_body_json(&app, purges).await;
+
+        // Assert
+        assert_eq!(forbidden.status(), StatusCode::FORBIDDEN);
+        assert_eq!(purged.status(), StatusCode::OK);
+        assert!(trash.data.is_empty());
+        assert_eq!(purges.data.len(), 1);
+        assert_eq!(
+            purges.data[0].actor.map(|id| id.to_string()).as_deref(),
+            Some(ADMIN_ID)
+        );
+        assert_eq!(purges.data[0].diff["before"]["name"], "A");
+    }
+}
@@ -2,50 +2,100 @@ use actix_web::{
     cookie::{time::Duration, Cookie},
     web, HttpResponse,
 };
-use common::{AuthPayload, SuccessPayload};
+use common::{metrics, AuthPayload, SuccessPayload};
 use domains::{
+    account::models::RESOURCE,
+    audit::models::{AuditAction, NewAuditEntry},
     auth::{
-        controller_mock,
-        models::{SignInAuth, SignUpAuth},
+        controller_db, controller_mock,
+        models::{SignInAuth, SignUpAuth, SignedIn},
     },
     data_source::DataSource,
 };
-use errors::AppError;
+use errors::{AppError, ClientError, Errors};
+use setup::config::{
+    auth_config::AuthConfig, runtime_config::SharedConfig, server_options::FEATURE_SIGNUP,
+};
 
 use validator::Validate;
 
-use crate::middlewares::auth::JwtMiddleware;
+use crate::middlewares::{audit::AuditMiddleware, auth::JwtMiddleware};
 
+/// Closed (403) unless the `signup` feature is enabled in the runtime config
 pub async fn sign_up(
     auth: web::Json<SignUpAuth>,
     data: web::Data<DataSource>,
+    runtime: web::Data<SharedConfig>,
+    audit: AuditMiddleware,
 ) -> Result<HttpResponse, AppError> {
+    if !runtime.get().is_enabled(FEATURE_SIGNUP) {
+        return Err(AppError::new(Errors::Client(ClientError::Forbidden {
+            reason: "Sign up is closed.".into(),
+        })));
+    }
     auth.validate()?;
 
     let account = data
         .exec_controller(
-            |data_source| Box::pin(controller_mock::sign_up(auth.clone(), data_source)),
-            |_data_source| unimplemented!(),
+            |data_source| {
+                Box::pin(controller_mock::sign_up(
+                    auth.clone(),
+                    &audit.context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::sign_up(
+                    auth.clone(),
+                    &audit.context,
+                    data_source,
+                ))
+            },
         )
-        .await?;
+        .await;
+    metrics::record_auth("sign_up", account.is_ok());
+    let account = account?.secure();
 
-    Ok(HttpResponse::Ok().json(SuccessPayload {
-        data: account.secure(),
-    }))
+    Ok(HttpResponse::Ok().json(SuccessPayload { data: account }))
 }
 
 pub async fn sign_in(
     auth: web::Json<SignInAuth>,
     data: web::Data<DataSource>,
+    auth_config: web::Data<AuthConfig>,
+    audit: AuditMiddleware,
 ) -> Result<HttpResponse, AppError> {
     auth.validate()?;
 
-    let token = data
+    let signed_in = data
         .exec_controller(
-            |data_source| Box::pin(controller_mock::sign_in(auth.clone(), data_source)),
-            |_data_source| unimplemented!(),
+            |data_source| {
+                Box::pin(controller_mock::sign_in(
+                    auth.clone(),
+                    &auth_config,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::sign_in(
+                    auth.clone(),
+                    &auth_config,
+                    data_source,
+                ))
+            },
         )
-        .await?;
+        .await;
+    metrics::record_auth("sign_in", signed_in.is_ok());
+    let SignedIn { account_id, token } = signed_in?;
+
+    let context = audit.context.acted_by(account_id);
+    data.audit(NewAuditEntry::new(
+        &context,
+        AuditAction::SignIn,
+        RESOURCE,
+        account_id,
+    ))
+    .await?;
 
     let cookie = Cookie::build("token", token.to_owned())
         .path("/")
@@ -58,7 +108,20 @@ pub async fn sign_in(
         .json(AuthPayload { token: Some(token) }))
 }
 
-pub async fn sign_out(_: JwtMiddleware) -> Result<HttpResponse, AppError> {
+pub async fn sign_out(
+    jwt: JwtMiddleware,
+    data: web::Data<DataSource>,
+    audit: AuditMiddleware,
+) -> Result<HttpResponse, AppError> {
+    let context = audit.context.acted_by(jwt.account_id);
+    data.audit(NewAuditEntry::new(
+        &context,
+        AuditAction::SignOut,
+        RESOURCE,
+        jwt.account_id,
+    ))
+    .await?;
+
     let cookie = Cookie::build("token", "")
         .path("/")
         .max_age(Duration::new(-1, 0))
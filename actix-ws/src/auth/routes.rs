@@ -1,13 +1,18 @@
-use actix_web::web::{self, ServiceConfig};
+use actix_web::{
+    http::header::{self, CacheDirective},
+    middleware::DefaultHeaders,
+    web::{self, ServiceConfig},
+};
 
 use super::handlers;
 
 pub const SCOPE: &str = "/auth";
 
-// routes
+// routes, never cached since they carry tokens and cookies
 pub fn routes_config(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope(SCOPE)
+            .wrap(DefaultHeaders::new().add(header::CacheControl(vec![CacheDirective::NoStore])))
             .route("/signup/", web::post().to(handlers::sign_up))
             .route("/signin/", web::post().to(handlers::sign_in))
             .route("/signout/", web::get().to(handlers::sign_out)),
@@ -16,7 +21,6 @@ pub fn routes_config(cfg: &mut ServiceConfig) {
 
 #[cfg(test)]
 mod tests {
-    use dotenv;
     use std::str::FromStr;
 
     use actix_web::{test, App};
@@ -25,7 +29,12 @@ mod tests {
     use domains::{
         account::models::{Account, AccountId, SecureAccount},
         auth::models::{SignInAuth, SignUpAuth},
-        data_source::{DataSource, MockData, MockSource},
+        data_source::{DataSource, MockData, MockSource, SourceType},
+    };
+    use setup::config::{
+        auth_config::AuthConfig,
+        runtime_config::{RuntimeConfig, SharedConfig},
+        server_config::ServerConfig,
     };
 
     use super::*;
@@ -45,12 +54,26 @@ mod tests {
         web::Data::new(DataSource::mock(Some(data)))
     }
 
+    fn test_auth_config() -> web::Data<AuthConfig> {
+        web::Data::new(AuthConfig::new(
+            "test_secret",
+            "actix_web",
+            &ServerConfig::default(),
+        ))
+    }
+
     #[actix_web::test]
     async fn test_sign_up() {
         // Arrange
         let data = test_data_mock();
-        let app =
-            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .app_data(test_auth_config())
+                .app_data(web::Data::new(SharedConfig::default()))
+                .configure(routes_config),
+        )
+        .await;
         let req = test::TestRequest::post()
             .uri(format!("{}/signup/", SCOPE).as_str())
             .set_json(SignUpAuth {
@@ -67,13 +90,46 @@ mod tests {
         assert_eq!(resp.data.email, "catlover@email.com".to_owned());
     }
 
+    #[actix_web::test]
+    async fn test_sign_up_closed_without_feature() {
+        // Arrange
+        let mut config = RuntimeConfig::default();
+        config.options.features.clear();
+        let app = test::init_service(
+            App::new()
+                .app_data(test_data_mock())
+                .app_data(test_auth_config())
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .configure(routes_config),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(format!("{}/signup/", SCOPE).as_str())
+            .set_json(SignUpAuth {
+                email: "catlover@email.com".into(),
+                password: "Yop?yop!123".into(),
+                confirmation: "Yop?yop!123".into(),
+            })
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), 403);
+    }
+
     #[actix_web::test]
     async fn test_sign_in() {
-        dotenv::dotenv().ok();
         // Arrange
         let data = test_data_mock();
-        let app =
-            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .app_data(test_auth_config())
+                .configure(routes_config),
+        )
+        .await;
 
         let req = test::TestRequest::post()
             .uri(format!("{}/signin/", SCOPE).as_str())
@@ -87,25 +143,83 @@ mod tests {
         let resp: AuthPayload = test::call_and_read_body_json(&app, req).await;
 
         // Assert
-        assert_eq!(resp.token.is_some(), true);
+        assert!(resp.token.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_sign_up_and_sign_in_are_audited() {
+        // Arrange
+        let data = test_data_mock();
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .app_data(test_auth_config())
+                .app_data(web::Data::new(SharedConfig::default()))
+                .configure(routes_config),
+        )
+        .await;
+        let sign_up = test::TestRequest::post()
+            .uri(format!("{}/signup/", SCOPE).as_str())
+            .set_json(SignUpAuth {
+                email: "catlover@email.com".into(),
+                password: "Yop?yop!123".into(),
+                confirmation: "Yop?yop!123".into(),
+            })
+            .to_request();
+        let sign_in = test::TestRequest::post()
+            .uri(format!("{}/signin/", SCOPE).as_str())
+            .set_json(SignInAuth {
+                email: "test@mail.com".into(),
+                password: "Pass:12345".into(),
+            })
+            .to_request();
+
+        // Act
+        let signed_up: SuccessPayload<SecureAccount> =
+            test::call_and_read_body_json(&app, sign_up).await;
+        test::call_service(&app, sign_in).await;
+
+        // Assert
+        let SourceType::Mock(data_source) = &data.source else {
+            unreachable!()
+        };
+        let audit_log = data_source.audit_log.read().await;
+        assert_eq!(audit_log.len(), 2);
+        assert_eq!(audit_log[0].action, "sign_up");
+        assert_eq!(audit_log[0].actor, Some(signed_up.data.id.0));
+        assert_eq!(audit_log[0].diff["after"]["email"], "catlover@email.com");
+        assert_eq!(audit_log[1].action, "sign_in");
+        assert_eq!(
+            audit_log[1].actor.map(|actor| actor.to_string()).as_deref(),
+            Some("b8213d90-bfa5-43bd-a2d2-df94641f4176")
+        );
     }
 
     #[actix_web::test]
     async fn test_sign_out() {
-        dotenv::dotenv().ok();
         // Arrange
         let data = test_data_mock();
-        let app =
-            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .app_data(test_auth_config())
+                .configure(routes_config),
+        )
+        .await;
 
         let req = test::TestRequest::get()
             .uri(format!("{}/signout/", SCOPE).as_str())
             .to_request();
 
         // Act
-        let resp: AuthPayload = test::call_and_read_body_json(&app, req).await;
+        let resp = test::call_service(&app, req).await;
 
         // Assert
-        assert_eq!(resp.token.is_none(), true);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        let resp: AuthPayload = test::read_body_json(resp).await;
+        assert!(resp.token.is_none());
     }
 }
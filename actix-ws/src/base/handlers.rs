@@ -1,8 +1,36 @@
-use actix_web::HttpResponse;
-use common::InfoPayload;
-use errors::AppError;
+use actix_web::{web, HttpResponse};
+use common::{metrics, InfoPayload};
+use domains::data_source::DataSource;
+use errors::{AppError, Errors, ServerError};
 
-pub async fn check_health() -> Result<HttpResponse, AppError> {
+/// Not ready while the database is unreachable (degraded mode)
+pub async fn check_health(data: web::Data<DataSource>) -> Result<HttpResponse, AppError> {
+    if !data.is_ready() {
+        return Err(AppError::new(Errors::Server(ServerError::Unavailable)));
+    }
     let message = "[actix-ws] Instance of Actix-web server is running".into();
     Ok(HttpResponse::Ok().json(InfoPayload { message }))
 }
+
+/// Prometheus metrics, the database pool is sampled on each scrape
+pub async fn render_metrics(data: web::Data<DataSource>) -> HttpResponse {
+    data.record_metrics().await;
+    HttpResponse::Ok()
+        .content_type(metrics::content_type())
+        .body(metrics::render())
+}
+
+/// Up as long as the process answers, whatever the state of its dependencies
+pub async fn check_liveness() -> HttpResponse {
+    let message = "[actix-ws] Instance of Actix-web server is alive".into();
+    HttpResponse::Ok().json(InfoPayload { message })
+}
+
+/// Dependencies checked live, the report comes with a 503 when one of them fails
+pub async fn check_readiness(data: web::Data<DataSource>) -> HttpResponse {
+    let readiness = data.readiness().await;
+    match readiness.is_ready() {
+        true => HttpResponse::Ok().json(readiness),
+        false => HttpResponse::ServiceUnavailable().json(readiness),
+    }
+}
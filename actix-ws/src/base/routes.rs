@@ -3,5 +3,49 @@ use actix_web::web;
 use super::handlers;
 
 pub fn routes_config(cfg: &mut web::ServiceConfig) {
-    cfg.route("/health/", web::get().to(handlers::check_health));
+    cfg.route("/health/", web::get().to(handlers::check_health))
+        .route("/health/live/", web::get().to(handlers::check_liveness))
+        .route("/health/ready/", web::get().to(handlers::check_readiness));
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, App};
+    use domains::data_source::DataSource;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_readiness_fails_once_shutting_down() {
+        // Arrange
+        let data = web::Data::new(DataSource::mock(None));
+        let app =
+            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+
+        // Act
+        let ready = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/health/ready/").to_request(),
+        )
+        .await;
+        data.stop_serving();
+        let stopping = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/health/ready/").to_request(),
+        )
+        .await;
+        let live = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/health/live/").to_request(),
+        )
+        .await;
+
+        // Assert
+        assert_eq!(ready.status(), StatusCode::OK);
+        assert_eq!(stopping.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let report: serde_json::Value = test::read_body_json(stopping).await;
+        assert_eq!(report["status"], "unavailable");
+        assert_eq!(report["build"]["data_mode"], "file");
+        assert_eq!(live.status(), StatusCode::OK);
+    }
 }
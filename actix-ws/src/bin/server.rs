@@ -1,18 +1,26 @@
 #![warn(clippy::all)]
 
 use actix_ws::start;
-use setup::config::app_config::DataMode;
-use setup::APP_CONFIG;
+use setup::{
+    config::{
+        app_config::DataMode,
+        runtime_config::{ConfigReloader, RuntimeConfig, SharedConfig},
+    },
+    listener,
+    shutdown::Shutdown,
+    telemetry,
+    tls::TlsConfig,
+};
 
 use domains::data_source::DataSource;
 
-use std::env;
 use std::io;
+use std::process;
 
 /// Actix HTTP server
 /// uses multi-threading concurrency by starting multiple worker threads on startup
 /// Each thread runs a separate instance of the Actix web application
-
+///
 /// In addition to multi-threading, Actix uses Async I/O
 /// This enables an Actix web application to perform other tasks while waiting on I/O on a single thread
 /// Actix has its own Async runtime that is based on Tokio
@@ -22,26 +30,81 @@ async fn main() -> io::Result<()> {
     let env_file = concat!(env!("CARGO_MANIFEST_DIR"), "/.env");
     dotenv::from_path(env_file).ok();
 
-    let log_level = &APP_CONFIG.server.log_level;
-    env::set_var("RUST_LOG", format!("actix_web={}", log_level));
+    // Print every config problem at once and exit
+    let (app_config, auth_config) = match setup::load_config() {
+        Ok(configs) => configs,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+
+    // Init tracing, the max level comes from the (reloadable) runtime config
+    // Exported spans are flushed when `_telemetry` drops, once the server stopped
+    let runtime = RuntimeConfig::new(&app_config.server, app_config.env_mode);
+    let _telemetry = match telemetry::init(
+        app_config.env_mode,
+        runtime.log_level,
+        &app_config.server.telemetry,
+    ) {
+        Ok(telemetry) => Some(telemetry),
+        Err(err) => {
+            eprintln!("⚠️ Tracing not initialized. {err}");
+            None
+        }
+    };
+    let runtime = SharedConfig::new(runtime);
 
-    // Init logger
-    env_logger::init();
+    // Report which layer supplied each config value
+    if let Some(layers) = &app_config.layers {
+        println!("⚙️ Config layers:\n{layers}");
+    }
 
     // Data source selection
-    let data_source = match &APP_CONFIG.data_mode {
+    let data_source = match &app_config.data_mode {
         DataMode::File => {
             println!("📄 Data source set to: File");
             DataSource::mock(None)
         }
         DataMode::Database => {
             println!("🛢️ Data source set to: Db");
-            DataSource::db().await
+            DataSource::db(&app_config.database)
+                .await
+                .map_err(|err| io::Error::other(err.to_string()))?
         }
     };
 
-    let addr = &APP_CONFIG.server.format_url();
+    // Refuse to start on an outdated database schema
+    data_source
+        .prepare_schema(app_config.auto_migrate)
+        .await
+        .map_err(|err| io::Error::other(err.to_string()))?;
+
+    // HTTPS when a certificate is configured, reloaded when its files change
+    let tls = match TlsConfig::load(&app_config.server.tls) {
+        Ok(tls) => tls,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+    if let Some(tls) = &tls {
+        tls.watch();
+    }
+
+    // Bind every listen address before the config moves into the reloader
+    let listeners = listener::bind(
+        &app_config.server.listen_addrs(),
+        app_config.server.listen.unix_socket_mode,
+    )?;
+
+    // Reload the config on SIGHUP or config file change
+    ConfigReloader::new(app_config, runtime.clone()).spawn();
+
+    // Stop gracefully on SIGTERM/SIGINT
+    let shutdown = Shutdown::new();
+    shutdown.listen_signals();
 
     // Start server-app
-    start(data_source, addr).await
+    start(data_source, auth_config, runtime, shutdown, listeners, tls).await
 }
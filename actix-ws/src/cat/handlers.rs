@@ -1,16 +1,23 @@
-use actix_web::{web, HttpResponse};
-use common::{InfoPayload, SuccessPayload};
+use actix_web::{web, HttpRequest, HttpResponse};
+use common::{etag, InfoPayload, SuccessPayload};
 use domains::{
+    account,
     cat::{
         controller_db, controller_mock,
-        models::{NewCat, ReplaceCat, UpdateCat},
+        models::{Cat, NewCat, ReplaceCat, UpdateCat},
     },
     data_source::DataSource,
 };
-use errors::AppError;
+use errors::{AppError, ClientError, Errors};
+use serde::Serialize;
+
+use crate::middlewares::{audit::AuditMiddleware, auth::JwtMiddleware};
 
 /// Fetch all cats
-pub async fn fetch_all(data: web::Data<DataSource>) -> Result<HttpResponse, AppError> {
+pub async fn fetch_all(
+    req: HttpRequest,
+    data: web::Data<DataSource>,
+) -> Result<HttpResponse, AppError> {
     let cats = data
         .exec_controller(
             |data_source| Box::pin(controller_mock::select_all(data_source)),
@@ -18,11 +25,12 @@ pub async fn fetch_all(data: web::Data<DataSource>) -> Result<HttpResponse, AppE
         )
         .await?;
 
-    Ok(HttpResponse::Ok().json(SuccessPayload { data: cats }))
+    Ok(conditional_json(&req, etag::strong(&cats), cats))
 }
 
 /// Fetch one cat
 pub async fn fetch_one(
+    req: HttpRequest,
     data: web::Data<DataSource>,
     path: web::Path<i32>,
 ) -> Result<HttpResponse, AppError> {
@@ -35,18 +43,31 @@ pub async fn fetch_one(
         )
         .await?;
 
-    Ok(HttpResponse::Ok().json(SuccessPayload { data: cat }))
+    Ok(conditional_json(&req, cat.etag(), cat))
 }
 
 /// Add new cat
 pub async fn add_one(
     new_cat: web::Json<NewCat>, // data payload
     data: web::Data<DataSource>,
+    audit: AuditMiddleware,
 ) -> Result<HttpResponse, AppError> {
     let cat = data
         .exec_controller(
-            |data_source| Box::pin(controller_mock::create_one(new_cat.clone(), data_source)),
-            |data_source| Box::pin(controller_db::create_one(new_cat.clone(), data_source)),
+            |data_source| {
+                Box::pin(controller_mock::create_one(
+                    new_cat.clone(),
+                    &audit.context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::create_one(
+                    new_cat.clone(),
+                    &audit.context,
+                    data_source,
+                ))
+            },
         )
         .await?;
 
@@ -55,18 +76,23 @@ pub async fn add_one(
 
 /// Modify existing cat
 pub async fn modify_one(
+    req: HttpRequest,
     data: web::Data<DataSource>,
     update_cat: web::Json<UpdateCat>,
     path: web::Path<i32>,
+    audit: AuditMiddleware,
 ) -> Result<HttpResponse, AppError> {
     let cat_id = path.into_inner();
 
+    let version = Cat::if_match_version(header(&req, etag::IF_MATCH), &data, cat_id).await?;
+    let update_cat = update_cat.into_inner().expecting(version);
     let cat = data
         .exec_controller(
             |data_source| {
                 Box::pin(controller_mock::update_one(
                     cat_id,
                     update_cat.clone(),
+                    &audit.context,
                     data_source,
                 ))
             },
@@ -74,29 +100,38 @@ pub async fn modify_one(
                 Box::pin(controller_db::update_one(
                     cat_id,
                     update_cat.clone(),
+                    &audit.context,
                     data_source,
                 ))
             },
         )
-        .await?;
+        .await
+        .map_err(|err| Cat::if_match_error(err, version, update_cat.version, cat_id))?;
 
-    Ok(HttpResponse::Ok().json(SuccessPayload { data: cat }))
+    Ok(HttpResponse::Ok()
+        .insert_header((etag::ETAG, cat.etag()))
+        .json(SuccessPayload { data: cat }))
 }
 
 /// Replace existing cat
 pub async fn replace_one(
+    req: HttpRequest,
     data: web::Data<DataSource>,
     replace_cat: web::Json<ReplaceCat>,
     path: web::Path<i32>,
+    audit: AuditMiddleware,
 ) -> Result<HttpResponse, AppError> {
     let cat_id = path.into_inner();
 
+    let version = Cat::if_match_version(header(&req, etag::IF_MATCH), &data, cat_id).await?;
+    let replace_cat = replace_cat.into_inner().expecting(version);
     let cat = data
         .exec_controller(
             |data_source| {
                 Box::pin(controller_mock::replace_one(
                     cat_id,
                     replace_cat.clone(),
+                    &audit.context,
                     data_source,
                 ))
             },
@@ -104,28 +139,165 @@ pub async fn replace_one(
                 Box::pin(controller_db::replace_one(
                     cat_id,
                     replace_cat.clone(),
+                    &audit.context,
                     data_source,
                 ))
             },
         )
-        .await?;
+        .await
+        .map_err(|err| Cat::if_match_error(err, version, replace_cat.version, cat_id))?;
 
-    Ok(HttpResponse::Ok().json(SuccessPayload { data: cat }))
+    Ok(HttpResponse::Ok()
+        .insert_header((etag::ETAG, cat.etag()))
+        .json(SuccessPayload { data: cat }))
 }
 
-/// Delete existing cat
+/// Move existing cat to the trash
 pub async fn remove_one(
+    req: HttpRequest,
     data: web::Data<DataSource>,
     path: web::Path<i32>,
+    audit: AuditMiddleware,
 ) -> Result<HttpResponse, AppError> {
     let cat_id = path.into_inner();
 
+    let version = Cat::if_match_version(header(&req, etag::IF_MATCH), &data, cat_id).await?;
     let result = data
         .exec_controller(
-            |data_source| Box::pin(controller_mock::delete_one(cat_id, data_source)),
-            |data_source| Box::pin(controller_db::delete_one(cat_id, data_source)),
+            |data_source| {
+                Box::pin(controller_mock::delete_one(
+                    cat_id,
+                    version,
+                    &audit.context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::delete_one(
+                    cat_id,
+                    version,
+                    &audit.context,
+                    data_source,
+                ))
+            },
+        )
+        .await
+        .map_err(|err| Cat::if_match_error(err, version, version, cat_id))?;
+
+    Ok(HttpResponse::Ok().json(InfoPayload { message: result }))
+}
+
+/// Fetch the trashed cats, most recently deleted first
+pub async fn fetch_trash(data: web::Data<DataSource>) -> Result<HttpResponse, AppError> {
+    let cats = data
+        .exec_controller(
+            |data_source| Box::pin(controller_mock::select_trash(data_source)),
+            |data_source| Box::pin(controller_db::select_trash(data_source)),
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(SuccessPayload { data: cats }))
+}
+
+/// Restore a trashed cat
+pub async fn restore_one(
+    data: web::Data<DataSource>,
+    path: web::Path<i32>,
+    audit: AuditMiddleware,
+) -> Result<HttpResponse, AppError> {
+    let cat_id = path.into_inner();
+
+    let cat = data
+        .exec_controller(
+            |data_source| {
+                Box::pin(controller_mock::restore_one(
+                    cat_id,
+                    &audit.context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::restore_one(
+                    cat_id,
+                    &audit.context,
+                    data_source,
+                ))
+            },
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((etag::ETAG, cat.etag()))
+        .json(SuccessPayload { data: cat }))
+}
+
+/// Delete a trashed cat for good (admins only)
+pub async fn purge_one(
+    data: web::Data<DataSource>,
+    path: web::Path<i32>,
+    jwt: JwtMiddleware,
+    audit: AuditMiddleware,
+) -> Result<HttpResponse, AppError> {
+    let cat_id = path.into_inner();
+
+    let account = data
+        .exec_controller(
+            |data_source| {
+                Box::pin(account::controller_mock::select_one(
+                    jwt.account_id,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(account::controller_db::select_one(
+                    jwt.account_id,
+                    data_source,
+                ))
+            },
+        )
+        .await?;
+    if !account.is_admin() {
+        return Err(AppError::new(Errors::Client(ClientError::Forbidden {
+            reason: "Purging cats is restricted to admins.".into(),
+        })));
+    }
+
+    let result = data
+        .exec_controller(
+            |data_source| {
+                Box::pin(controller_mock::purge_one(
+                    cat_id,
+                    &audit.context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::purge_one(
+                    cat_id,
+                    &audit.context,
+                    data_source,
+                ))
+            },
         )
         .await?;
 
     Ok(HttpResponse::Ok().json(InfoPayload { message: result }))
 }
+
+/// Json payload of `data` tagged `tag`, 304 without body when the client copy is up to date
+fn conditional_json<T: Serialize>(req: &HttpRequest, tag: String, data: T) -> HttpResponse {
+    match header(req, etag::IF_NONE_MATCH) {
+        Some(header) if etag::none_match_hit(header, &tag) => HttpResponse::NotModified()
+            .insert_header((etag::ETAG, tag))
+            .finish(),
+        _ => HttpResponse::Ok()
+            .insert_header((etag::ETAG, tag))
+            .json(SuccessPayload { data }),
+    }
+}
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
@@ -8,12 +8,16 @@ pub const SCOPE: &str = "/cats";
 pub fn routes_config(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope(SCOPE)
+            // Before the `{cat_id}` routes, which would match `trash`
+            .route("/trash/", web::get().to(handlers::fetch_trash))
+            .route("/trash/{cat_id}/", web::delete().to(handlers::purge_one))
             .route("/", web::get().to(handlers::fetch_all))
             .route("/{cat_id}/", web::get().to(handlers::fetch_one))
             .route("/", web::post().to(handlers::add_one))
             .route("/{cat_id}/", web::patch().to(handlers::modify_one))
             .route("/{cat_id}/", web::put().to(handlers::replace_one))
-            .route("/{cat_id}/", web::delete().to(handlers::remove_one)),
+            .route("/{cat_id}/", web::delete().to(handlers::remove_one))
+            .route("/{cat_id}/restore/", web::post().to(handlers::restore_one)),
     );
 }
 
@@ -21,13 +25,20 @@ pub fn routes_config(cfg: &mut ServiceConfig) {
 mod tests {
     use super::*;
 
-    use actix_web::{test, web, App};
+    use actix_web::{
+        http::{
+            header::{HeaderValue, AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH},
+            StatusCode,
+        },
+        test, web, App,
+    };
     use chrono::Utc;
     use common::{InfoPayload, SuccessPayload};
     use domains::{
         cat::models::{Cat, CatId, NewCat, ReplaceCat, UpdateCat},
         data_source::{DataSource, MockData, MockSource},
     };
+    use setup::config::{db_config::DbConfig, db_options::DbOptions};
 
     fn test_data_mock() -> web::Data<DataSource> {
         let data = MockSource::default().set(MockData::Cat(vec![
@@ -37,6 +48,8 @@ mod tests {
                 age: 1,
                 weight: None,
                 creation_time: Utc::now(),
+                version: 1,
+                deletion_time: None,
             },
             Cat {
                 id: CatId("2".into()),
@@ -44,6 +57,8 @@ mod tests {
                 age: 1,
                 weight: Some(3.0),
                 creation_time: Utc::now(),
+                version: 1,
+                deletion_time: None,
             },
         ]));
         web::Data::new(DataSource::mock(Some(data)))
@@ -117,6 +132,7 @@ mod tests {
                 name: None,
                 age: Some(3),
                 weight: Some(7.5),
+                version: None,
             })
             .to_request();
 
@@ -140,6 +156,7 @@ mod tests {
                 name: "Z".into(),
                 age: 5,
                 weight: Some(5.4),
+                version: None,
             })
             .to_request();
 
@@ -166,6 +183,212 @@ mod tests {
         let payload: InfoPayload = test::call_and_read_body_json(&app, req).await;
 
         // Assert
-        assert_eq!(payload.message.is_empty(), false);
+        assert!(!payload.message.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_one_if_match_unavailable_database() {
+        // Arrange
+        let config = DbConfig {
+            host: "127.0.0.1".into(),
+            port: 1,
+            options: DbOptions {
+                connect_retries: 1,
+                retry_delay_ms: 10,
+                acquire_timeout_secs: 1,
+                degraded_start: true,
+                ..DbOptions::default()
+            },
+            ..DbConfig::default()
+        };
+        let data = web::Data::new(DataSource::db(&config).await.unwrap());
+        let app = test::init_service(App::new().app_data(data).configure(routes_config)).await;
+        let req = test::TestRequest::delete()
+            .uri(format!("{}/2/", SCOPE).as_str())
+            .insert_header((IF_MATCH, "*"))
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_authorization_is_anonymous() {
+        // Arrange
+        let data = test_data_mock();
+        let app =
+            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let delete = |cat_id: i32, authorization: HeaderValue| {
+            test::TestRequest::delete()
+                .uri(format!("{}/{}/", SCOPE, cat_id).as_str())
+                .insert_header((AUTHORIZATION, authorization))
+                .to_request()
+        };
+
+        // Act
+        let short = test::call_service(&app, delete(1, HeaderValue::from_static("x"))).await;
+        let non_ascii = test::call_service(
+            &app,
+            delete(2, HeaderValue::from_bytes(b"Bearer \xe9t\xe9").unwrap()),
+        )
+        .await;
+
+        // Assert
+        assert_eq!(short.status(), StatusCode::OK);
+        assert_eq!(non_ascii.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_trash_and_restore() {
+        // Arrange
+        let data = test_data_mock();
+        let app =
+            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let delete = test::TestRequest::delete()
+            .uri(format!("{}/2/", SCOPE).as_str())
+            .to_request();
+        test::call_service(&app, delete).await;
+
+        // Act
+        let trash = test::TestRequest::get()
+            .uri(format!("{}/trash/", SCOPE).as_str())
+            .to_request();
+        let trash: SuccessPayload<Vec<Cat>> = test::call_and_read_body_json(&app, trash).await;
+        let all = test::TestRequest::get()
+            .uri(format!("{}/", SCOPE).as_str())
+            .to_request();
+        let all: SuccessPayload<Vec<Cat>> = test::call_and_read_body_json(&app, all).await;
+        let restore = test::TestRequest::post()
+            .uri(format!("{}/2/restore/", SCOPE).as_str())
+            .to_request();
+        let restored: SuccessPayload<Cat> = test::call_and_read_body_json(&app, restore).await;
+        let restore_again = test::TestRequest::post()
+            .uri(format!("{}/2/restore/", SCOPE).as_str())
+            .to_request();
+        let restore_again = test::call_service(&app, restore_again).await;
+
+        // Assert
+        assert_eq!(trash.data.len(), 1);
+        assert!(trash.data[0].deletion_time.is_some());
+        assert!(all.data.iter().all(|cat| cat.id.0 != "2"));
+        assert_eq!(restored.data.name, "B");
+        assert!(restored.data.deletion_time.is_none());
+        assert_eq!(restore_again.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_get_one_not_modified() {
+        // Arrange
+        let data = test_data_mock();
+        let app =
+            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let uri = format!("{}/1/", SCOPE);
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        let etag = resp.headers().get(ETAG).unwrap().clone();
+
+        // Act
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri(&uri)
+                .insert_header((IF_NONE_MATCH, etag.clone()))
+                .to_request(),
+        )
+        .await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(ETAG), Some(&etag));
+        assert!(test::read_body(resp).await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_patch_one_if_match() {
+        // Arrange
+        let data = test_data_mock();
+        let app =
+            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let uri = format!("{}/1/", SCOPE);
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        let etag = resp.headers().get(ETAG).unwrap().clone();
+        let patch = |age| {
+            test::TestRequest::patch()
+                .uri(&uri)
+                .insert_header((IF_MATCH, etag.clone()))
+                .set_json(UpdateCat {
+                    name: None,
+                    age: Some(age),
+                    weight: None,
+                    version: None,
+                })
+                .to_request()
+        };
+
+        // Act
+        let first = test::call_service(&app, patch(4)).await;
+        let second = test::call_service(&app, patch(5)).await;
+
+        // Assert
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_ne!(first.headers().get(ETAG), Some(&etag));
+        assert_eq!(second.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[actix_web::test]
+    async fn test_patch_one_stale_version() {
+        // Arrange
+        let data = test_data_mock();
+        let app =
+            test::init_service(App::new().app_data(data.clone()).configure(routes_config)).await;
+        let uri = format!("{}/1/", SCOPE);
+        let patch = |version| {
+            test::TestRequest::patch()
+                .uri(&uri)
+                .set_json(UpdateCat {
+                    name: None,
+                    age: Some(4),
+                    weight: None,
+                    version: Some(version),
+                })
+                .to_request()
+        };
+
+        // Act
+        let first: SuccessPayload<Cat> = test::call_and_read_body_json(&app, patch(1)).await;
+        let second = test::call_service(&app, patch(1)).await;
+
+        // Assert
+        assert_eq!(first.data.version, 2);
+        assert_eq!(second.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn test_error_payload_carries_request_id() {
+        // Arrange
+        let data = test_data_mock();
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .wrap(actix_web::middleware::from_fn(
+                    crate::middlewares::request_id::request_id,
+                ))
+                .configure(routes_config),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(format!("{}/99/", SCOPE).as_str())
+            .insert_header(("x-request-id", "abc-123"))
+            .to_request();
+
+        // Act
+        let res = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "abc-123");
+        let payload: common::ErrorPayload<String> = test::read_body_json(res).await;
+        assert_eq!(payload.request_id.as_deref(), Some("abc-123"));
     }
 }
@@ -0,0 +1,20 @@
+use actix_web::{web, HttpResponse};
+use errors::{AppError, ClientError, Errors};
+use utoipa::OpenApi;
+
+/// Serve the OpenAPI spec
+pub async fn openapi_spec() -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .body(docs::openapi_json(&docs::ApiDoc::openapi())))
+}
+
+/// Serve the embedded Swagger UI assets
+pub async fn swagger_ui(tail: web::Path<String>) -> Result<HttpResponse, AppError> {
+    match docs::swagger_ui_file(&tail) {
+        Some(file) => Ok(HttpResponse::Ok()
+            .content_type(file.content_type)
+            .body(file.bytes.into_owned())),
+        None => Err(AppError::new(Errors::Client(ClientError::RouteUnknown))),
+    }
+}
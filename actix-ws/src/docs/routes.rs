@@ -0,0 +1,88 @@
+use actix_web::web;
+
+use super::handlers;
+
+/// Registered at the app root, the docs routes being absolute
+pub fn routes_config(cfg: &mut web::ServiceConfig) {
+    cfg.route(docs::OPENAPI_PATH, web::get().to(handlers::openapi_spec))
+        .route(
+            &format!("{}{{tail:.*}}", docs::DOCS_PATH),
+            web::get().to(handlers::swagger_ui),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, web, App};
+    use domains::data_source::{DataSource, MockSource};
+    use utoipa::OpenApi;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_get_openapi_spec() {
+        // Arrange
+        let app = test::init_service(App::new().configure(routes_config)).await;
+        let req = test::TestRequest::get()
+            .uri(docs::OPENAPI_PATH)
+            .to_request();
+
+        // Act
+        let spec: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"].get("/api/cats/").is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_get_swagger_ui() {
+        // Arrange
+        let app = test::init_service(App::new().configure(routes_config)).await;
+        let req = test::TestRequest::get().uri(docs::DOCS_PATH).to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    /// Every documented path must be served by the route configs
+    #[actix_web::test]
+    async fn test_documented_paths_are_routed() {
+        // Arrange
+        let data = web::Data::new(DataSource::mock(Some(MockSource::new())));
+        let app = test::init_service(
+            App::new()
+                .app_data(data)
+                .service(web::scope("/api").configure(crate::api_config)),
+        )
+        .await;
+        let spec = docs::ApiDoc::openapi();
+
+        for (path, item) in spec.paths.paths.iter() {
+            let uri = path.replace("{cat_id}", "1");
+            let methods = [
+                (item.get.is_some(), test::TestRequest::get()),
+                (item.post.is_some(), test::TestRequest::post()),
+                (item.patch.is_some(), test::TestRequest::patch()),
+                (item.put.is_some(), test::TestRequest::put()),
+                (item.delete.is_some(), test::TestRequest::delete()),
+            ];
+            for (_, req) in methods.into_iter().filter(|(documented, _)| *documented) {
+                // Act
+                let resp = test::call_service(&app, req.uri(&uri).to_request()).await;
+
+                // Assert
+                // Unrouted requests get an empty 404, routed ones always carry a payload
+                let status = resp.status();
+                let body = test::read_body(resp).await;
+                assert!(
+                    status != StatusCode::NOT_FOUND || !body.is_empty(),
+                    "{uri} is documented but not routed"
+                );
+            }
+        }
+    }
+}
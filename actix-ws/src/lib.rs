@@ -1,58 +1,505 @@
-use std::io;
+use std::{future::Future, io, net::IpAddr};
 
 use actix_cors::Cors;
 use actix_web::{
+    dev::Server,
     http::header,
-    middleware::{self, Logger, NormalizePath},
-    web, App, HttpServer,
+    middleware::{self, NormalizePath},
+    web, App, HttpRequest, HttpResponse, HttpServer,
 };
+use common::{etag, request_id};
 use domains::data_source::DataSource;
-use errors::{AppError, ClientError, Errors};
+use setup::{
+    config::{
+        auth_config::AuthConfig, runtime_config::SharedConfig, server_options::MAX_BODY_LIMIT,
+    },
+    listener::Listener,
+    shutdown::Shutdown,
+    tls::{self, TlsConfig},
+};
 
 mod account;
+mod audit;
 mod auth;
 mod base;
 mod cat;
+mod docs;
 mod middlewares;
 
-/// Start HTTP server
-pub async fn start(data_source: DataSource, addr: &str) -> io::Result<()> {
+/// CORS layer of the configured policy, allowed origins are read from the live config
+fn cors_layer(runtime: SharedConfig) -> Cors {
+    let policy = runtime.get().options.cors.clone();
+    let cors = Cors::default()
+        .allowed_origin_fn(move |origin, _req_head| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| runtime.get().allows_origin(origin))
+        })
+        .allowed_methods(policy.methods.iter().map(String::as_str))
+        .allowed_headers(policy.headers.iter().map(String::as_str))
+        .expose_headers(vec![request_id::HEADER, etag::ETAG])
+        .max_age(usize::try_from(policy.max_age_secs).ok());
+
+    match policy.credentials {
+        true => cors.supports_credentials(),
+        false => cors,
+    }
+}
+
+/// Start HTTP server on every listener, HTTPS when `tls` is set
+pub async fn start(
+    data_source: DataSource,
+    auth_config: AuthConfig,
+    runtime: SharedConfig,
+    shutdown: Shutdown,
+    listeners: Vec<Listener>,
+    tls: Option<TlsConfig>,
+) -> io::Result<()> {
+    for listener in &listeners {
+        println!("🚀 Server listening on: {}", listener.url(tls.is_some()));
+    }
+    if let Some(port) = tls.as_ref().and_then(|tls| tls.redirect_port) {
+        println!("↪️ Redirecting HTTP to HTTPS from port: {}", port);
+    }
+
+    server(data_source, auth_config, runtime, shutdown, listeners, tls)?.await
+}
+
+/// Build the HTTP server on already bound listeners (see `setup::listener::bind`)
+/// The server only runs once awaited (or spawned), and stops once `shutdown` is triggered:
+/// readiness is cleared, the listeners close after the shutdown delay, in-flight requests get the grace
+/// period to finish, then the database pool is closed
+/// While running, the trash is purged of the cats kept past their retention
+/// CORS origins and body limit are read from the runtime config on each request (reloadable),
+/// the rest of the CORS policy when the server is built
+/// With `tls`, TCP listeners serve HTTPS and a second server may redirect plain HTTP to it
+/// (on the same ips), unix sockets stay plain HTTP
+pub fn server(
+    data_source: DataSource,
+    auth_config: AuthConfig,
+    runtime: SharedConfig,
+    shutdown: Shutdown,
+    listeners: Vec<Listener>,
+    tls: Option<TlsConfig>,
+) -> io::Result<impl Future<Output = io::Result<()>>> {
+    let delay = runtime.get().options.shutdown_delay();
+    let grace = runtime.get().options.shutdown_grace_secs;
+    let tcp_addrs = listeners
+        .iter()
+        .filter_map(|listener| match listener {
+            Listener::Tcp(listener) => listener.local_addr().ok(),
+            Listener::Unix(..) => None,
+        })
+        .collect::<Vec<_>>();
+    let redirect = match (
+        tls.as_ref().and_then(|tls| tls.redirect_port),
+        tcp_addrs.first(),
+    ) {
+        (Some(port), Some(https_addr)) => {
+            let mut ips = tcp_addrs.iter().map(|addr| addr.ip()).collect::<Vec<_>>();
+            ips.sort();
+            ips.dedup();
+            Some(redirect_server(&ips, port, https_addr.port())?)
+        }
+        _ => None,
+    };
+
     // web::Data will wrap our data into an Arc
     let data = web::Data::new(data_source);
-
-    println!("🚀 Server listening on: {}", addr);
+    let auth_config = web::Data::new(auth_config);
+    let purge_runtime = runtime.clone();
+    let runtime = web::Data::new(runtime);
+    let app_data = data.clone();
 
     // HttpServer constructs an application instance for each thread
-    HttpServer::new(move || {
-        let logger = Logger::default();
+    let mut server = HttpServer::new(move || {
         let path_normalizer = NormalizePath::new(middleware::TrailingSlash::Always);
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:8080")
-            .allowed_methods(vec!["GET", "POST", "PATCH", "PUT", "DELETE"])
-            .allowed_headers(vec![
-                header::CONTENT_TYPE,
-                header::AUTHORIZATION,
-                header::ACCEPT,
-            ])
-            .supports_credentials();
+        let cors = cors_layer(runtime.get_ref().clone());
 
         App::new()
-            .app_data(data.clone())
+            .app_data(app_data.clone())
+            .app_data(auth_config.clone())
+            .app_data(runtime.clone())
+            .wrap(middleware::from_fn(middlewares::body_limit::body_limit))
             .wrap(cors)
+            .wrap(middleware::from_fn(middlewares::metrics::metrics))
+            .wrap(middleware::from_fn(
+                middlewares::security_headers::security_headers,
+            ))
             .wrap(path_normalizer)
-            .wrap(logger)
-            .app_data(web::JsonConfig::default().error_handler(|_err, _req| {
-                AppError::new(Errors::Client(ClientError::InvalidJson)).into()
-            }))
-            .service(
-                web::scope("/api")
-                    .configure(base::routes::routes_config)
-                    .configure(auth::routes::routes_config)
-                    .configure(account::routes::routes_config)
-                    .configure(cat::routes::routes_config),
+            .wrap(middleware::from_fn(middlewares::request_id::request_id))
+            .app_data(
+                web::JsonConfig::default()
+                    .limit(MAX_BODY_LIMIT as usize)
+                    .error_handler(middlewares::body_limit::json_error),
             )
+            // Ahead of the `/api` scope, which would otherwise answer their paths
+            .configure(docs::routes::routes_config)
+            .service(web::scope("/api").configure(api_config))
+            .route("/metrics/", web::get().to(base::handlers::render_metrics))
+    })
+    // Signals are handled by `Shutdown`, same as warp-ws
+    .disable_signals()
+    .shutdown_timeout(grace);
+    let mut socket_files = Vec::new();
+    for listener in listeners {
+        server = match (listener, &tls) {
+            (Listener::Tcp(listener), Some(tls)) => {
+                server.listen_rustls_0_21(listener, (*tls.server_config).clone())?
+            }
+            (Listener::Tcp(listener), None) => server.listen(listener)?,
+            (Listener::Unix(listener, file), _) => {
+                socket_files.push(file);
+                server.listen_uds(listener)?
+            }
+        };
+    }
+    let server = server.run();
+
+    Ok(async move {
+        let handle = server.handle();
+        let redirect_handle = redirect.as_ref().map(Server::handle);
+        if let Some(redirect) = redirect {
+            actix_web::rt::spawn(redirect);
+        }
+        let purge = data.clone().into_inner().spawn_trash_purge(purge_runtime);
+        let stopping = data.clone();
+        let stop = actix_web::rt::spawn(async move {
+            shutdown.wait().await;
+            stopping.stop_serving();
+            // Readiness fails while the listeners are still open
+            actix_web::rt::time::sleep(delay).await;
+            if let Some(redirect_handle) = redirect_handle {
+                redirect_handle.stop(true).await;
+            }
+            handle.stop(true).await;
+        });
+
+        let result = server.await;
+        stop.abort();
+        purge.abort();
+        data.close().await;
+        drop(socket_files);
+        result
+    })
+}
+
+/// Plain HTTP server on `port` of every ip, redirecting every request to its HTTPS url
+fn redirect_server(ips: &[IpAddr], port: u16, https_port: u16) -> io::Result<Server> {
+    let mut server = HttpServer::new(move || {
+        App::new().default_service(web::to(move |req: HttpRequest| async move {
+            let host = req
+                .headers()
+                .get(header::HOST)
+                .and_then(|host| host.to_str().ok());
+            let path_and_query = req.uri().path_and_query().map_or("/", |path| path.as_str());
+            let ip = req.app_config().local_addr().ip();
+            HttpResponse::PermanentRedirect()
+                .insert_header((
+                    header::LOCATION,
+                    tls::https_location(host, ip, https_port, path_and_query),
+                ))
+                .finish()
+        }))
     })
-    .bind(addr)?
-    .run()
-    .await
+    .disable_signals()
+    .workers(1);
+    for ip in ips {
+        server = server.bind((*ip, port))?;
+    }
+    Ok(server.run())
+}
+
+/// Routes served under the `/api` scope
+fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.configure(base::routes::routes_config)
+        .configure(auth::routes::routes_config)
+        .configure(account::routes::routes_config)
+        .configure(audit::routes::routes_config)
+        .configure(cat::routes::routes_config);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use actix_web::{http::Method, rt::time, test};
+    use domains::data_source::SourceType;
+    use setup::{
+        config::{
+            app_config::EnvMode,
+            cors_options::{self, CorsOptions},
+            db_config::DbConfig,
+            db_options::DbOptions,
+            listen_options::ListenAddr,
+            runtime_config::RuntimeConfig,
+            security_options,
+            server_config::ServerConfig,
+        },
+        listener,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+
+    /// Server on a random local port, with the `shutdown_delay_secs` pre-stop delay
+    fn spawn_server(
+        data_source: DataSource,
+        shutdown_delay_secs: u64,
+        shutdown: &Shutdown,
+    ) -> (SocketAddr, actix_web::rt::task::JoinHandle<io::Result<()>>) {
+        let listeners =
+            listener::bind(&[ListenAddr::Tcp(([127, 0, 0, 1], 0).into())], 0o600).unwrap();
+        let addr = match &listeners[0] {
+            Listener::Tcp(listener) => listener.local_addr().unwrap(),
+            Listener::Unix(..) => unreachable!(),
+        };
+        let mut config = RuntimeConfig::default();
+        config.options.shutdown_delay_secs = shutdown_delay_secs;
+        let server = server(
+            data_source,
+            AuthConfig::new("test_secret", "actix_web", &ServerConfig::default()),
+            SharedConfig::new(config),
+            shutdown.clone(),
+            listeners,
+            None,
+        )
+        .unwrap();
+        (addr, actix_web::rt::spawn(server))
+    }
+
+    /// Raw HTTP/1.1 exchange on a new connection, closed by the server once answered
+    async fn exchange(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[actix_web::test]
+    async fn test_cors_preflight_follows_policy() {
+        // Arrange
+        let mut config = RuntimeConfig::default();
+        config.options.cors = CorsOptions::from_lookup(|key| match key {
+            cors_options::ORIGINS => Some("https://*.cats.dev".into()),
+            _ => None,
+        })
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(cors_layer(SharedConfig::new(config)))
+                .route("/", web::patch().to(HttpResponse::Ok)),
+        )
+        .await;
+        let preflight = |origin: &str| {
+            test::TestRequest::default()
+                .method(Method::OPTIONS)
+                .insert_header((header::ORIGIN, origin))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization"))
+                .to_request()
+        };
+
+        // Act
+        let allowed = test::call_service(&app, preflight("https://a.cats.dev")).await;
+        let denied = test::call_service(&app, preflight("https://cats.dev")).await;
+
+        // Assert
+        assert!(allowed.status().is_success());
+        let headers = allowed.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://a.cats.dev"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+        assert!(headers
+            .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+            .is_some_and(|methods| methods.to_str().unwrap().contains("PATCH")));
+        assert!(denied.status().is_client_error());
+    }
+
+    #[actix_web::test]
+    async fn test_security_headers_by_route() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(RuntimeConfig::new(
+                    &ServerConfig::default(),
+                    EnvMode::Production,
+                ))))
+                .wrap(middleware::from_fn(
+                    middlewares::security_headers::security_headers,
+                ))
+                .configure(docs::routes::routes_config),
+        )
+        .await;
+
+        // Act
+        let spec = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri(::docs::OPENAPI_PATH)
+                .to_request(),
+        )
+        .await;
+        let ui = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/api/docs/").to_request(),
+        )
+        .await;
+        let unknown = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/api/docs/unknown.js")
+                .to_request(),
+        )
+        .await;
+
+        // Assert
+        assert_eq!(
+            spec.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+            "nosniff"
+        );
+        assert_eq!(spec.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        assert_eq!(
+            spec.headers().get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            security_options::DEFAULT_CSP
+        );
+        assert_eq!(
+            spec.headers()
+                .get(header::STRICT_TRANSPORT_SECURITY)
+                .unwrap(),
+            security_options::DEFAULT_HSTS
+        );
+        assert_eq!(
+            ui.headers().get(header::CONTENT_SECURITY_POLICY).unwrap(),
+            security_options::DEFAULT_DOCS_CSP
+        );
+        assert!(unknown.status().is_client_error());
+        assert_eq!(
+            unknown
+                .headers()
+                .get(header::X_CONTENT_TYPE_OPTIONS)
+                .unwrap(),
+            "nosniff"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_trigger_stops_server() {
+        // Arrange
+        let listeners =
+            listener::bind(&[ListenAddr::Tcp(([127, 0, 0, 1], 0).into())], 0o600).unwrap();
+        let shutdown = Shutdown::new();
+        let server = server(
+            DataSource::mock(None),
+            AuthConfig::new("test_secret", "actix_web", &ServerConfig::default()),
+            SharedConfig::default(),
+            shutdown.clone(),
+            listeners,
+            None,
+        )
+        .unwrap();
+        let server = actix_web::rt::spawn(server);
+
+        // Act
+        shutdown.trigger();
+
+        // Assert
+        actix_web::rt::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("Server stopped")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_drains_in_flight_requests() {
+        // Arrange
+        let shutdown = Shutdown::new();
+        let (addr, server) = spawn_server(DataSource::mock(None), 1, &shutdown);
+        let body = r#"{"name": "Nala", "age": 2}"#;
+        let mut in_flight = TcpStream::connect(addr).await.unwrap();
+        in_flight
+            .write_all(
+                format!(
+                    "POST /api/cats/ HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    &body[..10]
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let ready =
+            "GET /api/health/ready/ HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        let serving = exchange(addr, ready).await;
+
+        // Act
+        shutdown.trigger();
+        time::sleep(Duration::from_millis(200)).await;
+        let stopping = exchange(addr, ready).await;
+        // Past the delay, the listeners are closed
+        time::sleep(Duration::from_millis(1300)).await;
+        in_flight.write_all(&body.as_bytes()[10..]).await.unwrap();
+        let mut response = String::new();
+        in_flight.read_to_string(&mut response).await.unwrap();
+
+        // Assert
+        assert!(serving.starts_with("HTTP/1.1 200"), "{serving}");
+        assert!(stopping.starts_with("HTTP/1.1 503"), "{stopping}");
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(TcpStream::connect(addr).await.is_err());
+        time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("Server stopped")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_closes_pool() {
+        // Arrange
+        let config = DbConfig {
+            host: "127.0.0.1".into(),
+            port: 1,
+            options: DbOptions {
+                connect_retries: 1,
+                retry_delay_ms: 10,
+                acquire_timeout_secs: 1,
+                degraded_start: true,
+                ..DbOptions::default()
+            },
+            ..DbConfig::default()
+        };
+        let data_source = DataSource::db(&config).await.unwrap();
+        let pool = match &data_source.source {
+            SourceType::DB(source) => source.db.connection.clone(),
+            SourceType::Mock(_) => unreachable!(),
+        };
+        let shutdown = Shutdown::new();
+        let (_, server) = spawn_server(data_source, 0, &shutdown);
+
+        // Act
+        shutdown.trigger();
+        time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("Server stopped")
+            .unwrap()
+            .unwrap();
+
+        // Assert
+        assert!(pool.is_closed());
+    }
 }
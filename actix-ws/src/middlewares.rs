@@ -1 +1,6 @@
+pub mod audit;
 pub mod auth;
+pub mod body_limit;
+pub mod metrics;
+pub mod request_id;
+pub mod security_headers;
@@ -0,0 +1,34 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use domains::audit::models::AuditContext;
+use errors::AppError;
+use setup::config::auth_config::AuthConfig;
+
+use crate::middlewares::auth;
+
+/// Origin of the mutations audited by a handler
+/// The actor is the account of a valid token, the request stays anonymous otherwise (no auth error)
+#[derive(Debug)]
+pub struct AuditMiddleware {
+    pub context: AuditContext,
+}
+
+impl FromRequest for AuditMiddleware {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let actor = req
+            .app_data::<web::Data<AuthConfig>>()
+            .zip(auth::token(req))
+            .and_then(|(auth_config, token)| auth_config.decode_claims(&token).ok())
+            .and_then(|claims| uuid::Uuid::parse_str(&claims.sub).ok());
+        // No peer address on unix sockets
+        let ip = req.peer_addr().map(|addr| addr.ip());
+
+        ready(Ok(AuditMiddleware {
+            context: AuditContext::new(actor, ip),
+        }))
+    }
+}
@@ -1,10 +1,11 @@
 use std::future::{ready, Ready};
 
 use actix_web::dev::Payload;
-use actix_web::{http, FromRequest, HttpRequest};
+use actix_web::{http, web, FromRequest, HttpRequest};
 
-use errors::{AppError, ClientError, Errors};
-use setup;
+use common::{metrics, request_id};
+use errors::{AppError, ClientError, Errors, ServerError};
+use setup::config::auth_config::AuthConfig;
 
 #[derive(Debug)]
 pub struct JwtMiddleware {
@@ -16,27 +17,98 @@ impl FromRequest for JwtMiddleware {
     type Future = Ready<Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        let token = req
-            .cookie("token")
-            .map(|c| c.value().to_string())
-            .or_else(|| {
-                req.headers()
-                    .get(http::header::AUTHORIZATION)
-                    .map(|h| h.to_str().unwrap().split_at(7).1.to_string())
-            });
-
-        if token.is_none() {
+        let Some(token) = token(req) else {
+            metrics::record_auth("token", false);
             return ready(Err(AppError::new(Errors::Client(
                 ClientError::TokenNotFound,
             ))));
-        }
-
-        match setup::AUTH_CONFIG.decode_claims(&token.unwrap()) {
-            Ok(claims) => {
-                let account_id = uuid::Uuid::parse_str(claims.sub.as_str()).unwrap();
-                ready(Ok(JwtMiddleware { account_id }))
-            }
-            Err(err) => ready(Err(err)),
-        }
+        };
+
+        // Set by `server`, a missing config is a server error rather than a client one
+        let Some(auth_config) = req.app_data::<web::Data<AuthConfig>>() else {
+            return ready(Err(AppError::new(Errors::Server(ServerError::Internal))));
+        };
+
+        let account_id = auth_config.decode_claims(&token).and_then(|claims| {
+            uuid::Uuid::parse_str(&claims.sub).map_err(|_| {
+                AppError::new(Errors::Client(ClientError::Unauthorized {
+                    reason: "Invalid token subject.".into(),
+                }))
+            })
+        });
+        metrics::record_auth("token", account_id.is_ok());
+        ready(account_id.map(|account_id| {
+            request_id::record_account(&account_id.to_string());
+            JwtMiddleware { account_id }
+        }))
+    }
+}
+
+/// Auth token of the request, from the `token` cookie or the bearer authorization header
+/// A malformed header (not `Bearer <token>`, or not visible ascii) counts as no token
+pub fn token(req: &HttpRequest) -> Option<String> {
+    req.cookie("token")
+        .map(|c| c.value().to_string())
+        .or_else(|| {
+            req.headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|h| h.to_str().ok()?.strip_prefix("Bearer "))
+                .map(str::to_owned)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test, App, HttpResponse};
+    use setup::config::server_config::ServerConfig;
+
+    use super::*;
+
+    async fn authenticated(jwt: JwtMiddleware) -> HttpResponse {
+        HttpResponse::Ok().body(jwt.account_id.to_string())
+    }
+
+    fn test_auth_config() -> AuthConfig {
+        AuthConfig::new("test_secret", "actix_web", &ServerConfig::default())
+    }
+
+    #[actix_web::test]
+    async fn test_token_subject_not_uuid_unauthorized() {
+        // Arrange
+        let auth_config = test_auth_config();
+        let token = auth_config.encode_token("not-an-uuid".into()).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(auth_config))
+                .route("/", web::get().to(authenticated)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .insert_header((http::header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_missing_auth_config_internal_error() {
+        // Arrange
+        let token = test_auth_config()
+            .encode_token(uuid::Uuid::new_v4().to_string())
+            .unwrap();
+        let app = test::init_service(App::new().route("/", web::get().to(authenticated))).await;
+        let req = test::TestRequest::get()
+            .insert_header((http::header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request();
+
+        // Act
+        let resp = test::call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 }
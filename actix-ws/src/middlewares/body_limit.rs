@@ -0,0 +1,143 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::{JsonPayloadError, PayloadError},
+    http::header,
+    middleware::Next,
+    web, Error, HttpMessage, HttpRequest,
+};
+use errors::{AppError, ClientError, Errors, ServerError};
+use futures_util::StreamExt;
+use setup::config::runtime_config::SharedConfig;
+
+/// Reject requests with a body larger than the runtime body limit
+/// A declared Content-Length is checked upfront, the streamed body is counted as it is read
+/// (a chunked body can't get past the limit either)
+/// The limit is read on each request, so a config reload applies right away
+pub async fn body_limit(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(limit) = limit(req.request()) else {
+        return Err(AppError::new(Errors::Server(ServerError::Internal)).into());
+    };
+
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<u64>().ok());
+
+    if length.is_some_and(|length| length > limit) {
+        return Err(too_large(limit));
+    }
+
+    let mut read = 0;
+    let payload = req.take_payload().map(move |chunk| {
+        let chunk = chunk?;
+        read += chunk.len() as u64;
+        match read > limit {
+            true => Err(PayloadError::Overflow),
+            false => Ok(chunk),
+        }
+    });
+    req.set_payload(Payload::from(payload.boxed_local()));
+
+    next.call(req).await
+}
+
+/// Error of a json body the `JsonConfig` couldn't extract
+/// A body over the runtime limit is too large, any other error is invalid json
+pub fn json_error(err: JsonPayloadError, req: &HttpRequest) -> Error {
+    match (err, limit(req)) {
+        (
+            JsonPayloadError::Overflow { .. }
+            | JsonPayloadError::OverflowKnownLength { .. }
+            | JsonPayloadError::Payload(PayloadError::Overflow),
+            Some(limit),
+        ) => too_large(limit),
+        _ => AppError::new(Errors::Client(ClientError::InvalidJson)).into(),
+    }
+}
+
+fn limit(req: &HttpRequest) -> Option<u64> {
+    req.app_data::<web::Data<SharedConfig>>()
+        .map(|runtime| runtime.get().body_limit())
+}
+
+fn too_large(limit: u64) -> Error {
+    AppError::new(Errors::Client(ClientError::PayloadTooLarge { limit })).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, middleware, test, App, HttpResponse};
+    use setup::config::runtime_config::RuntimeConfig;
+
+    use super::*;
+
+    async fn echo(body: web::Json<serde_json::Value>) -> HttpResponse {
+        HttpResponse::Ok().json(body.into_inner())
+    }
+
+    #[actix_web::test]
+    async fn test_body_over_runtime_limit_too_large() {
+        // Arrange
+        let mut config = RuntimeConfig::default();
+        config.options.body_limit_bytes = 32;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .app_data(web::JsonConfig::default().error_handler(json_error))
+                .wrap(middleware::from_fn(body_limit))
+                .route("/", web::post().to(echo)),
+        )
+        .await;
+        let request = |body: &str| {
+            test::TestRequest::post()
+                .insert_header((header::CONTENT_TYPE, "application/json"))
+                .set_payload(body.to_owned())
+                .to_request()
+        };
+        let large = format!(r#"{{"name": "{}"}}"#, "a".repeat(40));
+        // A chunked body declares no length
+        let mut undeclared = request(&large);
+        undeclared.headers_mut().remove(header::CONTENT_LENGTH);
+
+        // Act
+        let small = test::try_call_service(&app, request(r#"{"name": "Nala"}"#)).await;
+        let declared = test::try_call_service(&app, request(&large)).await;
+        let undeclared = test::try_call_service(&app, undeclared).await;
+
+        // Assert
+        assert_eq!(small.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            declared.err().unwrap().as_response_error().status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(undeclared.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_missing_runtime_config_internal_error() {
+        // Arrange
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(body_limit))
+                .route("/", web::post().to(echo)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .set_json(serde_json::json!({ "name": "Nala" }))
+            .to_request();
+
+        // Act
+        let resp = test::try_call_service(&app, req).await;
+
+        // Assert
+        assert_eq!(
+            resp.err().unwrap().as_response_error().status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error,
+};
+use common::{
+    metrics::{RequestTimer, UNMATCHED_ROUTE},
+    request_id,
+};
+
+/// Count requests and their latency by route template (e.g /api/cats/{cat_id}/) and status
+/// Wrapped inside the path normalizer, so the template is matched against the normalized path
+pub async fn metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let timer = RequestTimer::start();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_ROUTE.into());
+    request_id::record_route(&method, &route);
+
+    let res = next.call(req).await;
+
+    let status = match &res {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    timer.finish(&method, &route, status.as_u16());
+    res
+}
@@ -0,0 +1,54 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error,
+};
+use common::request_id::{self, RequestTrace};
+
+/// Serve the request within its trace (span and request id), the id being echoed in the `X-Request-Id` header
+/// Outermost middleware, so errors of the inner ones are rendered while the request id is still known
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let trace = RequestTrace::start(
+        |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        },
+        req.method().as_str(),
+        req.path(),
+    );
+    let header = (
+        HeaderName::from_static(request_id::HEADER),
+        HeaderValue::from_str(&trace.id).expect("Request ids are visible ascii"),
+    );
+
+    let res = trace
+        .scope(async {
+            next.call(req).await.map_err(|err| {
+                let mut response = err.error_response();
+                response
+                    .headers_mut()
+                    .insert(header.0.clone(), header.1.clone());
+                (err, response)
+            })
+        })
+        .await;
+
+    match res {
+        Ok(mut res) => {
+            trace.finish(res.status().as_u16());
+            res.headers_mut().insert(header.0, header.1);
+            Ok(res)
+        }
+        Err((err, response)) => {
+            trace.finish(response.status().as_u16());
+            Err(InternalError::from_response(err, response).into())
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    web, Error,
+};
+use setup::config::runtime_config::SharedConfig;
+
+/// Add the security headers of the runtime config to every response, errors included
+/// Headers already set by a route are kept, the docs UI gets its own CSP
+pub async fn security_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let runtime = req
+        .app_data::<web::Data<SharedConfig>>()
+        .map(|runtime| runtime.get())
+        .unwrap_or_default();
+    let docs_ui = req.path().starts_with(docs::DOCS_PATH);
+    let headers = runtime.security_headers.headers(docs_ui);
+
+    match next.call(req).await {
+        Ok(mut res) => {
+            insert_missing(res.headers_mut(), &headers);
+            Ok(res)
+        }
+        Err(err) => {
+            let mut response = err.error_response();
+            insert_missing(response.headers_mut(), &headers);
+            Err(InternalError::from_response(err, response).into())
+        }
+    }
+}
+
+fn insert_missing(map: &mut HeaderMap, headers: &[(&'static str, &str)]) {
+    for (name, value) in headers {
+        let name = HeaderName::from_static(name);
+        if let (false, Ok(value)) = (map.contains_key(&name), HeaderValue::from_str(value)) {
+            map.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::sync::RwLock;
+
+use common::{AuthPayload, ErrorPayload, InfoPayload, SuccessPayload};
+use domains::{
+    account::models::SecureAccount,
+    auth::models::{SignInAuth, SignUpAuth},
+    cat::models::{Cat, NewCat, ReplaceCat, UpdateCat},
+};
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+use crate::ApiError;
+
+/// Async client of the web service api
+/// Holds the auth token once signed in and sends it as a bearer token
+#[derive(Debug)]
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: RwLock<Option<String>>,
+}
+
+impl ApiClient {
+    /// `base_url` is the server root (e.g http://127.0.0.1:3000), without the `/api` scope
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            token: RwLock::new(None),
+        }
+    }
+
+    /// Token held after a successful sign in
+    pub fn token(&self) -> Option<String> {
+        self.token.read().unwrap().clone()
+    }
+
+    // Cats
+
+    pub async fn fetch_cats(&self) -> Result<Vec<Cat>, ApiError> {
+        self.send_data(self.request(Method::GET, "/cats/")).await
+    }
+
+    pub async fn fetch_cat(&self, cat_id: i32) -> Result<Cat, ApiError> {
+        self.send_data(self.request(Method::GET, &format!("/cats/{cat_id}/")))
+            .await
+    }
+
+    pub async fn add_cat(&self, new_cat: &NewCat) -> Result<Cat, ApiError> {
+        self.send_data(self.request(Method::POST, "/cats/").json(new_cat))
+            .await
+    }
+
+    pub async fn modify_cat(&self, cat_id: i32, update_cat: &UpdateCat) -> Result<Cat, ApiError> {
+        self.send_data(
+            self.request(Method::PATCH, &format!("/cats/{cat_id}/"))
+                .json(update_cat),
+        )
+        .await
+    }
+
+    pub async fn replace_cat(
+        &self,
+        cat_id: i32,
+        replace_cat: &ReplaceCat,
+    ) -> Result<Cat, ApiError> {
+        self.send_data(
+            self.request(Method::PUT, &format!("/cats/{cat_id}/"))
+                .json(replace_cat),
+        )
+        .await
+    }
+
+    /// Returns the server info message
+    pub async fn remove_cat(&self, cat_id: i32) -> Result<String, ApiError> {
+        let payload: InfoPayload = self
+            .send(self.request(Method::DELETE, &format!("/cats/{cat_id}/")))
+            .await?;
+        Ok(payload.message)
+    }
+
+    pub async fn fetch_trash(&self) -> Result<Vec<Cat>, ApiError> {
+        self.send_data(self.request(Method::GET, "/cats/trash/"))
+            .await
+    }
+
+    pub async fn restore_cat(&self, cat_id: i32) -> Result<Cat, ApiError> {
+        self.send_data(self.request(Method::POST, &format!("/cats/{cat_id}/restore/")))
+            .await
+    }
+
+    /// Admins only, returns the server info message
+    pub async fn purge_cat(&self, cat_id: i32) -> Result<String, ApiError> {
+        let payload: InfoPayload = self
+            .send(self.authorized_request(Method::DELETE, &format!("/cats/trash/{cat_id}/"))?)
+            .await?;
+        Ok(payload.message)
+    }
+
+    // Auth
+
+    pub async fn sign_up(&self, sign_up_auth: &SignUpAuth) -> Result<SecureAccount, ApiError> {
+        self.send_data(
+            self.request(Method::POST, "/auth/signup/")
+                .json(sign_up_auth),
+        )
+        .await
+    }
+
+    /// The returned token is kept for the next requests
+    pub async fn sign_in(&self, sign_in_auth: &SignInAuth) -> Result<String, ApiError> {
+        let payload: AuthPayload = self
+            .send(
+                self.request(Method::POST, "/auth/signin/")
+                    .json(sign_in_auth),
+            )
+            .await?;
+        let token = payload.token.ok_or(ApiError::MissingToken)?;
+        *self.token.write().unwrap() = Some(token.clone());
+        Ok(token)
+    }
+
+    pub async fn sign_out(&self) -> Result<(), ApiError> {
+        let _: AuthPayload = self
+            .send(self.authorized_request(Method::GET, "/auth/signout/")?)
+            .await?;
+        *self.token.write().unwrap() = None;
+        Ok(())
+    }
+
+    // Accounts
+
+    pub async fn fetch_me(&self) -> Result<SecureAccount, ApiError> {
+        self.send_data(self.authorized_request(Method::GET, "/accounts/me/")?)
+            .await
+    }
+
+    // Helpers
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}/api{}", self.base_url, path));
+        match self.token() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn authorized_request(&self, method: Method, path: &str) -> Result<RequestBuilder, ApiError> {
+        match self.token() {
+            Some(_) => Ok(self.request(method, path)),
+            None => Err(ApiError::NotSignedIn),
+        }
+    }
+
+    async fn send_data<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ApiError> {
+        let payload: SuccessPayload<T> = self.send(request).await?;
+        Ok(payload.data)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ApiError> {
+        let response = request.send().await?;
+        decode(response).await
+    }
+}
+
+/// Decode the expected payload or the `ErrorPayload` sent by the server, as a typed error
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, ApiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json::<T>().await?);
+    }
+
+    let errors = match response.json::<ErrorPayload<String>>().await {
+        Ok(payload) => payload.errors,
+        Err(_) => vec![status.canonical_reason().unwrap_or_default().to_owned()],
+    };
+    Err(ApiError::from_status(status.as_u16(), errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use domains::data_source::{DataSource, MockSource};
+    use setup::{
+        config::{
+            auth_config::AuthConfig, runtime_config::SharedConfig, server_config::ServerConfig,
+        },
+        listener::Listener,
+        shutdown::Shutdown,
+    };
+
+    use super::*;
+
+    /// Spawn an actix-ws instance on a random port and return its base url
+    fn spawn_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let auth_config =
+            AuthConfig::new("client_test_secret", "actix_web", &ServerConfig::default());
+        let server = actix_ws::server(
+            DataSource::mock(Some(MockSource::new())),
+            auth_config,
+            SharedConfig::default(),
+            Shutdown::default(),
+            vec![Listener::Tcp(listener)],
+            None,
+        )
+        .unwrap();
+        actix_web::rt::spawn(server);
+        format!("http://{addr}")
+    }
+
+    #[actix_web::test]
+    async fn test_cats_crud() {
+        // Arrange
+        let client = ApiClient::new(&spawn_server());
+
+        // Act
+        let cat = client
+            .add_cat(&NewCat {
+                name: "Nala".into(),
+                age: 2,
+                weight: None,
+            })
+            .await
+            .unwrap();
+        let cat_id: i32 = cat.id.0.parse().unwrap();
+        let modified = client
+            .modify_cat(
+                cat_id,
+                &UpdateCat {
+                    name: None,
+                    age: Some(3),
+                    weight: Some(4.2),
+                    version: None,
+                },
+            )
+            .await
+            .unwrap();
+        let replaced = client
+            .replace_cat(
+                cat_id,
+                &ReplaceCat {
+                    name: "Simba".into(),
+                    age: 4,
+                    weight: None,
+                    version: None,
+                },
+            )
+            .await
+            .unwrap();
+        let fetched = client.fetch_cat(cat_id).await.unwrap();
+        let message = client.remove_cat(cat_id).await.unwrap();
+        let cats = client.fetch_cats().await.unwrap();
+        let trash = client.fetch_trash().await.unwrap();
+        let restored = client.restore_cat(cat_id).await.unwrap();
+        let purge = client.purge_cat(cat_id).await;
+
+        // Assert
+        assert_eq!(modified.age, 3);
+        assert_eq!(replaced.name, "Simba");
+        assert_eq!(fetched.name, "Simba");
+        assert!(!message.is_empty());
+        assert!(cats.iter().all(|cat| cat.id.0 != cat_id.to_string()));
+        assert!(trash.iter().any(|cat| cat.id.0 == cat_id.to_string()));
+        assert!(restored.deletion_time.is_none());
+        assert!(matches!(purge, Err(ApiError::NotSignedIn)));
+    }
+
+    #[actix_web::test]
+    async fn test_error_payload_decoding() {
+        // Arrange
+        let client = ApiClient::new(&spawn_server());
+
+        // Act
+        let result = client.fetch_cat(999).await;
+
+        // Assert
+        match result {
+            Err(ApiError::NotFound { errors }) => {
+                assert_eq!(errors, vec!["Resource: cats/999 not found."]);
+            }
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_auth_flow() {
+        // Arrange
+        let client = ApiClient::new(&spawn_server());
+
+        // Act
+        let not_signed_in = client.fetch_me().await;
+        let account = client
+            .sign_up(&SignUpAuth {
+                email: "client@mail.com".into(),
+                password: "Pass:12345".into(),
+                confirmation: "Pass:12345".into(),
+            })
+            .await
+            .unwrap();
+        let duplicate = client
+            .sign_up(&SignUpAuth {
+                email: "client@mail.com".into(),
+                password: "Pass:12345".into(),
+                confirmation: "Pass:12345".into(),
+            })
+            .await;
+        let wrong_password = client
+            .sign_in(&SignInAuth {
+                email: "client@mail.com".into(),
+                password: "Pass:54321".into(),
+            })
+            .await;
+        client
+            .sign_in(&SignInAuth {
+                email: "client@mail.com".into(),
+                password: "Pass:12345".into(),
+            })
+            .await
+            .unwrap();
+        let me = client.fetch_me().await.unwrap();
+        client.sign_out().await.unwrap();
+
+        // Assert
+        assert!(matches!(not_signed_in, Err(ApiError::NotSignedIn)));
+        assert!(matches!(duplicate, Err(ApiError::Conflict { .. })));
+        assert!(matches!(wrong_password, Err(ApiError::BadRequest { .. })));
+        assert_eq!(me.id.0, account.id.0);
+        assert!(client.token().is_none());
+    }
+}
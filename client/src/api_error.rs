@@ -0,0 +1,112 @@
+use derive_more::Display;
+
+/// Errors returned by the `ApiClient`
+/// The error statuses the api documents have their own variant, carrying the `ErrorPayload` messages
+#[derive(Debug, Display)]
+pub enum ApiError {
+    /// 400: invalid json body, fields or credentials
+    #[display(fmt = "Bad request: {}", "errors.join(\" \")")]
+    BadRequest { errors: Vec<String> },
+    /// 401: missing, invalid or expired token
+    #[display(fmt = "Unauthorized: {}", "errors.join(\" \")")]
+    Unauthorized { errors: Vec<String> },
+    /// 403: the account isn't allowed to use the route
+    #[display(fmt = "Forbidden: {}", "errors.join(\" \")")]
+    Forbidden { errors: Vec<String> },
+    /// 404: unknown resource or route
+    #[display(fmt = "Not found: {}", "errors.join(\" \")")]
+    NotFound { errors: Vec<String> },
+    /// 409: the resource already exists or its version is stale
+    #[display(fmt = "Conflict: {}", "errors.join(\" \")")]
+    Conflict { errors: Vec<String> },
+    /// 412: the resource changed since its ETag was fetched
+    #[display(fmt = "Precondition failed: {}", "errors.join(\" \")")]
+    PreconditionFailed { errors: Vec<String> },
+    /// 413: the request body exceeds the server limit
+    #[display(fmt = "Payload too large: {}", "errors.join(\" \")")]
+    PayloadTooLarge { errors: Vec<String> },
+    /// 503: the server is shutting down or its database is unreachable
+    #[display(fmt = "Unavailable: {}", "errors.join(\" \")")]
+    Unavailable { errors: Vec<String> },
+    /// Any other error status answered by the api
+    #[display(fmt = "Api error ({}): {}", status, "errors.join(\" \")")]
+    Api { status: u16, errors: Vec<String> },
+    /// The request couldn't be sent or the response couldn't be decoded
+    #[display(fmt = "Http error: {}", _0)]
+    Http(reqwest::Error),
+    /// The route requires a token and the client isn't signed in
+    #[display(fmt = "No auth token held. Sign in first.")]
+    NotSignedIn,
+    /// The sign in succeeded but the response carries no token
+    #[display(fmt = "The sign in response carries no auth token.")]
+    MissingToken,
+}
+
+impl ApiError {
+    /// Error of an api response with the `status` error code
+    pub fn from_status(status: u16, errors: Vec<String>) -> Self {
+        match status {
+            400 => ApiError::BadRequest { errors },
+            401 => ApiError::Unauthorized { errors },
+            403 => ApiError::Forbidden { errors },
+            404 => ApiError::NotFound { errors },
+            409 => ApiError::Conflict { errors },
+            412 => ApiError::PreconditionFailed { errors },
+            413 => ApiError::PayloadTooLarge { errors },
+            503 => ApiError::Unavailable { errors },
+            status => ApiError::Api { status, errors },
+        }
+    }
+
+    /// Status code answered by the api, none when no response was decoded
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::BadRequest { .. } => Some(400),
+            ApiError::Unauthorized { .. } => Some(401),
+            ApiError::Forbidden { .. } => Some(403),
+            ApiError::NotFound { .. } => Some(404),
+            ApiError::Conflict { .. } => Some(409),
+            ApiError::PreconditionFailed { .. } => Some(412),
+            ApiError::PayloadTooLarge { .. } => Some(413),
+            ApiError::Unavailable { .. } => Some(503),
+            ApiError::Api { status, .. } => Some(*status),
+            ApiError::Http(err) => err.status().map(|status| status.as_u16()),
+            ApiError::NotSignedIn | ApiError::MissingToken => None,
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ApiError::Http(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        // Arrange
+        let errors = || vec!["error".to_owned()];
+
+        // Act
+        let not_found = ApiError::from_status(404, errors());
+        let precondition_failed = ApiError::from_status(412, errors());
+        let teapot = ApiError::from_status(418, errors());
+
+        // Assert
+        assert!(matches!(not_found, ApiError::NotFound { .. }));
+        assert!(matches!(
+            precondition_failed,
+            ApiError::PreconditionFailed { .. }
+        ));
+        assert!(matches!(teapot, ApiError::Api { status: 418, .. }));
+        assert_eq!(not_found.status(), Some(404));
+        assert_eq!(teapot.status(), Some(418));
+        assert_eq!(not_found.to_string(), "Not found: error");
+    }
+}
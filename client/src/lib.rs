@@ -0,0 +1,5 @@
+mod api_client;
+mod api_error;
+
+pub use api_client::*;
+pub use api_error::*;
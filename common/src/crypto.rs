@@ -3,12 +3,16 @@ use argon2::{
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
 
+use crate::metrics;
+
 pub fn hash_password(password: String) -> String {
     let salt = SaltString::generate(&mut OsRng);
-    Argon2::default()
-        .hash_password(password.as_bytes(), &salt)
-        .expect("Error while hashing password")
-        .to_string()
+    metrics::time_password_hash("hash", || {
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .expect("Error while hashing password")
+            .to_string()
+    })
 }
 
 pub fn verify_password(
@@ -16,6 +20,8 @@ pub fn verify_password(
     password_received: String,
 ) -> Result<(), argon2::password_hash::Error> {
     let parsed_hash = PasswordHash::new(password)?;
-    Argon2::default().verify_password(password_received.as_bytes(), &parsed_hash)?;
+    metrics::time_password_hash("verify", || {
+        Argon2::default().verify_password(password_received.as_bytes(), &parsed_hash)
+    })?;
     Ok(())
 }
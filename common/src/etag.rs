@@ -0,0 +1,58 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Header names, lowercased
+pub const ETAG: &str = "etag";
+pub const IF_NONE_MATCH: &str = "if-none-match";
+pub const IF_MATCH: &str = "if-match";
+
+/// Strong ETag of a representation, the SHA-256 of its JSON serialization (quoted)
+/// The mock and database sources serialize cats the same way, so they get the same tags
+pub fn strong<T: Serialize>(representation: &T) -> String {
+    let json = serde_json::to_vec(representation).expect("Representations serialize to json");
+    format!("\"{:x}\"", Sha256::digest(json))
+}
+
+/// Whether an `If-None-Match` header matches `etag`, the client copy being up to date (304)
+/// Weak comparison: `W/` prefixes are ignored, `*` matches any tag
+pub fn none_match_hit(header: &str, etag: &str) -> bool {
+    tags(header).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Whether an `If-Match` header allows modifying the resource tagged `etag` (412 otherwise)
+/// Strong comparison: weak tags never match, `*` matches any existing resource
+/// `etag` is None when the resource has no current representation
+pub fn match_hit(header: &str, etag: Option<&str>) -> bool {
+    etag.is_some_and(|etag| tags(header).any(|tag| tag == "*" || tag == etag))
+}
+
+/// Entity tags of a comma separated header value
+fn tags(header: &str) -> impl Iterator<Item = &str> {
+    header
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conditional_headers() {
+        // Arrange
+        let etag = strong(&serde_json::json!({ "name": "Nala" }));
+        let other = strong(&serde_json::json!({ "name": "Simba" }));
+
+        // Assert
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_eq!(etag, strong(&serde_json::json!({ "name": "Nala" })));
+        assert!(none_match_hit(&format!("{other}, W/{etag}"), &etag));
+        assert!(none_match_hit("*", &etag));
+        assert!(!none_match_hit(&other, &etag));
+        assert!(match_hit(&etag, Some(&etag)));
+        assert!(match_hit("*", Some(&etag)));
+        assert!(!match_hit(&format!("W/{etag}"), Some(&etag)));
+        assert!(!match_hit("*", None));
+    }
+}
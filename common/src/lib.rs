@@ -1,24 +1,41 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 pub mod crypto;
+pub mod etag;
+pub mod metrics;
+pub mod request_id;
 pub mod validation;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SuccessPayload<T> {
     pub data: T,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ErrorPayload<T> {
     pub errors: Vec<T>,
+    /// Id of the failed request, same as the `X-Request-Id` response header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl<T> ErrorPayload<T> {
+    /// Errors of the request being served
+    pub fn new(errors: Vec<T>) -> Self {
+        Self {
+            errors,
+            request_id: request_id::current(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AuthPayload {
     pub token: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct InfoPayload {
     pub message: String,
 }
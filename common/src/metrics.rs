@@ -0,0 +1,278 @@
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+/// Route label of requests matching no known route, keeps the label cardinality bounded
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Metrics of the web service, exposed in the Prometheus text format by `render`
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    http_requests_in_flight: IntGauge,
+    app_errors: IntCounterVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_acquire_duration: Histogram,
+    password_hash_duration: HistogramVec,
+    auth_attempts: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+    let registry = Registry::new();
+    let register = |collector: Box<dyn prometheus::core::Collector>| {
+        registry
+            .register(collector)
+            .expect("Metrics are registered once");
+    };
+
+    let http_requests = IntCounterVec::new(
+        Opts::new("http_requests_total", "HTTP requests served"),
+        &["method", "route", "status"],
+    )
+    .expect("Valid metric");
+    let http_request_duration = HistogramVec::new(
+        HistogramOpts::new(
+            "http_request_duration_seconds",
+            "HTTP request latency, until the response is ready",
+        ),
+        &["method", "route", "status"],
+    )
+    .expect("Valid metric");
+    let http_requests_in_flight = IntGauge::new(
+        "http_requests_in_flight",
+        "HTTP requests currently being served",
+    )
+    .expect("Valid metric");
+    let app_errors = IntCounterVec::new(
+        Opts::new("app_errors_total", "Errors returned to clients"),
+        &["kind", "variant"],
+    )
+    .expect("Valid metric");
+    let db_pool_connections = IntGaugeVec::new(
+        Opts::new(
+            "db_pool_connections",
+            "Database pool connections (size, idle and max), sampled on scrape",
+        ),
+        &["state"],
+    )
+    .expect("Valid metric");
+    let db_pool_acquire_duration = Histogram::with_opts(HistogramOpts::new(
+        "db_pool_acquire_duration_seconds",
+        "Wait of the queries for a database pool connection",
+    ))
+    .expect("Valid metric");
+    let password_hash_duration = HistogramVec::new(
+        HistogramOpts::new(
+            "password_hash_duration_seconds",
+            "Argon2 password hashing and verification duration",
+        )
+        .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+        &["operation"],
+    )
+    .expect("Valid metric");
+    let auth_attempts = IntCounterVec::new(
+        Opts::new("auth_attempts_total", "Sign up, sign in and token checks"),
+        &["action", "outcome"],
+    )
+    .expect("Valid metric");
+
+    register(Box::new(http_requests.clone()));
+    register(Box::new(http_request_duration.clone()));
+    register(Box::new(http_requests_in_flight.clone()));
+    register(Box::new(app_errors.clone()));
+    register(Box::new(db_pool_connections.clone()));
+    register(Box::new(db_pool_acquire_duration.clone()));
+    register(Box::new(password_hash_duration.clone()));
+    register(Box::new(auth_attempts.clone()));
+
+    Metrics {
+        registry,
+        http_requests,
+        http_request_duration,
+        http_requests_in_flight,
+        app_errors,
+        db_pool_connections,
+        db_pool_acquire_duration,
+        password_hash_duration,
+        auth_attempts,
+    }
+});
+
+/// Timer of a request being served, counted in flight until dropped
+/// Dropping it without `finish` (e.g client gone) only releases the in-flight count
+pub struct RequestTimer {
+    started: Instant,
+}
+
+impl Default for RequestTimer {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl RequestTimer {
+    pub fn start() -> Self {
+        METRICS.http_requests_in_flight.inc();
+        Self {
+            started: Instant::now(),
+        }
+    }
+
+    /// Count the request and its latency under its route template (e.g /api/cats/{cat_id}/)
+    pub fn finish(self, method: &str, route: &str, status: u16) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        METRICS.http_requests.with_label_values(&labels).inc();
+        METRICS
+            .http_request_duration
+            .with_label_values(&labels)
+            .observe(self.started.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for RequestTimer {
+    fn drop(&mut self) {
+        METRICS.http_requests_in_flight.dec();
+    }
+}
+
+/// Count an error returned to a client, `kind` being client, server or config
+pub fn record_error(kind: &str, variant: &str) {
+    METRICS.app_errors.with_label_values(&[kind, variant]).inc();
+}
+
+pub fn record_pool(size: u32, idle: u32, max: u32) {
+    for (state, value) in [("size", size), ("idle", idle), ("max", max)] {
+        METRICS
+            .db_pool_connections
+            .with_label_values(&[state])
+            .set(value.into());
+    }
+}
+
+/// Time a query or transaction waited for a pool connection
+pub fn record_pool_acquire(wait: Duration) {
+    METRICS.db_pool_acquire_duration.observe(wait.as_secs_f64());
+}
+
+/// Time a password hashing `operation` (hash or verify)
+pub fn time_password_hash<T>(operation: &str, hash: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = hash();
+    METRICS
+        .password_hash_duration
+        .with_label_values(&[operation])
+        .observe(started.elapsed().as_secs_f64());
+    result
+}
+
+/// Count an auth `action` (sign_up, sign_in or token) by outcome
+pub fn record_auth(action: &str, success: bool) {
+    let outcome = match success {
+        true => "success",
+        false => "failure",
+    };
+    METRICS
+        .auth_attempts
+        .with_label_values(&[action, outcome])
+        .inc();
+}
+
+/// Route template of `path` among `templates` (e.g /api/cats/12/ matches /api/cats/{cat_id}/)
+/// Trailing slashes are ignored, `UNMATCHED_ROUTE` when no template matches
+/// The template with the most literal segments wins (e.g /api/cats/trash/ over /api/cats/{cat_id}/)
+pub fn route_template<'a>(path: &str, templates: &'a [String]) -> &'a str {
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    templates
+        .iter()
+        .filter_map(|template| {
+            let template_segments: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+            let matches = template_segments.len() == segments.len()
+                && template_segments
+                    .iter()
+                    .zip(&segments)
+                    .all(|(template, segment)| template == segment || is_param(template));
+            let literals = template_segments
+                .iter()
+                .filter(|template| !is_param(template))
+                .count();
+            matches.then_some((literals, template))
+        })
+        .max_by_key(|(literals, _)| *literals)
+        .map_or(UNMATCHED_ROUTE, |(_, template)| template.as_str())
+}
+
+fn is_param(segment: &str) -> bool {
+    segment.starts_with('{') && segment.ends_with('}')
+}
+
+/// Every metric in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&METRICS.registry.gather(), &mut buffer)
+        .expect("Metrics are encodable");
+    String::from_utf8(buffer).expect("Metrics are utf-8")
+}
+
+/// Content type of `render`
+pub fn content_type() -> &'static str {
+    prometheus::TEXT_FORMAT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_template() {
+        // Arrange
+        let templates = [
+            "/api/cats/".into(),
+            "/api/cats/{cat_id}/".into(),
+            "/api/cats/{cat_id}/restore/".into(),
+            "/api/cats/trash/".into(),
+        ];
+
+        // Assert
+        assert_eq!(
+            route_template("/api/cats/12/", &templates),
+            "/api/cats/{cat_id}/"
+        );
+        assert_eq!(route_template("/api/cats", &templates), "/api/cats/");
+        assert_eq!(
+            route_template("/api/cats/trash/", &templates),
+            "/api/cats/trash/"
+        );
+        assert_eq!(
+            route_template("/api/cats/12/restore/", &templates),
+            "/api/cats/{cat_id}/restore/"
+        );
+        assert_eq!(route_template("/api/dogs/12/", &templates), UNMATCHED_ROUTE);
+    }
+
+    /// The registry is shared by the whole test binary, the labels are unique to this test
+    #[test]
+    fn test_render_recorded_metrics() {
+        // Act
+        RequestTimer::start().finish("GET", "/test/render/{id}/", 200);
+        record_auth("test_render", false);
+
+        // Assert
+        let metrics = render();
+        assert!(metrics.contains(
+            r#"http_requests_total{method="GET",route="/test/render/{id}/",status="200"} 1"#
+        ));
+        assert!(
+            metrics.contains(r#"auth_attempts_total{action="test_render",outcome="failure"} 1"#)
+        );
+        assert!(metrics.contains("# TYPE http_requests_in_flight gauge"));
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashMap, future::Future, time::Instant};
+
+use opentelemetry::{global, trace::TraceContextExt};
+use tracing::{field, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Header carrying the request id, propagated from the client (or a proxy) and echoed in the response
+pub const HEADER: &str = "x-request-id";
+
+/// Longest request id accepted from a client
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request being served, None outside of a `RequestTrace` scope
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Id sent by the client when usable (visible ascii, 128 chars max), a new uuid otherwise
+pub fn from_header(header: Option<&str>) -> String {
+    match header {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_LEN
+                && id.bytes().all(|b| b.is_ascii_graphic()) =>
+        {
+            id.to_owned()
+        }
+        _ => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+/// Record the route template (e.g /api/cats/{cat_id}/) on the request span, also naming the exported span
+/// To be called while the request is served (see `RequestTrace::scope`)
+pub fn record_route(method: &str, route: &str) {
+    let span = Span::current();
+    span.record("http.route", route);
+    // The exported span is already started, `otel.name` would only be read at its creation
+    span.context()
+        .span()
+        .update_name(format!("{method} {route}"));
+}
+
+/// Record the authenticated account on the request span
+pub fn record_account(account_id: &str) {
+    Span::current().record("account.id", account_id);
+}
+
+/// Trace of a request: its id, and the span its controllers log into
+pub struct RequestTrace {
+    pub id: String,
+    span: Span,
+    started: Instant,
+}
+
+impl RequestTrace {
+    /// `header` reads the request headers: `X-Request-Id`, and the W3C `traceparent`
+    /// continuing the trace of the caller (when propagation is set up, see `setup::telemetry`)
+    pub fn start<'a>(header: impl Fn(&str) -> Option<&'a str>, method: &str, path: &str) -> Self {
+        let id = from_header(header(HEADER));
+        let span = tracing::info_span!(
+            "request",
+            otel.name = %format!("{method} {path}"),
+            otel.kind = "server",
+            otel.status_code = field::Empty,
+            request_id = %id,
+            method,
+            path,
+            http.route = field::Empty,
+            http.response.status_code = field::Empty,
+            account.id = field::Empty,
+        );
+
+        let carrier: HashMap<String, String> = global::get_text_map_propagator(|propagator| {
+            propagator
+                .fields()
+                .filter_map(|field| Some((field.to_owned(), header(field)?.to_owned())))
+                .collect()
+        });
+        let parent = global::get_text_map_propagator(|propagator| propagator.extract(&carrier));
+        let _ = span.set_parent(parent);
+
+        Self {
+            id,
+            span,
+            started: Instant::now(),
+        }
+    }
+
+    /// Serve the request within its span, with its id as the `current` one
+    pub fn scope<F: Future>(&self, request: F) -> impl Future<Output = F::Output> {
+        REQUEST_ID
+            .scope(self.id.clone(), request)
+            .instrument(self.span.clone())
+    }
+
+    /// Log the response status and latency
+    pub fn finish(&self, status: u16) {
+        let latency_ms = self.started.elapsed().as_millis() as u64;
+        // Integer attribute once exported, u64 values would be exported as strings
+        self.span
+            .record("http.response.status_code", i64::from(status));
+        if status >= 500 {
+            self.span.record("otel.status_code", "error");
+        }
+        self.span.in_scope(|| match status {
+            500.. => tracing::error!(status, latency_ms, "request failed"),
+            _ => tracing::info!(status, latency_ms, "request served"),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_id_scope() {
+        // Arrange
+        let trace = RequestTrace::start(
+            |name| (name == HEADER).then_some("abc-123"),
+            "GET",
+            "/api/cats/",
+        );
+
+        // Act
+        let id = trace.scope(async { current() }).await;
+
+        // Assert
+        assert_eq!(id.as_deref(), Some("abc-123"));
+        assert_eq!(current(), None);
+        assert_ne!(from_header(Some("bad id\n")), "bad id\n");
+        assert_eq!(from_header(None).len(), 36);
+    }
+}
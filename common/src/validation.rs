@@ -27,7 +27,7 @@ fn check_password_complexity(password: &str) -> bool {
         }
     }
 
-    return has_lowercase && has_uppercase && has_number && has_punctuation;
+    has_lowercase && has_uppercase && has_number && has_punctuation
 }
 
 /// Validator for password
@@ -35,7 +35,7 @@ fn check_password_complexity(password: &str) -> bool {
 pub fn validate_password(password: &str) -> Result<(), ValidationError> {
     match check_password_complexity(password) {
         true => Ok(()),
-        false => return Err(ValidationError::new("password_complexity")),
+        false => Err(ValidationError::new("password_complexity")),
     }
 }
 
@@ -45,14 +45,14 @@ mod tests {
 
     #[test]
     fn test_check_password_complexity() {
-        assert_eq!(check_password_complexity("Aaδ1:M7"), false);
-        assert_eq!(check_password_complexity("Aaδ1:M78"), false);
-        assert_eq!(check_password_complexity("AaB1M78"), false);
-        assert_eq!(check_password_complexity("Aab1M78o"), false);
-        assert_eq!(check_password_complexity("aab1m78o"), false);
-        assert_eq!(check_password_complexity("aAb1m7:"), false);
-        assert_eq!(check_password_complexity("aAb1m7:/"), true);
-        assert_eq!(check_password_complexity("aAb1m7:|"), true);
-        assert_eq!(check_password_complexity("aAb1m7:|u?7"), true);
+        assert!(!check_password_complexity("Aaδ1:M7"));
+        assert!(!check_password_complexity("Aaδ1:M78"));
+        assert!(!check_password_complexity("AaB1M78"));
+        assert!(!check_password_complexity("Aab1M78o"));
+        assert!(!check_password_complexity("aab1m78o"));
+        assert!(!check_password_complexity("aAb1m7:"));
+        assert!(check_password_complexity("aAb1m7:/"));
+        assert!(check_password_complexity("aAb1m7:|"));
+        assert!(check_password_complexity("aAb1m7:|u?7"));
     }
 }
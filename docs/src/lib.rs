@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use utoipa::{
+    openapi::{
+        path::{Operation, PathItem},
+        security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme},
+    },
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::{Config, SwaggerFile};
+
+pub mod paths;
+
+/// Route serving the generated OpenAPI spec
+pub const OPENAPI_PATH: &str = "/api/openapi.json/";
+/// Route serving the embedded Swagger UI
+pub const DOCS_PATH: &str = "/api/docs/";
+
+/// OpenAPI 3 description of the whole web service, as served by the actix server
+/// Schemas are derived from the `domains` and `common` models,
+/// paths mirror the route configs of the web server adapters
+/// Servers routing only part of the service serve it through `routed_spec`
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "rust-webservice-study",
+        description = "Component structured web service, independent from the chosen web server"
+    ),
+    paths(
+        paths::base::check_health,
+        paths::base::check_liveness,
+        paths::base::check_readiness,
+        paths::auth::sign_up,
+        paths::auth::sign_in,
+        paths::auth::sign_out,
+        paths::account::fetch_auth_account,
+        paths::audit::fetch_all,
+        paths::cat::fetch_all,
+        paths::cat::fetch_one,
+        paths::cat::add_one,
+        paths::cat::modify_one,
+        paths::cat::replace_one,
+        paths::cat::remove_one,
+        paths::cat::fetch_trash,
+        paths::cat::restore_one,
+        paths::cat::purge_one,
+    ),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "base", description = "Server instance information"),
+        (name = "auth", description = "Authentication"),
+        (name = "accounts", description = "Accounts management"),
+        (name = "audit", description = "Audit log of the mutations, admins only"),
+        (name = "cats", description = "Cats management"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Auth token can be sent either as a bearer token or through the `token` cookie set on sign in
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+        components.add_security_scheme(
+            "cookie_auth",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("token"))),
+        );
+    }
+}
+
+/// `ApiDoc` restricted to the paths of `route_templates`, for servers routing only part of the service
+/// Tags and security schemes no remaining operation uses are dropped along
+pub fn routed_spec<S: AsRef<str>>(route_templates: &[S]) -> utoipa::openapi::OpenApi {
+    let mut spec = ApiDoc::openapi();
+    spec.paths.paths.retain(|path, _| {
+        route_templates
+            .iter()
+            .any(|template| template.as_ref() == path)
+    });
+
+    let operations: Vec<&Operation> = spec.paths.paths.values().flat_map(operations).collect();
+    let used_tags: Vec<&String> = operations
+        .iter()
+        .flat_map(|operation| operation.tags.iter().flatten())
+        .collect();
+    let secured = operations
+        .iter()
+        .any(|operation| operation.security.is_some());
+    let tags = spec.tags.take().map(|tags| {
+        tags.into_iter()
+            .filter(|tag| used_tags.contains(&&tag.name))
+            .collect()
+    });
+    spec.tags = tags;
+    if !secured {
+        if let Some(components) = spec.components.as_mut() {
+            components.security_schemes.clear();
+        }
+    }
+
+    spec
+}
+
+/// Documented operations of a path
+fn operations(item: &PathItem) -> impl Iterator<Item = &Operation> {
+    [
+        &item.get,
+        &item.put,
+        &item.post,
+        &item.delete,
+        &item.options,
+        &item.head,
+        &item.patch,
+        &item.trace,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Serialized spec served at `OPENAPI_PATH`
+pub fn openapi_json(spec: &utoipa::openapi::OpenApi) -> String {
+    spec.to_pretty_json()
+        .expect("Error serializing OpenAPI spec")
+}
+
+/// Swagger UI asset matching the path requested under `DOCS_PATH`
+/// Returns `None` when the asset doesn't exist
+pub fn swagger_ui_file(tail: &str) -> Option<SwaggerFile<'static>> {
+    let config = Arc::new(Config::new([OPENAPI_PATH]));
+    utoipa_swagger_ui::serve(tail.trim_matches('/'), config)
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_paths() {
+        let spec = ApiDoc::openapi();
+        let paths: Vec<&String> = spec.paths.paths.keys().collect();
+
+        assert!(paths.contains(&&"/api/health/".to_string()));
+        assert!(paths.contains(&&"/api/health/ready/".to_string()));
+        assert!(paths.contains(&&"/api/auth/signup/".to_string()));
+        assert!(paths.contains(&&"/api/accounts/me/".to_string()));
+        assert!(paths.contains(&&"/api/audit/".to_string()));
+        assert!(paths.contains(&&"/api/cats/{cat_id}/".to_string()));
+        assert!(paths.contains(&&"/api/cats/trash/".to_string()));
+    }
+
+    #[test]
+    fn test_routed_spec_paths() {
+        let spec = routed_spec(&["/api/health/ready/", "/api/cats/", "/api/cats/trash/"]);
+        let paths: Vec<&String> = spec.paths.paths.keys().collect();
+
+        assert_eq!(
+            paths,
+            ["/api/cats/", "/api/cats/trash/", "/api/health/ready/"]
+        );
+        let tags: Vec<String> = spec.tags.unwrap().into_iter().map(|tag| tag.name).collect();
+        assert_eq!(tags, ["base", "cats"]);
+        assert!(spec.components.unwrap().security_schemes.is_empty());
+    }
+
+    #[test]
+    fn test_routed_spec_keeps_used_security() {
+        let spec = routed_spec(&["/api/cats/trash/{cat_id}/"]);
+
+        assert!(spec
+            .components
+            .unwrap()
+            .security_schemes
+            .contains_key("bearer_auth"));
+    }
+
+    #[test]
+    fn test_spec_schemas() {
+        let spec: serde_json::Value =
+            serde_json::from_str(&openapi_json(&ApiDoc::openapi())).unwrap();
+        let schemas = &spec["components"]["schemas"];
+
+        assert!(schemas.get("Cat").is_some());
+        assert!(schemas.get("SecureAccount").is_some());
+        assert!(schemas.get("SignUpAuth").is_some());
+        assert!(spec["components"]["securitySchemes"]
+            .get("bearer_auth")
+            .is_some());
+    }
+
+    #[test]
+    fn test_swagger_ui_file() {
+        let index = swagger_ui_file("").unwrap();
+        assert_eq!(index.content_type, "text/html");
+
+        let initializer = swagger_ui_file("swagger-initializer.js").unwrap();
+        assert!(String::from_utf8_lossy(&initializer.bytes).contains(OPENAPI_PATH));
+
+        assert!(swagger_ui_file("unknown.js").is_none());
+    }
+}
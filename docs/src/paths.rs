@@ -0,0 +1,8 @@
+//! Path definitions of the api
+//! One module per route config, the functions only carry the documentation
+
+pub mod account;
+pub mod audit;
+pub mod auth;
+pub mod base;
+pub mod cat;
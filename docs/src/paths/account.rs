@@ -0,0 +1,16 @@
+use common::{ErrorPayload, SuccessPayload};
+use domains::account::models::SecureAccount;
+
+/// Fetch the authenticated account
+#[utoipa::path(
+    get,
+    path = "/api/accounts/me/",
+    tag = "accounts",
+    responses(
+        (status = 200, description = "Authenticated account", body = SuccessPayload<SecureAccount>),
+        (status = 401, description = "Missing or invalid token", body = ErrorPayload<String>),
+        (status = 404, description = "Account not found", body = ErrorPayload<String>)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
+pub fn fetch_auth_account() {}
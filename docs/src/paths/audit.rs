@@ -0,0 +1,18 @@
+use common::{ErrorPayload, SuccessPayload};
+use domains::audit::models::{AuditEntry, AuditFilter};
+
+/// Query the audit log, newest entries first
+#[utoipa::path(
+    get,
+    path = "/api/audit/",
+    tag = "audit",
+    params(AuditFilter),
+    responses(
+        (status = 200, description = "Matching audit entries", body = SuccessPayload<Vec<AuditEntry>>),
+        (status = 400, description = "Invalid filters", body = ErrorPayload<String>),
+        (status = 401, description = "Missing or invalid token", body = ErrorPayload<String>),
+        (status = 403, description = "Not an admin account", body = ErrorPayload<String>)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
+pub fn fetch_all() {}
@@ -0,0 +1,47 @@
+use common::{AuthPayload, ErrorPayload, SuccessPayload};
+use domains::{
+    account::models::SecureAccount,
+    auth::models::{SignInAuth, SignUpAuth},
+};
+
+/// Create a new account
+#[utoipa::path(
+    post,
+    path = "/api/auth/signup/",
+    tag = "auth",
+    request_body = SignUpAuth,
+    responses(
+        (status = 200, description = "Account created", body = SuccessPayload<SecureAccount>),
+        (status = 400, description = "Invalid fields or json body", body = ErrorPayload<String>),
+        (status = 403, description = "Sign up closed, the `signup` feature is disabled", body = ErrorPayload<String>),
+        (status = 409, description = "Account already existing for that email", body = ErrorPayload<String>)
+    )
+)]
+pub fn sign_up() {}
+
+/// Sign in with email and password
+/// The token is returned in the payload and set in the `token` cookie
+#[utoipa::path(
+    post,
+    path = "/api/auth/signin/",
+    tag = "auth",
+    request_body = SignInAuth,
+    responses(
+        (status = 200, description = "Signed in", body = AuthPayload),
+        (status = 400, description = "Invalid credentials or json body", body = ErrorPayload<String>)
+    )
+)]
+pub fn sign_in() {}
+
+/// Sign out, the `token` cookie is removed
+#[utoipa::path(
+    get,
+    path = "/api/auth/signout/",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Signed out", body = AuthPayload),
+        (status = 401, description = "Missing or invalid token", body = ErrorPayload<String>)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
+pub fn sign_out() {}
@@ -0,0 +1,37 @@
+use common::{ErrorPayload, InfoPayload};
+use domains::health::models::Readiness;
+
+/// Check that the server instance is running
+#[utoipa::path(
+    get,
+    path = "/api/health/",
+    tag = "base",
+    responses(
+        (status = 200, description = "Server instance is running", body = InfoPayload),
+        (status = 503, description = "Database unreachable (degraded mode)", body = ErrorPayload<String>)
+    )
+)]
+pub fn check_health() {}
+
+/// Liveness probe, up as long as the process answers
+#[utoipa::path(
+    get,
+    path = "/api/health/live/",
+    tag = "base",
+    responses(
+        (status = 200, description = "Server process is alive", body = InfoPayload)
+    )
+)]
+pub fn check_liveness() {}
+
+/// Readiness probe: database check (`SELECT 1`), pool saturation, migration status and build info
+#[utoipa::path(
+    get,
+    path = "/api/health/ready/",
+    tag = "base",
+    responses(
+        (status = 200, description = "Server is ready to serve requests", body = Readiness),
+        (status = 503, description = "Shutting down, or a dependency check failed", body = Readiness)
+    )
+)]
+pub fn check_readiness() {}
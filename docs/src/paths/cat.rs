@@ -0,0 +1,156 @@
+use common::{ErrorPayload, InfoPayload, SuccessPayload};
+use domains::cat::models::{Cat, NewCat, ReplaceCat, UpdateCat};
+
+/// Fetch all cats
+#[utoipa::path(
+    get,
+    path = "/api/cats/",
+    tag = "cats",
+    params(("If-None-Match" = Option<String>, Header, description = "ETag of the client copy")),
+    responses(
+        (status = 200, description = "All the cats", body = SuccessPayload<Vec<Cat>>,
+            headers(("ETag" = String, description = "Strong tag of the cat list"))),
+        (status = 304, description = "Client copy up to date"),
+        (status = 500, description = "Internal error", body = ErrorPayload<String>)
+    )
+)]
+pub fn fetch_all() {}
+
+/// Fetch one cat
+#[utoipa::path(
+    get,
+    path = "/api/cats/{cat_id}/",
+    tag = "cats",
+    params(
+        ("cat_id" = i32, Path, description = "Cat id"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the client copy")
+    ),
+    responses(
+        (status = 200, description = "Cat found", body = SuccessPayload<Cat>,
+            headers(("ETag" = String, description = "Strong tag of the cat"))),
+        (status = 304, description = "Client copy up to date"),
+        (status = 404, description = "Cat not found", body = ErrorPayload<String>)
+    )
+)]
+pub fn fetch_one() {}
+
+/// Add new cat
+#[utoipa::path(
+    post,
+    path = "/api/cats/",
+    tag = "cats",
+    request_body = NewCat,
+    responses(
+        (status = 200, description = "Cat created", body = SuccessPayload<Cat>),
+        (status = 400, description = "Invalid json body", body = ErrorPayload<String>)
+    )
+)]
+pub fn add_one() {}
+
+/// Modify existing cat
+/// Only the provided fields are updated
+#[utoipa::path(
+    patch,
+    path = "/api/cats/{cat_id}/",
+    tag = "cats",
+    params(
+        ("cat_id" = i32, Path, description = "Cat id"),
+        ("If-Match" = Option<String>, Header, description = "Only apply when the cat still has this ETag")
+    ),
+    request_body = UpdateCat,
+    responses(
+        (status = 200, description = "Cat modified", body = SuccessPayload<Cat>,
+            headers(("ETag" = String, description = "Strong tag of the modified cat"))),
+        (status = 400, description = "Invalid json body", body = ErrorPayload<String>),
+        (status = 404, description = "Cat not found", body = ErrorPayload<String>),
+        (status = 409, description = "Cat version is stale", body = ErrorPayload<String>),
+        (status = 412, description = "Cat changed since fetched, or while being written", body = ErrorPayload<String>)
+    )
+)]
+pub fn modify_one() {}
+
+/// Replace existing cat
+#[utoipa::path(
+    put,
+    path = "/api/cats/{cat_id}/",
+    tag = "cats",
+    params(
+        ("cat_id" = i32, Path, description = "Cat id"),
+        ("If-Match" = Option<String>, Header, description = "Only apply when the cat still has this ETag")
+    ),
+    request_body = ReplaceCat,
+    responses(
+        (status = 200, description = "Cat replaced", body = SuccessPayload<Cat>,
+            headers(("ETag" = String, description = "Strong tag of the replaced cat"))),
+        (status = 400, description = "Invalid json body", body = ErrorPayload<String>),
+        (status = 404, description = "Cat not found", body = ErrorPayload<String>),
+        (status = 409, description = "Cat version is stale", body = ErrorPayload<String>),
+        (status = 412, description = "Cat changed since fetched, or while being written", body = ErrorPayload<String>)
+    )
+)]
+pub fn replace_one() {}
+
+/// Move existing cat to the trash
+#[utoipa::path(
+    delete,
+    path = "/api/cats/{cat_id}/",
+    tag = "cats",
+    params(
+        ("cat_id" = i32, Path, description = "Cat id"),
+        ("If-Match" = Option<String>, Header, description = "Only apply when the cat still has this ETag")
+    ),
+    responses(
+        (status = 200, description = "Cat moved to the trash", body = InfoPayload),
+        (status = 404, description = "Cat not found", body = ErrorPayload<String>),
+        (status = 409, description = "Cat changed while being deleted without If-Match", body = ErrorPayload<String>),
+        (status = 412, description = "Cat changed since fetched, or while being written", body = ErrorPayload<String>)
+    )
+)]
+pub fn remove_one() {}
+
+/// Fetch the trashed cats, most recently deleted first
+#[utoipa::path(
+    get,
+    path = "/api/cats/trash/",
+    tag = "cats",
+    responses(
+        (status = 200, description = "Trashed cats", body = SuccessPayload<Vec<Cat>>)
+    )
+)]
+pub fn fetch_trash() {}
+
+/// Restore a trashed cat
+#[utoipa::path(
+    post,
+    path = "/api/cats/{cat_id}/restore/",
+    tag = "cats",
+    params(
+        ("cat_id" = i32, Path, description = "Cat id")
+    ),
+    responses(
+        (status = 200, description = "Cat restored", body = SuccessPayload<Cat>,
+            headers(("ETag" = String, description = "Strong tag of the restored cat"))),
+        (status = 404, description = "Cat not in the trash", body = ErrorPayload<String>),
+        (status = 409, description = "Cat changed meanwhile", body = ErrorPayload<String>)
+    )
+)]
+pub fn restore_one() {}
+
+/// Delete a trashed cat for good (admins only)
+#[utoipa::path(
+    delete,
+    path = "/api/cats/trash/{cat_id}/",
+    tag = "cats",
+    params(
+        ("cat_id" = i32, Path, description = "Cat id")
+    ),
+    responses(
+        (status = 200, description = "Cat purged", body = InfoPayload),
+        (status = 401, description = "Missing or invalid token", body = ErrorPayload<String>),
+        (status = 403, description = "Not an admin account", body = ErrorPayload<String>),
+        (status = 404, description = "Cat not in the trash", body = ErrorPayload<String>),
+        (status = 409, description = "Cat changed meanwhile", body = ErrorPayload<String>)
+    ),
+    security(("bearer_auth" = []), ("cookie_auth" = []))
+)]
+pub fn purge_one() {}
@@ -1 +1,2 @@
--- None
+ALTER TABLE cats ALTER COLUMN created_on TYPE TIMESTAMP USING created_on AT TIME ZONE 'UTC';
+ALTER TABLE accounts ALTER COLUMN created_on TYPE TIMESTAMP USING created_on AT TIME ZONE 'UTC';
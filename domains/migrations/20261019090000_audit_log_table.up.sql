@@ -0,0 +1,15 @@
+-- No foreign key on the actor: entries outlive the deleted accounts
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    actor UUID,
+    action VARCHAR(50) NOT NULL,
+    resource VARCHAR(50) NOT NULL,
+    resource_id VARCHAR(255) NOT NULL,
+    diff JSONB NOT NULL DEFAULT '{}',
+    request_id VARCHAR(128),
+    ip VARCHAR(45),
+    created_on TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_resource_idx ON audit_log (resource, resource_id);
+CREATE INDEX IF NOT EXISTS audit_log_created_on_idx ON audit_log (created_on);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS cats_deleted_on_idx;
+ALTER TABLE cats DROP COLUMN IF EXISTS deleted_on;
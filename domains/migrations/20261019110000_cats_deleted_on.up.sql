@@ -0,0 +1,4 @@
+-- Trashed cats keep their row until purged, NULL while in use
+ALTER TABLE cats ADD COLUMN IF NOT EXISTS deleted_on TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS cats_deleted_on_idx ON cats (deleted_on) WHERE deleted_on IS NOT NULL;
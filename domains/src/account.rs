@@ -1,2 +1,3 @@
+pub mod controller_db;
 pub mod controller_mock;
 pub mod models;
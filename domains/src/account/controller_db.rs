@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use errors::{AppError, ClientError, Errors};
+use sqlx::{Connection, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, models::AuditContext},
+    data_source::DbSource,
+};
+
+use super::models::{role_change, Account, AccountId, NewAccount, UpdateAccount};
+
+/// Row of the accounts table, every query maps it to the model
+struct AccountRow {
+    id: Uuid,
+    email: String,
+    password: String,
+    verified: bool,
+    role: String,
+    created_on: DateTime<Utc>,
+    updated_on: Option<DateTime<Utc>>,
+}
+
+impl From<AccountRow> for Account {
+    fn from(row: AccountRow) -> Self {
+        Account {
+            id: AccountId(row.id),
+            email: row.email,
+            password: row.password,
+            role: row.role,
+            verified: row.verified,
+            creation_time: row.created_on,
+            last_modification_time: row.updated_on,
+        }
+    }
+}
+
+pub async fn select_all(source: &DbSource) -> Result<Vec<Account>, AppError> {
+    let accounts = sqlx::query_as!(AccountRow, "SELECT * FROM accounts ORDER BY created_on")
+        .fetch_all(&mut *source.acquire().await?)
+        .await?;
+
+    Ok(accounts.into_iter().map(Account::from).collect())
+}
+
+pub async fn select_one(id: Uuid, source: &DbSource) -> Result<Account, AppError> {
+    sqlx::query_as!(AccountRow, "SELECT * FROM accounts WHERE id = $1", id)
+        .fetch_optional(&mut *source.acquire().await?)
+        .await?
+        .map(Account::from)
+        .ok_or_else(|| Account::not_found(id))
+}
+
+pub async fn select_by_email(email: String, source: &DbSource) -> Result<Account, AppError> {
+    sqlx::query_as!(AccountRow, "SELECT * FROM accounts WHERE email = $1", email)
+        .fetch_optional(&mut *source.acquire().await?)
+        .await?
+        .map(Account::from)
+        .ok_or_else(|| Account::not_found(&email))
+}
+
+pub async fn create_one(new_account: NewAccount, source: &DbSource) -> Result<Account, AppError> {
+    let mut connection = source.acquire().await?;
+
+    insert(new_account, &mut connection).await
+}
+
+/// Insert the account with the given connection, e.g in the transaction auditing its sign up
+pub(crate) async fn insert(
+    new_account: NewAccount,
+    connection: &mut PgConnection,
+) -> Result<Account, AppError> {
+    let existing = sqlx::query!(
+        "SELECT id FROM accounts WHERE email = $1",
+        new_account.email
+    )
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    if existing.is_some() {
+        return Err(AppError::new(Errors::Client(
+            ClientError::AccountAlreadyExists,
+        )));
+    }
+
+    let hashed_password = common::crypto::hash_password(new_account.password);
+
+    let account = sqlx::query_as!(
+        AccountRow,
+        "INSERT INTO accounts (email, password, role, verified)
+         VALUES ($1, $2, $3, $4)
+         RETURNING *",
+        new_account.email,
+        hashed_password,
+        new_account.role,
+        new_account.verified
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+
+    Ok(account.into())
+}
+
+/// A role change is audited, in the transaction of the update
+pub async fn update_one(
+    id: Uuid,
+    update_account: UpdateAccount,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<Account, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let before: Account = sqlx::query_as!(
+        AccountRow,
+        "SELECT * FROM accounts WHERE id = $1 FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| Account::not_found(id))?
+    .into();
+
+    let account: Account = sqlx::query_as!(
+        AccountRow,
+        "UPDATE accounts SET role = COALESCE($1, role), verified = COALESCE($2, verified), updated_on = NOW()
+         WHERE id = $3
+         RETURNING *",
+        update_account.role,
+        update_account.verified,
+        id
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .into();
+
+    if let Some(entry) = role_change(context, &before, &account) {
+        audit::controller_db::record(entry, &mut transaction).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(account)
+}
+
+pub async fn reset_password(
+    id: Uuid,
+    password: String,
+    source: &DbSource,
+) -> Result<Account, AppError> {
+    let hashed_password = common::crypto::hash_password(password);
+
+    sqlx::query_as!(
+        AccountRow,
+        "UPDATE accounts SET password = $1, updated_on = NOW()
+         WHERE id = $2
+         RETURNING *",
+        hashed_password,
+        id
+    )
+    .fetch_optional(&mut *source.acquire().await?)
+    .await?
+    .map(Account::from)
+    .ok_or_else(|| Account::not_found(id))
+}
+
+pub async fn delete_one(id: Uuid, source: &DbSource) -> Result<String, AppError> {
+    let result = sqlx::query!("DELETE FROM accounts WHERE id = $1", id)
+        .execute(&mut *source.acquire().await?)
+        .await?;
+
+    match result.rows_affected() {
+        0 => Err(Account::not_found(id)),
+        rows => Ok(format!("{rows} row deleted")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use setup::db_store::DbStore;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_account_round_trip(pool: PgPool) {
+        // Arrange
+        let source = DbSource {
+            db: DbStore::from_pool(pool),
+        };
+        let new_account = NewAccount {
+            email: "member@mail.com".into(),
+            password: "Pass:12345".into(),
+            role: "member".into(),
+            verified: false,
+        };
+
+        // Act
+        let created = create_one(new_account, &source).await.unwrap();
+        let by_email = select_by_email("member@mail.com".into(), &source)
+            .await
+            .unwrap();
+        let reset = reset_password(created.id.0, "New:pass99".into(), &source)
+            .await
+            .unwrap();
+        delete_one(created.id.0, &source).await.unwrap();
+        let deleted = select_one(created.id.0, &source).await;
+
+        // Assert
+        assert_eq!(by_email.id.0, created.id.0);
+        assert_eq!(by_email.role, "member");
+        assert!(common::crypto::verify_password(&reset.password, "New:pass99".into()).is_ok());
+        assert!(reset.last_modification_time.is_some());
+        assert!(matches!(
+            deleted,
+            Err(AppError {
+                error: Errors::Client(ClientError::ResourceNotFound { .. })
+            })
+        ));
+    }
+}
@@ -1,25 +1,121 @@
+use chrono::Utc;
 use errors::{AppError, ClientError, Errors};
 
-use crate::data_source::MockSource;
+use crate::{
+    audit::{self, models::AuditContext},
+    data_source::MockSource,
+};
 
-use super::models::Account;
+use super::models::{role_change, Account, AccountId, NewAccount, UpdateAccount};
+
+pub async fn select_all(source: &MockSource) -> Result<Vec<Account>, AppError> {
+    Ok(source.accounts.read().await.to_vec())
+}
 
 pub async fn select_one(id: uuid::Uuid, source: &MockSource) -> Result<Account, AppError> {
     let accounts = source.accounts.read().await;
 
     accounts
-        .clone()
-        .into_iter()
+        .iter()
+        .find(|account| id == account.id.0)
+        .cloned()
+        .ok_or_else(|| Account::not_found(id))
+}
+
+pub async fn select_by_email(email: String, source: &MockSource) -> Result<Account, AppError> {
+    let accounts = source.accounts.read().await;
+
+    accounts
+        .iter()
+        .find(|account| email == account.email)
+        .cloned()
+        .ok_or_else(|| Account::not_found(&email))
+}
+
+pub async fn create_one(new_account: NewAccount, source: &MockSource) -> Result<Account, AppError> {
+    let mut accounts = source.accounts.write().await;
+
+    if accounts
+        .iter()
+        .any(|account| account.email == new_account.email)
+    {
+        return Err(AppError::new(Errors::Client(
+            ClientError::AccountAlreadyExists,
+        )));
+    }
+
+    let account = Account {
+        id: AccountId(uuid::Uuid::new_v4()),
+        email: new_account.email,
+        password: common::crypto::hash_password(new_account.password),
+        role: new_account.role,
+        verified: new_account.verified,
+        creation_time: Utc::now(),
+        last_modification_time: None,
+    };
+
+    accounts.push(account.to_owned());
+
+    Ok(account)
+}
+
+/// A role change is audited, under the lock of the update
+pub async fn update_one(
+    id: uuid::Uuid,
+    update_account: UpdateAccount,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<Account, AppError> {
+    let mut accounts = source.accounts.write().await;
+
+    let index = accounts
+        .iter()
+        .position(|account| id == account.id.0)
+        .ok_or_else(|| Account::not_found(id))?;
+    let before = accounts[index].clone();
+    let mut current_account = before.clone();
+
+    if let Some(role) = update_account.role {
+        current_account.role = role;
+    }
+
+    if let Some(verified) = update_account.verified {
+        current_account.verified = verified;
+    }
+
+    current_account.last_modification_time = Some(Utc::now());
+    accounts[index] = current_account.clone();
+    if let Some(entry) = role_change(context, &before, &current_account) {
+        audit::controller_mock::record(entry, &mut *source.audit_log.write().await);
+    }
+    Ok(current_account)
+}
+
+pub async fn reset_password(
+    id: uuid::Uuid,
+    password: String,
+    source: &MockSource,
+) -> Result<Account, AppError> {
+    let mut accounts = source.accounts.write().await;
+
+    let account = accounts
+        .iter_mut()
         .find(|account| id == account.id.0)
-        .map_or_else(
-            || {
-                Err(AppError::new(Errors::Client(
-                    ClientError::ResourceNotFound {
-                        resource_name: "accounts".into(),
-                        id: id.to_string(),
-                    },
-                )))
-            },
-            Ok,
-        )
+        .ok_or_else(|| Account::not_found(id))?;
+    account.password = common::crypto::hash_password(password);
+    account.last_modification_time = Some(Utc::now());
+
+    Ok(account.clone())
+}
+
+pub async fn delete_one(id: uuid::Uuid, source: &MockSource) -> Result<String, AppError> {
+    let mut accounts = source.accounts.write().await;
+
+    let index = accounts
+        .iter()
+        .position(|account| id == account.id.0)
+        .ok_or_else(|| Account::not_found(id))?;
+    accounts.remove(index);
+
+    Ok("1 row deleted".to_string())
 }
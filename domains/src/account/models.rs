@@ -1,11 +1,22 @@
 use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
+use common::validation::validate_password;
 use errors::{AppError, ClientError, Errors};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::audit::models::{AuditAction, AuditContext, NewAuditEntry};
+
+/// Resource name of the accounts, in errors and the audit log
+pub const RESOURCE: &str = "accounts";
+
+/// Role of the accounts allowed to administrate the service (e.g query the audit log)
+pub const ADMIN_ROLE: &str = "admin";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AccountId(pub Uuid);
 
 impl FromStr for AccountId {
@@ -35,6 +46,16 @@ impl Account {
         let file = include_str!("./mock/accounts.json");
         serde_json::from_str(file).expect("can't read accounts.json")
     }
+    /// Error of an account missing, looked up by `id` (or email)
+    pub fn not_found(id: impl ToString) -> AppError {
+        AppError::new(Errors::Client(ClientError::ResourceNotFound {
+            resource_name: RESOURCE.into(),
+            id: id.to_string(),
+        }))
+    }
+    pub fn is_admin(&self) -> bool {
+        self.role == ADMIN_ROLE
+    }
     pub fn secure(self) -> SecureAccount {
         SecureAccount {
             id: self.id,
@@ -47,9 +68,24 @@ impl Account {
     }
 }
 
+/// Audit entry of the update from `before` to `after`, only the role changes are audited
+/// (not the verifications)
+pub(crate) fn role_change(
+    context: &AuditContext,
+    before: &Account,
+    after: &Account,
+) -> Option<NewAuditEntry> {
+    (before.role != after.role).then(|| {
+        NewAuditEntry::new(context, AuditAction::RoleChange, RESOURCE, after.id.0).with_diff(
+            Some(&before.clone().secure()),
+            Some(&after.clone().secure()),
+        )
+    })
+}
+
 /// Model sent back to the client
 /// Sensitive data are removed
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SecureAccount {
     pub id: AccountId,
     pub email: String,
@@ -58,3 +94,30 @@ pub struct SecureAccount {
     pub creation_time: DateTime<Utc>,
     pub last_modification_time: Option<DateTime<Utc>>,
 }
+
+/// New Account struct
+/// Used when an account is created outside of the sign up flow (e.g. admin cli)
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+pub struct NewAccount {
+    #[validate(email)]
+    pub email: String,
+    #[validate(length(min = 8), custom(function = "validate_password"))]
+    pub password: String,
+    pub role: String,
+    pub verified: bool,
+}
+
+/// Update Account struct
+/// All the fields are optional
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UpdateAccount {
+    pub role: Option<String>,
+    pub verified: Option<bool>,
+}
+
+/// Password reset struct
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+pub struct ResetPassword {
+    #[validate(length(min = 8), custom(function = "validate_password"))]
+    pub password: String,
+}
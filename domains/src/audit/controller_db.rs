@@ -0,0 +1,92 @@
+use errors::AppError;
+use sqlx::PgConnection;
+use tracing::instrument;
+
+use crate::{
+    audit::models::{AuditEntry, AuditFilter, NewAuditEntry},
+    data_source::DbSource,
+};
+
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn select_all(
+    filter: AuditFilter,
+    source: &DbSource,
+) -> Result<Vec<AuditEntry>, AppError> {
+    let entries: Vec<AuditEntry> = traced_query!(
+        "SELECT * FROM audit_log
+         WHERE ($1::uuid IS NULL OR actor = $1)
+           AND ($2::text IS NULL OR action = $2)
+           AND ($3::text IS NULL OR resource = $3)
+           AND ($4::text IS NULL OR resource_id = $4)
+           AND ($5::timestamptz IS NULL OR created_on >= $5)
+           AND ($6::timestamptz IS NULL OR created_on < $6)
+         ORDER BY id DESC
+         LIMIT $7",
+        filter.actor,
+        filter.action.map(|action| action.as_str()),
+        filter.resource,
+        filter.resource_id,
+        filter.since,
+        filter.until,
+        filter.limit()
+    )
+    .map(|row| AuditEntry {
+        id: row.id,
+        actor: row.actor,
+        action: row.action,
+        resource: row.resource,
+        resource_id: row.resource_id,
+        diff: row.diff,
+        request_id: row.request_id,
+        ip: row.ip,
+        creation_time: row.created_on,
+    })
+    .fetch_all(&mut *source.acquire().await?)
+    .await?;
+
+    Ok(entries)
+}
+
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn insert_one(
+    new_entry: NewAuditEntry,
+    source: &DbSource,
+) -> Result<AuditEntry, AppError> {
+    let mut connection = source.acquire().await?;
+
+    record(new_entry, &mut connection).await
+}
+
+/// Insert the entry with the connection of the mutation it records, so both are committed together
+pub(crate) async fn record(
+    new_entry: NewAuditEntry,
+    connection: &mut PgConnection,
+) -> Result<AuditEntry, AppError> {
+    let entry: AuditEntry = traced_query!(
+        "INSERT INTO audit_log (actor, action, resource, resource_id, diff, request_id, ip)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING *",
+        new_entry.actor,
+        new_entry.action.as_str(),
+        new_entry.resource,
+        new_entry.resource_id,
+        new_entry.diff,
+        new_entry.request_id,
+        new_entry.ip
+    )
+    .map(|row| AuditEntry {
+        id: row.id,
+        actor: row.actor,
+        action: row.action,
+        resource: row.resource,
+        resource_id: row.resource_id,
+        diff: row.diff,
+        request_id: row.request_id,
+        ip: row.ip,
+        creation_time: row.created_on,
+    })
+    .fetch_one(connection)
+    .await?;
+
+    Ok(entry)
+}
@@ -0,0 +1,46 @@
+use chrono::Utc;
+use errors::AppError;
+
+use crate::{
+    audit::models::{AuditEntry, AuditFilter, NewAuditEntry},
+    data_source::MockSource,
+};
+
+pub async fn select_all(
+    filter: AuditFilter,
+    source: &MockSource,
+) -> Result<Vec<AuditEntry>, AppError> {
+    let audit_log = source.audit_log.read().await;
+
+    Ok(audit_log
+        .iter()
+        .rev()
+        .filter(|entry| filter.matches(entry))
+        .take(filter.limit() as usize)
+        .cloned()
+        .collect())
+}
+
+pub async fn insert_one(
+    new_entry: NewAuditEntry,
+    source: &MockSource,
+) -> Result<AuditEntry, AppError> {
+    Ok(record(new_entry, &mut *source.audit_log.write().await))
+}
+
+/// Append the entry to the log, while the lock of the mutation it records is held
+pub(crate) fn record(new_entry: NewAuditEntry, audit_log: &mut Vec<AuditEntry>) -> AuditEntry {
+    let entry = AuditEntry {
+        id: audit_log.len() as i64 + 1,
+        actor: new_entry.actor,
+        action: new_entry.action.as_str().into(),
+        resource: new_entry.resource,
+        resource_id: new_entry.resource_id,
+        diff: new_entry.diff,
+        request_id: new_entry.request_id,
+        ip: new_entry.ip,
+        creation_time: Utc::now(),
+    };
+    audit_log.push(entry.clone());
+    entry
+}
@@ -0,0 +1,214 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use common::request_id;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// Entries returned by a query without limit
+pub const DEFAULT_LIMIT: i64 = 100;
+/// Most entries returned by a query
+pub const MAX_LIMIT: i64 = 1000;
+
+/// Audited mutations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Replace,
+    Delete,
+    Restore,
+    Purge,
+    SignUp,
+    SignIn,
+    SignOut,
+    RoleChange,
+}
+
+impl AuditAction {
+    /// Name stored in the audit log (same as serialized)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Replace => "replace",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+            AuditAction::SignUp => "sign_up",
+            AuditAction::SignIn => "sign_in",
+            AuditAction::SignOut => "sign_out",
+            AuditAction::RoleChange => "role_change",
+        }
+    }
+}
+
+/// Origin of the audited mutations of a request
+/// Without actor for anonymous requests, without request id nor ip outside of the servers (e.g wsctl)
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor: Option<Uuid>,
+    pub request_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl AuditContext {
+    /// Context of the request being served, its id is the current one (see `common::request_id`)
+    pub fn new(actor: Option<Uuid>, ip: Option<IpAddr>) -> Self {
+        Self {
+            actor,
+            request_id: request_id::current(),
+            ip: ip.map(|ip| ip.to_string()),
+        }
+    }
+
+    /// Same context acted by `actor` (e.g the account that just signed in)
+    pub fn acted_by(self, actor: Uuid) -> Self {
+        Self {
+            actor: Some(actor),
+            ..self
+        }
+    }
+}
+
+/// Audit log entry
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: Option<Uuid>,
+    pub action: String,
+    pub resource: String,
+    pub resource_id: String,
+    /// Changed fields, as `{"before": {...}, "after": {...}}`
+    #[schema(value_type = Object)]
+    pub diff: Value,
+    pub request_id: Option<String>,
+    pub ip: Option<String>,
+    pub creation_time: DateTime<Utc>,
+}
+
+/// New audit log entry, see `DataSource::audit`
+#[derive(Debug, Clone)]
+pub struct NewAuditEntry {
+    pub actor: Option<Uuid>,
+    pub action: AuditAction,
+    pub resource: String,
+    pub resource_id: String,
+    pub diff: Value,
+    pub request_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl NewAuditEntry {
+    pub fn new(
+        context: &AuditContext,
+        action: AuditAction,
+        resource: &str,
+        resource_id: impl ToString,
+    ) -> Self {
+        Self {
+            actor: context.actor,
+            action,
+            resource: resource.into(),
+            resource_id: resource_id.to_string(),
+            diff: json!({}),
+            request_id: context.request_id.clone(),
+            ip: context.ip.clone(),
+        }
+    }
+
+    /// Diff of the fields changed from `before` to `after`, every field when one of them is missing
+    /// (creation or deletion)
+    pub fn with_diff<T: Serialize>(self, before: Option<&T>, after: Option<&T>) -> Self {
+        let value = |resource: Option<&T>| resource.and_then(|r| serde_json::to_value(r).ok());
+        Self {
+            diff: diff(value(before), value(after)),
+            ..self
+        }
+    }
+}
+
+fn diff(before: Option<Value>, after: Option<Value>) -> Value {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let changed = |from: &Map<String, Value>, to: &Map<String, Value>| {
+                from.iter()
+                    .filter(|(key, value)| to.get(*key) != Some(*value))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect::<Map<String, Value>>()
+            };
+            json!({ "before": changed(&before, &after), "after": changed(&after, &before) })
+        }
+        (before, after) => json!({ "before": before, "after": after }),
+    }
+}
+
+/// Filters of an audit log query, newest entries first
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    /// Account that made the mutations
+    pub actor: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    /// Resource name (e.g cats)
+    pub resource: Option<String>,
+    pub resource_id: Option<String>,
+    /// Entries recorded from that time (e.g 2023-03-01T00:00:00Z)
+    pub since: Option<DateTime<Utc>>,
+    /// Entries recorded before that time
+    pub until: Option<DateTime<Utc>>,
+    /// Max number of entries, 100 by default and 1000 at most
+    pub limit: Option<i64>,
+}
+
+impl AuditFilter {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// Whether the entry passes every filter (mock source)
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.actor.is_none_or(|actor| entry.actor == Some(actor))
+            && self
+                .action
+                .is_none_or(|action| entry.action == action.as_str())
+            && self
+                .resource
+                .as_ref()
+                .is_none_or(|resource| &entry.resource == resource)
+            && self
+                .resource_id
+                .as_ref()
+                .is_none_or(|id| &entry.resource_id == id)
+            && self.since.is_none_or(|since| entry.creation_time >= since)
+            && self.until.is_none_or(|until| entry.creation_time < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_keeps_changed_fields() {
+        // Arrange
+        let before = json!({ "id": "1", "name": "A", "age": 1 });
+        let after = json!({ "id": "1", "name": "B", "age": 1 });
+
+        // Act
+        let update = NewAuditEntry::new(&AuditContext::default(), AuditAction::Update, "cats", 1)
+            .with_diff(Some(&before), Some(&after));
+        let delete = NewAuditEntry::new(&AuditContext::default(), AuditAction::Delete, "cats", 1)
+            .with_diff(Some(&before), None);
+
+        // Assert
+        assert_eq!(
+            update.diff,
+            json!({ "before": { "name": "A" }, "after": { "name": "B" } })
+        );
+        assert_eq!(delete.diff, json!({ "before": before, "after": null }));
+    }
+}
@@ -0,0 +1,162 @@
+use errors::{AppError, ClientError, Errors};
+use setup::config::auth_config::AuthConfig;
+use sqlx::Connection;
+use tracing::instrument;
+
+use crate::{
+    account::{
+        self,
+        models::{Account, NewAccount, RESOURCE},
+    },
+    audit::{
+        self,
+        models::{AuditAction, AuditContext, NewAuditEntry},
+    },
+    data_source::DbSource,
+};
+
+use super::models::{SignInAuth, SignUpAuth, SignedIn};
+
+// Credentials are kept out of the spans
+/// The account and the audit entry of its sign up are committed together
+#[instrument(skip_all, err(Display))]
+pub async fn sign_up(
+    sign_up_auth: SignUpAuth,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<Account, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let account = account::controller_db::insert(
+        NewAccount {
+            email: sign_up_auth.email,
+            password: sign_up_auth.password,
+            role: "member".into(),
+            verified: false,
+        },
+        &mut transaction,
+    )
+    .await?;
+    // The new account is the actor of its sign up
+    audit::controller_db::record(
+        NewAuditEntry::new(
+            &context.clone().acted_by(account.id.0),
+            AuditAction::SignUp,
+            RESOURCE,
+            account.id.0,
+        )
+        .with_diff(None, Some(&account.clone().secure())),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(account)
+}
+
+/// An unknown email fails like a wrong password
+#[instrument(skip_all, err(Display))]
+pub async fn sign_in(
+    sign_in_auth: SignInAuth,
+    auth_config: &AuthConfig,
+    source: &DbSource,
+) -> Result<SignedIn, AppError> {
+    let account = match account::controller_db::select_by_email(sign_in_auth.email, source).await {
+        Ok(account) => account,
+        Err(AppError {
+            error: Errors::Client(ClientError::ResourceNotFound { .. }),
+        }) => {
+            return Err(AppError::new(Errors::Client(
+                ClientError::InvalidCredentials,
+            )))
+        }
+        Err(err) => return Err(err),
+    };
+
+    common::crypto::verify_password(&account.password, sign_in_auth.password)?;
+
+    let token = auth_config.encode_token(account.id.0.to_string())?;
+
+    Ok(SignedIn {
+        account_id: account.id.0,
+        token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use setup::{config::server_config::ServerConfig, db_store::DbStore};
+    use sqlx::PgPool;
+
+    use crate::audit::models::AuditFilter;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_sign_up_audited_then_sign_in(pool: PgPool) {
+        // Arrange
+        let source = DbSource {
+            db: DbStore::from_pool(pool),
+        };
+        let auth_config = AuthConfig::new("test_secret", "domains", &ServerConfig::default());
+        let sign_up_auth = SignUpAuth {
+            email: "catlover@email.com".into(),
+            password: "Yop?yop!123".into(),
+            confirmation: "Yop?yop!123".into(),
+        };
+        let sign_in_auth = |email: &str, password: &str| SignInAuth {
+            email: email.into(),
+            password: password.into(),
+        };
+
+        // Act
+        let account = sign_up(sign_up_auth.clone(), &AuditContext::default(), &source)
+            .await
+            .unwrap();
+        let twice = sign_up(sign_up_auth, &AuditContext::default(), &source).await;
+        let signed_in = sign_in(
+            sign_in_auth("catlover@email.com", "Yop?yop!123"),
+            &auth_config,
+            &source,
+        )
+        .await
+        .unwrap();
+        let wrong_password = sign_in(
+            sign_in_auth("catlover@email.com", "Yop?yop!124"),
+            &auth_config,
+            &source,
+        )
+        .await;
+        let unknown = sign_in(
+            sign_in_auth("unknown@email.com", "Yop?yop!123"),
+            &auth_config,
+            &source,
+        )
+        .await;
+
+        // Assert
+        assert_eq!(signed_in.account_id, account.id.0);
+        assert!(matches!(
+            twice,
+            Err(AppError {
+                error: Errors::Client(ClientError::AccountAlreadyExists)
+            })
+        ));
+        for failed in [wrong_password, unknown] {
+            assert!(matches!(
+                failed,
+                Err(AppError {
+                    error: Errors::Client(ClientError::InvalidCredentials)
+                })
+            ));
+        }
+        let entries = audit::controller_db::select_all(AuditFilter::default(), &source)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "sign_up");
+        assert_eq!(entries[0].actor, Some(account.id.0));
+        assert_eq!(entries[0].diff["after"]["email"], "catlover@email.com");
+    }
+}
@@ -1,14 +1,26 @@
 use chrono::Utc;
 use errors::{AppError, ClientError, Errors};
+use setup::config::auth_config::AuthConfig;
+use tracing::instrument;
 
 use crate::{
-    account::models::{Account, AccountId},
+    account::models::{Account, AccountId, RESOURCE},
+    audit::{
+        self,
+        models::{AuditAction, AuditContext, NewAuditEntry},
+    },
     data_source::MockSource,
 };
 
-use super::models::{SignInAuth, SignUpAuth};
+use super::models::{SignInAuth, SignUpAuth, SignedIn};
 
-pub async fn sign_up(sign_up_auth: SignUpAuth, source: &MockSource) -> Result<Account, AppError> {
+// Credentials are kept out of the spans
+#[instrument(skip_all, err(Display))]
+pub async fn sign_up(
+    sign_up_auth: SignUpAuth,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<Account, AppError> {
     let mut accounts = source.accounts.write().await;
 
     let account_exist = accounts
@@ -35,11 +47,27 @@ pub async fn sign_up(sign_up_auth: SignUpAuth, source: &MockSource) -> Result<Ac
     };
 
     accounts.push(account.to_owned());
+    // The new account is the actor of its sign up
+    audit::controller_mock::record(
+        NewAuditEntry::new(
+            &context.clone().acted_by(account.id.0),
+            AuditAction::SignUp,
+            RESOURCE,
+            account.id.0,
+        )
+        .with_diff(None, Some(&account.clone().secure())),
+        &mut *source.audit_log.write().await,
+    );
 
     Ok(account)
 }
 
-pub async fn sign_in(sign_in_auth: SignInAuth, source: &MockSource) -> Result<String, AppError> {
+#[instrument(skip_all, err(Display))]
+pub async fn sign_in(
+    sign_in_auth: SignInAuth,
+    auth_config: &AuthConfig,
+    source: &MockSource,
+) -> Result<SignedIn, AppError> {
     let accounts = source.accounts.read().await;
 
     let existing_account = accounts
@@ -47,9 +75,10 @@ pub async fn sign_in(sign_in_auth: SignInAuth, source: &MockSource) -> Result<St
         .into_iter()
         .find(|account| sign_in_auth.email == account.email);
 
+    // An unknown email fails like a wrong password
     if existing_account.is_none() {
         return Err(AppError::new(Errors::Client(
-            ClientError::AccountAlreadyExists,
+            ClientError::InvalidCredentials,
         )));
     }
 
@@ -57,7 +86,10 @@ pub async fn sign_in(sign_in_auth: SignInAuth, source: &MockSource) -> Result<St
 
     common::crypto::verify_password(&account.password, sign_in_auth.password)?;
 
-    let token = setup::AUTH_CONFIG.encode_token(account.id.0.to_string())?;
+    let token = auth_config.encode_token(account.id.0.to_string())?;
 
-    Ok(token)
+    Ok(SignedIn {
+        account_id: account.id.0,
+        token,
+    })
 }
@@ -1,8 +1,10 @@
 use common::validation::validate_password;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 pub struct SignUpAuth {
     #[validate(email)]
     pub email: String,
@@ -12,10 +14,17 @@ pub struct SignUpAuth {
     pub confirmation: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
 pub struct SignInAuth {
     #[validate(email)]
     pub email: String,
     #[validate(custom(function = "validate_password"))]
     pub password: String,
 }
+
+/// Token issued to a signed in account
+#[derive(Debug, Clone)]
+pub struct SignedIn {
+    pub account_id: Uuid,
+    pub token: String,
+}
@@ -1,45 +1,69 @@
+use chrono::{DateTime, Utc};
 use errors::AppError;
+use sqlx::{Connection, PgConnection};
+use tracing::instrument;
 
 use crate::{
-    cat::models::{Cat, CatId, CurrentCat, NewCat, ReplaceCat, UpdateCat},
+    audit::{
+        self,
+        models::{AuditAction, AuditContext, NewAuditEntry},
+    },
+    cat::models::{Cat, CatId, NewCat, ReplaceCat, UpdateCat, RESOURCE},
     data_source::DbSource,
 };
 
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
 pub async fn select_all(source: &DbSource) -> Result<Vec<Cat>, AppError> {
-    let cats: Vec<Cat> = sqlx::query!("SELECT * FROM cats")
+    let cats: Vec<Cat> = traced_query!("SELECT * FROM cats WHERE deleted_on IS NULL")
         .map(|row| Cat {
             id: CatId(row.id.to_string()),
             name: row.name,
             age: row.age,
             weight: row.weight,
             creation_time: row.created_on,
+            version: row.version,
+            deletion_time: row.deleted_on,
         })
-        .fetch_all(&source.db.connection)
+        .fetch_all(&mut *source.acquire().await?)
         .await?;
 
     Ok(cats)
 }
 
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
 pub async fn select_one(id: i32, source: &DbSource) -> Result<Cat, AppError> {
-    let cat: Cat = sqlx::query!("SELECT * FROM cats WHERE id = $1", id)
-        .map(|row| Cat {
-            id: CatId(row.id.to_string()),
-            name: row.name,
-            age: row.age,
-            weight: row.weight,
-            creation_time: row.created_on,
-        })
-        .fetch_one(&source.db.connection)
-        .await?;
-
-    Ok(cat)
+    traced_query!(
+        "SELECT * FROM cats WHERE id = $1 AND deleted_on IS NULL",
+        id
+    )
+    .map(|row| Cat {
+        id: CatId(row.id.to_string()),
+        name: row.name,
+        age: row.age,
+        weight: row.weight,
+        creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
+    })
+    .fetch_optional(&mut *source.acquire().await?)
+    .await?
+    .ok_or_else(|| Cat::not_found(id))
 }
 
-pub async fn create_one(new_cat: NewCat, source: &DbSource) -> Result<Cat, AppError> {
-    let cat: Cat = sqlx::query!(
+/// The cat and its audit entry are committed together
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn create_one(
+    new_cat: NewCat,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<Cat, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let cat: Cat = traced_query!(
         "INSERT INTO cats (name, age, weight) 
          VALUES ($1, $2, $3) 
-         RETURNING id, name, age, weight, created_on",
+         RETURNING id, name, age, weight, created_on, version, deleted_on",
         new_cat.name,
         new_cat.age,
         new_cat.weight
@@ -50,50 +74,52 @@ pub async fn create_one(new_cat: NewCat, source: &DbSource) -> Result<Cat, AppEr
         age: row.age,
         weight: row.weight,
         creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
     })
-    .fetch_one(&source.db.connection)
+    .fetch_one(&mut *transaction)
     .await?;
 
+    audit::controller_db::record(
+        NewAuditEntry::new(context, AuditAction::Create, RESOURCE, &cat.id.0)
+            .with_diff(None, Some(&cat)),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
     Ok(cat)
 }
 
+/// The update applies to the version read (or the one expected by the client),
+/// a concurrent write in between makes it fail with a conflict instead of being overwritten
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
 pub async fn update_one(
     id: i32,
     update_cat: UpdateCat,
+    context: &AuditContext,
     source: &DbSource,
 ) -> Result<Cat, AppError> {
-    // Retrieve current data
-    let current_cat = sqlx::query_as!(
-        CurrentCat,
-        "SELECT name, age, weight FROM cats WHERE id = $1",
-        id
-    )
-    .fetch_one(&source.db.connection)
-    .await?;
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
 
-    let name = match update_cat.name {
-        Some(name) => name,
-        None => current_cat.name,
-    };
-
-    let age = match update_cat.age {
-        Some(age) => age,
-        None => current_cat.age,
-    };
-
-    let weight = match update_cat.weight {
-        Some(weight) => weight,
-        None => current_cat.weight.unwrap_or_default(),
-    };
-
-    let cat: Cat = sqlx::query!(
-        "UPDATE cats SET name = $1, age = $2, weight = $3  
-         WHERE id = $4
-         RETURNING id, name, age, weight, created_on",
-        name,
-        age,
-        weight,
-        id
+    let before = read_one(id, false, &mut transaction)
+        .await?
+        .ok_or_else(|| Cat::not_found(id))?;
+    let version = update_cat.version.unwrap_or(before.version);
+    if version != before.version {
+        return Err(Cat::conflict(id));
+    }
+
+    let cat: Cat = traced_query!(
+        "UPDATE cats SET name = $1, age = $2, weight = $3, version = version + 1
+         WHERE id = $4 AND version = $5 AND deleted_on IS NULL
+         RETURNING id, name, age, weight, created_on, version, deleted_on",
+        update_cat.name.unwrap_or_else(|| before.name.clone()),
+        update_cat.age.unwrap_or(before.age),
+        update_cat.weight.or(before.weight).unwrap_or_default(),
+        id,
+        version
     )
     .map(|row| Cat {
         id: CatId(row.id.to_string()),
@@ -101,25 +127,147 @@ pub async fn update_one(
         age: row.age,
         weight: row.weight,
         creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
     })
-    .fetch_one(&source.db.connection)
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| Cat::conflict(id))?;
+
+    audit::controller_db::record(
+        NewAuditEntry::new(context, AuditAction::Update, RESOURCE, id)
+            .with_diff(Some(&before), Some(&cat)),
+        &mut transaction,
+    )
     .await?;
+    transaction.commit().await?;
 
     Ok(cat)
 }
 
+/// The replacement applies to the version read (or the one expected by the client),
+/// a concurrent write in between makes it fail with a conflict instead of being overwritten
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
 pub async fn replace_one(
     id: i32,
     replace_cat: ReplaceCat,
+    context: &AuditContext,
     source: &DbSource,
 ) -> Result<Cat, AppError> {
-    let cat: Cat = sqlx::query!(
-        "UPDATE cats SET name = $1, age = $2, weight = $3  
-         WHERE id = $4
-         RETURNING id, name, age, weight, created_on",
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let before = read_one(id, false, &mut transaction)
+        .await?
+        .ok_or_else(|| Cat::not_found(id))?;
+    let version = replace_cat.version.unwrap_or(before.version);
+    if version != before.version {
+        return Err(Cat::conflict(id));
+    }
+
+    let cat: Cat = traced_query!(
+        "UPDATE cats SET name = $1, age = $2, weight = $3, version = version + 1
+         WHERE id = $4 AND version = $5 AND deleted_on IS NULL
+         RETURNING id, name, age, weight, created_on, version, deleted_on",
         replace_cat.name,
         replace_cat.age,
         replace_cat.weight,
+        id,
+        version
+    )
+    .map(|row| Cat {
+        id: CatId(row.id.to_string()),
+        name: row.name,
+        age: row.age,
+        weight: row.weight,
+        creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
+    })
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| Cat::conflict(id))?;
+
+    audit::controller_db::record(
+        NewAuditEntry::new(context, AuditAction::Replace, RESOURCE, id)
+            .with_diff(Some(&before), Some(&cat)),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(cat)
+}
+
+/// Move the cat to the trash, it stays restorable until purged
+/// The deletion applies to the version read (or the one expected with `If-Match`),
+/// a concurrent write in between makes it fail with a conflict
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn delete_one(
+    id: i32,
+    version: Option<i32>,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<String, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let before = read_one(id, false, &mut transaction)
+        .await?
+        .ok_or_else(|| Cat::not_found(id))?;
+    let version = version.unwrap_or(before.version);
+    if version != before.version {
+        return Err(Cat::conflict(id));
+    }
+
+    let result = traced_query!(
+        "UPDATE cats SET deleted_on = NOW(), version = version + 1
+         WHERE id = $1 AND version = $2 AND deleted_on IS NULL",
+        id,
+        version
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(Cat::conflict(id));
+    }
+
+    audit::controller_db::record(
+        NewAuditEntry::new(context, AuditAction::Delete, RESOURCE, id)
+            .with_diff(Some(&before), None),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok("Cat moved to trash".to_string())
+}
+
+/// Trashed cats, most recently deleted first
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn select_trash(source: &DbSource) -> Result<Vec<Cat>, AppError> {
+    let cats: Vec<Cat> = traced_query!(
+        "SELECT * FROM cats WHERE deleted_on IS NOT NULL ORDER BY deleted_on DESC, id"
+    )
+    .map(|row| Cat {
+        id: CatId(row.id.to_string()),
+        name: row.name,
+        age: row.age,
+        weight: row.weight,
+        creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
+    })
+    .fetch_all(&mut *source.acquire().await?)
+    .await?;
+
+    Ok(cats)
+}
+
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn select_trashed_one(id: i32, source: &DbSource) -> Result<Cat, AppError> {
+    traced_query!(
+        "SELECT * FROM cats WHERE id = $1 AND deleted_on IS NOT NULL",
         id
     )
     .map(|row| Cat {
@@ -128,17 +276,257 @@ pub async fn replace_one(
         age: row.age,
         weight: row.weight,
         creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
     })
-    .fetch_one(&source.db.connection)
+    .fetch_optional(&mut *source.acquire().await?)
+    .await?
+    .ok_or_else(|| Cat::not_found(id))
+}
+
+/// Take the cat out of the trash, a concurrent write since it was read makes it fail with a conflict
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn restore_one(
+    id: i32,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<Cat, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let before = read_one(id, true, &mut transaction)
+        .await?
+        .ok_or_else(|| Cat::not_found(id))?;
+
+    let cat: Cat = traced_query!(
+        "UPDATE cats SET deleted_on = NULL, version = version + 1
+         WHERE id = $1 AND version = $2 AND deleted_on IS NOT NULL
+         RETURNING id, name, age, weight, created_on, version, deleted_on",
+        id,
+        before.version
+    )
+    .map(|row| Cat {
+        id: CatId(row.id.to_string()),
+        name: row.name,
+        age: row.age,
+        weight: row.weight,
+        creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
+    })
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or_else(|| Cat::conflict(id))?;
+
+    audit::controller_db::record(
+        NewAuditEntry::new(context, AuditAction::Restore, RESOURCE, id)
+            .with_diff(Some(&before), Some(&cat)),
+        &mut transaction,
+    )
     .await?;
+    transaction.commit().await?;
 
     Ok(cat)
 }
 
-pub async fn delete_one(id: i32, source: &DbSource) -> Result<String, AppError> {
-    let result = sqlx::query!("DELETE FROM cats WHERE id = $1", id)
-        .execute(&source.db.connection)
+/// Delete a trashed cat for good, a concurrent write since it was read makes it fail with a conflict
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn purge_one(
+    id: i32,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<String, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let before = read_one(id, true, &mut transaction)
+        .await?
+        .ok_or_else(|| Cat::not_found(id))?;
+
+    let result = traced_query!(
+        "DELETE FROM cats WHERE id = $1 AND version = $2 AND deleted_on IS NOT NULL",
+        id,
+        before.version
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(Cat::conflict(id));
+    }
+
+    audit::controller_db::record(
+        NewAuditEntry::new(context, AuditAction::Purge, RESOURCE, id)
+            .with_diff(Some(&before), None),
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok("Cat purged".to_string())
+}
+
+/// Delete for good the cats trashed before `deleted_before`, returns how many were purged
+/// Each purge is audited in the same transaction
+#[instrument(skip(source), fields(db.system = "postgresql", db.statement), err(Display))]
+pub async fn purge_expired(
+    deleted_before: DateTime<Utc>,
+    context: &AuditContext,
+    source: &DbSource,
+) -> Result<u64, AppError> {
+    let mut connection = source.acquire().await?;
+    let mut transaction = connection.begin().await?;
+
+    let purged: Vec<Cat> = traced_query!(
+        "DELETE FROM cats WHERE deleted_on < $1 RETURNING *",
+        deleted_before
+    )
+    .map(|row| Cat {
+        id: CatId(row.id.to_string()),
+        name: row.name,
+        age: row.age,
+        weight: row.weight,
+        creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
+    })
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    for cat in &purged {
+        audit::controller_db::record(
+            NewAuditEntry::new(context, AuditAction::Purge, RESOURCE, &cat.id.0)
+                .with_diff(Some(cat), None),
+            &mut transaction,
+        )
         .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(purged.len() as u64)
+}
+
+/// Cat in the trash or not (`trashed`), its state before a mutation for the audit diff
+/// Not locked: the mutation applies to this version only (optimistic concurrency)
+async fn read_one(
+    id: i32,
+    trashed: bool,
+    connection: &mut PgConnection,
+) -> Result<Option<Cat>, AppError> {
+    let cat = traced_query!(
+        "SELECT * FROM cats WHERE id = $1 AND (deleted_on IS NOT NULL) = $2",
+        id,
+        trashed
+    )
+    .map(|row| Cat {
+        id: CatId(row.id.to_string()),
+        name: row.name,
+        age: row.age,
+        weight: row.weight,
+        creation_time: row.created_on,
+        version: row.version,
+        deletion_time: row.deleted_on,
+    })
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(cat)
+}
+
+#[cfg(test)]
+mod tests {
+    use setup::db_store::DbStore;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_purge_expired_keeps_recent_trash(pool: PgPool) {
+        // Arrange
+        sqlx::query(
+            "INSERT INTO cats (name, age, deleted_on) VALUES
+             ('old', 1, NOW() - INTERVAL '40 days'),
+             ('recent', 1, NOW() - INTERVAL '10 days'),
+             ('kept', 1, NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let source = DbSource {
+            db: DbStore::from_pool(pool.clone()),
+        };
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+
+        // Act
+        let purged = purge_expired(cutoff, &AuditContext::default(), &source)
+            .await
+            .unwrap();
+
+        // Assert
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM cats ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let purges: Vec<String> = sqlx::query_scalar(
+            "SELECT diff->'before'->>'name' FROM audit_log WHERE action = 'purge'",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(purged, 1);
+        assert_eq!(names, vec!["recent", "kept"]);
+        assert_eq!(purges, vec!["old"]);
+    }
+
+    #[sqlx::test]
+    async fn test_update_racing_write_conflict(pool: PgPool) {
+        // Arrange
+        let id: i32 =
+            sqlx::query_scalar("INSERT INTO cats (name, age) VALUES ('Nala', 1) RETURNING id")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let source = DbSource {
+            db: DbStore::from_pool(pool.clone()),
+        };
+        // A concurrent write, committed once the update has read the cat
+        let mut other = pool.begin().await.unwrap();
+        sqlx::query("UPDATE cats SET age = 2, version = version + 1 WHERE id = $1")
+            .bind(id)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+        let update_cat = UpdateCat {
+            name: None,
+            age: Some(3),
+            weight: None,
+            version: None,
+        };
+        let context = AuditContext::default();
+
+        // Act
+        let (result, _) = tokio::join!(update_one(id, update_cat, &context, &source), async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            other.commit().await.unwrap();
+        });
 
-    Ok(result.rows_affected().to_string())
+        // Assert
+        let age: i16 = sqlx::query_scalar("SELECT age FROM cats WHERE id = $1")
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let updates: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM audit_log WHERE action = 'update'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(matches!(
+            result,
+            Err(AppError {
+                error: errors::Errors::Client(errors::ClientError::Conflict { .. })
+            })
+        ));
+        assert_eq!(age, 2);
+        assert_eq!(updates, 0);
+    }
 }
@@ -1,13 +1,23 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use errors::{AppError, ClientError, Errors};
 
 use crate::{
-    cat::models::{Cat, CatId, NewCat, ReplaceCat, UpdateCat},
+    audit::{
+        self,
+        models::{AuditAction, AuditContext, NewAuditEntry},
+    },
+    cat::models::{Cat, CatId, NewCat, ReplaceCat, UpdateCat, RESOURCE},
     data_source::MockSource,
 };
 
 pub async fn select_all(source: &MockSource) -> Result<Vec<Cat>, AppError> {
-    Ok(source.cats.read().await.to_vec())
+    let cats = source.cats.read().await;
+
+    Ok(cats
+        .iter()
+        .filter(|cat| cat.deletion_time.is_none())
+        .cloned()
+        .collect())
 }
 
 pub async fn select_one(id: i32, source: &MockSource) -> Result<Cat, AppError> {
@@ -15,7 +25,7 @@ pub async fn select_one(id: i32, source: &MockSource) -> Result<Cat, AppError> {
 
     cats.clone()
         .into_iter()
-        .position(|cat| cat.id.0 == id.to_string())
+        .position(|cat| cat.id.0 == id.to_string() && cat.deletion_time.is_none())
         .map_or_else(
             || {
                 Err(AppError::new(Errors::Client(
@@ -29,7 +39,12 @@ pub async fn select_one(id: i32, source: &MockSource) -> Result<Cat, AppError> {
         )
 }
 
-pub async fn create_one(new_cat: NewCat, source: &MockSource) -> Result<Cat, AppError> {
+/// The cat and its audit entry are recorded under the same locks
+pub async fn create_one(
+    new_cat: NewCat,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<Cat, AppError> {
     let mut cats = source.cats.write().await;
     let next_id = cats.len() + 1;
     let cat = Cat {
@@ -38,102 +53,280 @@ pub async fn create_one(new_cat: NewCat, source: &MockSource) -> Result<Cat, App
         age: new_cat.age,
         weight: new_cat.weight,
         creation_time: Utc::now(),
+        version: 1,
+        deletion_time: None,
     };
     cats.push(cat.clone());
+    audit::controller_mock::record(
+        NewAuditEntry::new(context, AuditAction::Create, RESOURCE, &cat.id.0)
+            .with_diff(None, Some(&cat)),
+        &mut *source.audit_log.write().await,
+    );
     Ok(cat)
 }
 
 pub async fn update_one(
     id: i32,
     update_cat: UpdateCat,
+    context: &AuditContext,
     source: &MockSource,
 ) -> Result<Cat, AppError> {
     let mut cats = source.cats.write().await;
 
-    cats.clone()
-        .into_iter()
-        .position(|cat| cat.id.0 == id.to_string())
-        .map_or_else(
-            || {
-                Err(AppError::new(Errors::Client(
-                    ClientError::ResourceNotFound {
-                        resource_name: "cats".into(),
-                        id: id.to_string(),
-                    },
-                )))
-            },
-            |index| {
-                let mut current_cat = cats[index].clone();
+    let index = position(&cats, id, false).ok_or_else(|| Cat::not_found(id))?;
+    let before = cats[index].clone();
+    if update_cat
+        .version
+        .is_some_and(|version| version != before.version)
+    {
+        return Err(Cat::conflict(id));
+    }
 
-                if update_cat.name.is_some() {
-                    current_cat.name = update_cat.name.clone().unwrap();
-                }
+    let mut current_cat = before.clone();
+    if let Some(name) = update_cat.name {
+        current_cat.name = name;
+    }
 
-                if update_cat.age.is_some() {
-                    current_cat.age = update_cat.age.unwrap();
-                }
+    if let Some(age) = update_cat.age {
+        current_cat.age = age;
+    }
 
-                if update_cat.weight.is_some() {
-                    current_cat.weight = update_cat.weight;
-                }
+    if update_cat.weight.is_some() {
+        current_cat.weight = update_cat.weight;
+    }
+    current_cat.version += 1;
 
-                cats[index] = current_cat.clone();
-                Ok(current_cat)
-            },
-        )
+    cats[index] = current_cat.clone();
+    audit::controller_mock::record(
+        NewAuditEntry::new(context, AuditAction::Update, RESOURCE, id)
+            .with_diff(Some(&before), Some(&current_cat)),
+        &mut *source.audit_log.write().await,
+    );
+    Ok(current_cat)
 }
 
 pub async fn replace_one(
     id: i32,
     replace_cat: ReplaceCat,
+    context: &AuditContext,
     source: &MockSource,
 ) -> Result<Cat, AppError> {
     let mut cats = source.cats.write().await;
 
-    cats.clone()
-        .into_iter()
-        .position(|cat| cat.id.0 == id.to_string())
-        .map_or_else(
-            || {
-                Err(AppError::new(Errors::Client(
-                    ClientError::ResourceNotFound {
-                        resource_name: "cats".into(),
-                        id: id.to_string(),
-                    },
-                )))
-            },
-            |index| {
-                let cat = Cat {
-                    id: CatId(id.to_string()),
-                    name: replace_cat.name.clone(),
-                    age: replace_cat.age,
-                    weight: replace_cat.weight,
-                    creation_time: cats[index].creation_time,
-                };
-                cats[index] = cat.clone();
-                Ok(cat)
-            },
-        )
+    let index = position(&cats, id, false).ok_or_else(|| Cat::not_found(id))?;
+    let before = cats[index].clone();
+    if replace_cat
+        .version
+        .is_some_and(|version| version != before.version)
+    {
+        return Err(Cat::conflict(id));
+    }
+    let cat = Cat {
+        id: CatId(id.to_string()),
+        name: replace_cat.name,
+        age: replace_cat.age,
+        weight: replace_cat.weight,
+        creation_time: before.creation_time,
+        version: before.version + 1,
+        deletion_time: None,
+    };
+
+    cats[index] = cat.clone();
+    audit::controller_mock::record(
+        NewAuditEntry::new(context, AuditAction::Replace, RESOURCE, id)
+            .with_diff(Some(&before), Some(&cat)),
+        &mut *source.audit_log.write().await,
+    );
+    Ok(cat)
 }
 
-pub async fn delete_one(id: i32, source: &MockSource) -> Result<String, AppError> {
+pub async fn delete_one(
+    id: i32,
+    version: Option<i32>,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<String, AppError> {
     let mut cats = source.cats.write().await;
 
-    cats.clone()
-        .into_iter()
-        .position(|cat| cat.id.0 == id.to_string())
-        .map_or_else(
-            || {
-                Err(AppError::new(Errors::Client(
-                    ClientError::ResourceNotFound {
-                        resource_name: "cats".into(),
-                        id: id.to_string(),
-                    },
-                )))
-            },
-            |index| {
-                cats.remove(index);
-                Ok("1 row deleted".to_string())
-            },
-        )
+    let index = position(&cats, id, false).ok_or_else(|| Cat::not_found(id))?;
+    let before = cats[index].clone();
+    if version.is_some_and(|version| version != before.version) {
+        return Err(Cat::conflict(id));
+    }
+    cats[index].deletion_time = Some(Utc::now());
+    cats[index].version += 1;
+
+    audit::controller_mock::record(
+        NewAuditEntry::new(context, AuditAction::Delete, RESOURCE, id)
+            .with_diff(Some(&before), None),
+        &mut *source.audit_log.write().await,
+    );
+    Ok("Cat moved to trash".to_string())
+}
+
+pub async fn select_trash(source: &MockSource) -> Result<Vec<Cat>, AppError> {
+    let mut trash: Vec<Cat> = source
+        .cats
+        .read()
+        .await
+        .iter()
+        .filter(|cat| cat.deletion_time.is_some())
+        .cloned()
+        .collect();
+    trash.sort_by_key(|cat| std::cmp::Reverse(cat.deletion_time));
+
+    Ok(trash)
+}
+
+pub async fn select_trashed_one(id: i32, source: &MockSource) -> Result<Cat, AppError> {
+    let cats = source.cats.read().await;
+
+    cats.iter()
+        .find(|cat| cat.id.0 == id.to_string() && cat.deletion_time.is_some())
+        .cloned()
+        .ok_or_else(|| Cat::not_found(id))
+}
+
+pub async fn restore_one(
+    id: i32,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<Cat, AppError> {
+    let mut cats = source.cats.write().await;
+
+    let index = position(&cats, id, true).ok_or_else(|| Cat::not_found(id))?;
+    let before = cats[index].clone();
+    let cat = &mut cats[index];
+    cat.deletion_time = None;
+    cat.version += 1;
+
+    audit::controller_mock::record(
+        NewAuditEntry::new(context, AuditAction::Restore, RESOURCE, id)
+            .with_diff(Some(&before), Some(&*cat)),
+        &mut *source.audit_log.write().await,
+    );
+    Ok(cat.clone())
+}
+
+pub async fn purge_one(
+    id: i32,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<String, AppError> {
+    let mut cats = source.cats.write().await;
+
+    let index = position(&cats, id, true).ok_or_else(|| Cat::not_found(id))?;
+    let before = cats.remove(index);
+
+    audit::controller_mock::record(
+        NewAuditEntry::new(context, AuditAction::Purge, RESOURCE, id)
+            .with_diff(Some(&before), None),
+        &mut *source.audit_log.write().await,
+    );
+    Ok("Cat purged".to_string())
+}
+
+pub async fn purge_expired(
+    deleted_before: DateTime<Utc>,
+    context: &AuditContext,
+    source: &MockSource,
+) -> Result<u64, AppError> {
+    let mut cats = source.cats.write().await;
+
+    let (purged, kept) = cats
+        .drain(..)
+        .partition(|cat| cat.deletion_time.is_some_and(|time| time < deleted_before));
+    *cats = kept;
+
+    let mut audit_log = source.audit_log.write().await;
+    for cat in &purged {
+        audit::controller_mock::record(
+            NewAuditEntry::new(context, AuditAction::Purge, RESOURCE, &cat.id.0)
+                .with_diff(Some(cat), None),
+            &mut audit_log,
+        );
+    }
+
+    Ok(purged.len() as u64)
+}
+
+/// Index of the cat, in the trash or not (`trashed`)
+fn position(cats: &[Cat], id: i32, trashed: bool) -> Option<usize> {
+    cats.iter()
+        .position(|cat| cat.id.0 == id.to_string() && cat.deletion_time.is_some() == trashed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_source::MockData;
+
+    use super::*;
+
+    fn test_source() -> MockSource {
+        MockSource::default().set(MockData::Cat(vec![Cat {
+            id: CatId("1".into()),
+            name: "A".into(),
+            age: 1,
+            weight: None,
+            creation_time: Utc::now(),
+            version: 2,
+            deletion_time: None,
+        }]))
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired_keeps_recent_trash() {
+        // Arrange
+        let cat = |id: &str, deleted_days_ago: Option<i64>| Cat {
+            id: CatId(id.into()),
+            name: id.into(),
+            age: 1,
+            weight: None,
+            creation_time: Utc::now(),
+            version: 1,
+            deletion_time: deleted_days_ago.map(|days| Utc::now() - chrono::Duration::days(days)),
+        };
+        let source = MockSource::default().set(MockData::Cat(vec![
+            cat("1", Some(40)),
+            cat("2", Some(10)),
+            cat("3", None),
+        ]));
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+
+        // Act
+        let purged = purge_expired(cutoff, &AuditContext::default(), &source)
+            .await
+            .unwrap();
+
+        // Assert
+        let cats = source.cats.read().await;
+        let audit_log = source.audit_log.read().await;
+        assert_eq!(purged, 1);
+        assert_eq!(cats.len(), 2);
+        assert!(cats.iter().all(|cat| cat.id.0 != "1"));
+        assert_eq!(audit_log.len(), 1);
+        assert_eq!(audit_log[0].action, "purge");
+        assert_eq!(audit_log[0].resource_id, "1");
+    }
+
+    #[tokio::test]
+    async fn test_delete_one_expected_version() {
+        // Arrange
+        let source = test_source();
+        let context = AuditContext::default();
+
+        // Act
+        let stale = delete_one(1, Some(1), &context, &source).await;
+        let current = delete_one(1, Some(2), &context, &source).await;
+
+        // Assert
+        assert!(matches!(
+            stale,
+            Err(AppError {
+                error: Errors::Client(ClientError::Conflict { .. })
+            })
+        ));
+        assert!(current.is_ok());
+        assert_eq!(source.audit_log.read().await.len(), 1);
+    }
 }
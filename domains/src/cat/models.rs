@@ -1,19 +1,35 @@
 use chrono::{DateTime, Utc};
+use common::etag;
+use errors::{AppError, ClientError, Errors};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    cat::{controller_db, controller_mock},
+    data_source::DataSource,
+};
+
+/// Resource name of the cats, in errors and the audit log
+pub const RESOURCE: &str = "cats";
 
 // Newtype idiom types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CatId(pub String);
 
 /// Cat struct
 /// Mostly to be serialized from db record to json
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Cat {
     pub id: CatId,
     pub name: String,
     pub age: i16,
     pub weight: Option<f32>,
     pub creation_time: DateTime<Utc>,
+    /// Incremented on each write, for optimistic concurrency
+    pub version: i32,
+    /// Set while the cat is in the trash, until restored or purged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deletion_time: Option<DateTime<Utc>>,
 }
 
 impl Cat {
@@ -21,42 +37,180 @@ impl Cat {
         let file = include_str!("./mock/cats.json");
         serde_json::from_str(file).expect("can't read cats.json")
     }
+
+    /// Strong ETag of the cat, as served by `GET /api/cats/{cat_id}/`
+    pub fn etag(&self) -> String {
+        etag::strong(self)
+    }
+
+    /// Fail with a precondition error when `if_match` doesn't match the `current` state of the cat
+    /// Returns the version the precondition holds for, the write must then apply to it
+    /// Without `If-Match` header the modification is unconditional (None)
+    pub fn check_if_match(
+        if_match: Option<&str>,
+        current: Option<&Cat>,
+        cat_id: i32,
+    ) -> Result<Option<i32>, AppError> {
+        match if_match {
+            Some(header) if !etag::match_hit(header, current.map(Cat::etag).as_deref()) => {
+                Err(Cat::precondition_failed(cat_id))
+            }
+            Some(_) => Ok(current.map(|cat| cat.version)),
+            None => Ok(None),
+        }
+    }
+
+    /// Version of the cat an `If-Match` precondition holds for, the write must then apply to it
+    /// None without precondition, a missing cat fails it but the other errors fail the request
+    pub async fn if_match_version(
+        if_match: Option<&str>,
+        data: &DataSource,
+        cat_id: i32,
+    ) -> Result<Option<i32>, AppError> {
+        let Some(if_match) = if_match else {
+            return Ok(None);
+        };
+        let current = match data
+            .exec_controller(
+                |data_source| Box::pin(controller_mock::select_one(cat_id, data_source)),
+                |data_source| Box::pin(controller_db::select_one(cat_id, data_source)),
+            )
+            .await
+        {
+            Ok(cat) => Some(cat),
+            Err(AppError {
+                error: Errors::Client(ClientError::ResourceNotFound { .. }),
+            }) => None,
+            Err(err) => return Err(err),
+        };
+        Cat::check_if_match(Some(if_match), current.as_ref(), cat_id)
+    }
+
+    /// Error of a write applying to the `expected` version
+    /// When that version is the `If-Match` one, another write landing after the check
+    /// fails the precondition (412) rather than conflicting (409)
+    pub fn if_match_error(
+        error: AppError,
+        if_match: Option<i32>,
+        expected: Option<i32>,
+        cat_id: i32,
+    ) -> AppError {
+        match error.error {
+            Errors::Client(ClientError::Conflict { .. })
+                if if_match.is_some() && if_match == expected =>
+            {
+                Cat::precondition_failed(cat_id)
+            }
+            _ => error,
+        }
+    }
+
+    /// Error of a cat missing (or trashed, outside of the trash routes)
+    pub fn not_found(cat_id: i32) -> AppError {
+        AppError::new(Errors::Client(ClientError::ResourceNotFound {
+            resource_name: RESOURCE.into(),
+            id: cat_id.to_string(),
+        }))
+    }
+
+    /// Error of a conditional write whose precondition doesn't hold (anymore)
+    pub fn precondition_failed(cat_id: i32) -> AppError {
+        AppError::new(Errors::Client(ClientError::PreconditionFailed {
+            resource_name: RESOURCE.into(),
+            id: cat_id.to_string(),
+        }))
+    }
+
+    /// Error of a write racing another one
+    pub fn conflict(cat_id: i32) -> AppError {
+        AppError::new(Errors::Client(ClientError::Conflict {
+            resource_name: RESOURCE.into(),
+            id: cat_id.to_string(),
+        }))
+    }
 }
 
 /// New Cat struct
 /// Mostly to be deserialized from json to db record
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct NewCat {
     pub name: String,
     pub age: i16,
     pub weight: Option<f32>,
 }
 
-/// Current Cat struct
-/// Mostly used when retrieving current record from the db before an update
-/// Unnecessary fields are removed (id, creation date etc.)
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct CurrentCat {
-    pub name: String,
-    pub age: i16,
-    pub weight: Option<f32>,
-}
-
 /// Update Cat struct
 /// Mostly to be deserialized from json to db record
 /// All the fields are optional
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct UpdateCat {
     pub name: Option<String>,
     pub age: Option<i16>,
     pub weight: Option<f32>,
+    /// Version the update applies to, 409 Conflict once the cat moved on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+}
+
+impl UpdateCat {
+    /// Apply to `version` unless the client already expects one
+    pub fn expecting(mut self, version: Option<i32>) -> Self {
+        self.version = self.version.or(version);
+        self
+    }
 }
 
 /// Replace Cat struct
 /// Mostly to be deserialized from json to db record
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ReplaceCat {
     pub name: String,
     pub age: i16,
     pub weight: Option<f32>,
+    /// Version the replacement applies to, 409 Conflict once the cat moved on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+}
+
+impl ReplaceCat {
+    /// Apply to `version` unless the client already expects one
+    pub fn expecting(mut self, version: Option<i32>) -> Self {
+        self.version = self.version.or(version);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_match_error_on_lost_race() {
+        // Arrange
+        let conflict = || Cat::conflict(1);
+
+        // Act
+        let if_match = Cat::if_match_error(conflict(), Some(3), Some(3), 1);
+        let body_version = Cat::if_match_error(conflict(), Some(3), Some(2), 1);
+        let unconditional = Cat::if_match_error(conflict(), None, Some(2), 1);
+        let not_found = Cat::if_match_error(Cat::not_found(1), Some(3), Some(3), 1);
+
+        // Assert
+        assert!(matches!(
+            if_match.error,
+            Errors::Client(ClientError::PreconditionFailed { .. })
+        ));
+        assert!(matches!(
+            body_version.error,
+            Errors::Client(ClientError::Conflict { .. })
+        ));
+        assert!(matches!(
+            unconditional.error,
+            Errors::Client(ClientError::Conflict { .. })
+        ));
+        assert!(matches!(
+            not_found.error,
+            Errors::Client(ClientError::ResourceNotFound { .. })
+        ));
+    }
 }
@@ -1,11 +1,41 @@
-use std::{future::Future, pin::Pin};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use errors::AppError;
-use setup::db_store::DbStore;
+use chrono::{DateTime, Utc};
+use common::metrics;
+use errors::{AppError, Errors, ServerError};
+use setup::{
+    config::{app_config::DataMode, db_config::DbConfig, runtime_config::SharedConfig},
+    db_store::DbStore,
+};
+use sqlx::{pool::PoolConnection, Postgres};
+use tokio::{sync::RwLock as TokioRwLock, task::JoinHandle};
 
-use tokio::sync::RwLock as TokioRwLock;
+use crate::{
+    account::models::Account,
+    audit::{
+        self,
+        models::{AuditContext, AuditEntry, NewAuditEntry},
+    },
+    cat::{self, models::Cat},
+    health::{
+        self,
+        models::{BuildInfo, Readiness},
+    },
+    migration,
+};
 
-use crate::{account::models::Account, cat::models::Cat};
+/// Delay between two automatic purges of the trash
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Delay before checking again a schema found behind once the database is reachable
+const SCHEMA_CHECK_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Debug)]
 pub enum SourceType {
@@ -16,6 +46,10 @@ pub enum SourceType {
 #[derive(Debug)]
 pub struct DataSource {
     pub source: SourceType,
+    /// Cleared on shutdown, so readiness checks fail before the server stops
+    serving: AtomicBool,
+    /// Set while the schema check waits for an unreachable database (degraded start)
+    schema_pending: Arc<AtomicBool>,
 }
 
 impl DataSource {
@@ -25,24 +59,184 @@ impl DataSource {
                 Some(d) => SourceType::Mock(d),
                 None => SourceType::Mock(MockSource::new()),
             },
+            serving: AtomicBool::new(true),
+            schema_pending: Arc::default(),
         }
     }
-    pub async fn db() -> Self {
-        Self {
-            source: SourceType::DB(DbSource::new().await),
+    pub async fn db(config: &DbConfig) -> Result<Self, AppError> {
+        Ok(Self {
+            source: SourceType::DB(DbSource::new(config).await?),
+            serving: AtomicBool::new(true),
+            schema_pending: Arc::default(),
+        })
+    }
+    /// Make sure the database schema is up to date before serving requests
+    /// Nothing to do for the mock source. For an unreachable database (degraded mode),
+    /// the check runs once it is reachable again, and data is not served until it passes
+    pub async fn prepare_schema(&self, auto_migrate: bool) -> Result<(), AppError> {
+        match &self.source {
+            SourceType::Mock(_) => Ok(()),
+            SourceType::DB(data_source) if !data_source.db.is_available() => {
+                eprintln!("⚠️ Database schema not checked yet, the database is unreachable");
+                self.defer_schema_check(auto_migrate);
+                Ok(())
+            }
+            SourceType::DB(data_source) => {
+                migration::prepare_schema(&data_source.db.connection, auto_migrate).await
+            }
+        }
+    }
+    /// Check the schema in the background once the database is reachable, again after a delay while it is behind
+    /// The task stops once the check passed or the pool is closed
+    fn defer_schema_check(&self, auto_migrate: bool) {
+        let SourceType::DB(data_source) = &self.source else {
+            return;
+        };
+        let db = data_source.db.clone();
+        let schema_pending = self.schema_pending.clone();
+        schema_pending.store(true, Ordering::Relaxed);
+
+        tokio::spawn(async move {
+            while !db.connection.is_closed() {
+                db.wait_available().await;
+                match migration::prepare_schema(&db.connection, auto_migrate).await {
+                    Ok(()) => {
+                        schema_pending.store(false, Ordering::Relaxed);
+                        println!("🛢️ Database schema checked, serving data");
+                        break;
+                    }
+                    Err(err) => {
+                        eprintln!("⚠️ Database schema not ready, data not served. {err}");
+                        tokio::time::sleep(SCHEMA_CHECK_RETRY_DELAY).await;
+                    }
+                }
+            }
+        });
+    }
+    /// Whether data can be served, false while the database is unreachable (degraded mode),
+    /// while its schema is not checked yet, or once the server is shutting down
+    pub fn is_ready(&self) -> bool {
+        if !self.serving.load(Ordering::Relaxed) {
+            return false;
+        }
+        match &self.source {
+            SourceType::Mock(_) => true,
+            SourceType::DB(data_source) => {
+                data_source.db.is_available() && !self.schema_pending.load(Ordering::Relaxed)
+            }
+        }
+    }
+    /// Readiness report, checking the database live (not the last known availability)
+    pub async fn readiness(&self) -> Readiness {
+        let serving = self.serving.load(Ordering::Relaxed);
+        match &self.source {
+            SourceType::Mock(_) => Readiness::new(serving, BuildInfo::new(DataMode::File), None),
+            SourceType::DB(data_source) => Readiness::new(
+                serving,
+                BuildInfo::new(DataMode::Database),
+                Some(health::controller_db::check(data_source).await),
+            ),
+        }
+    }
+    /// Update the database pool metrics before a scrape, nothing to sample for the mock source
+    pub async fn record_metrics(&self) {
+        if let SourceType::DB(data_source) = &self.source {
+            health::controller_db::record_pool_metrics(data_source);
+        }
+    }
+    /// Report not ready from now on (first step of a graceful shutdown)
+    pub fn stop_serving(&self) {
+        self.serving.store(false, Ordering::Relaxed);
+    }
+    /// Close the database pool once in-flight requests are done (last step of a graceful shutdown)
+    pub async fn close(&self) {
+        if let SourceType::DB(data_source) = &self.source {
+            data_source.db.connection.close().await;
         }
     }
+    /// Record an event without data mutation in the audit log (e.g a sign in)
+    /// The mutations record their entries themselves, in their transaction
+    pub async fn audit(&self, entry: NewAuditEntry) -> Result<AuditEntry, AppError> {
+        self.exec_controller(
+            |data_source| {
+                Box::pin(audit::controller_mock::insert_one(
+                    entry.clone(),
+                    data_source,
+                ))
+            },
+            |data_source| Box::pin(audit::controller_db::insert_one(entry.clone(), data_source)),
+        )
+        .await
+    }
+    /// Delete for good the cats trashed before `deleted_before`, returns how many were purged
+    /// The purges are audited without actor nor request (made by the server itself)
+    pub async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<u64, AppError> {
+        let context = AuditContext::default();
+        self.exec_controller(
+            |data_source| {
+                Box::pin(cat::controller_mock::purge_expired(
+                    deleted_before,
+                    &context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(cat::controller_db::purge_expired(
+                    deleted_before,
+                    &context,
+                    data_source,
+                ))
+            },
+        )
+        .await
+    }
+    /// Purge the trash hourly, of the cats kept longer than the retention of the runtime config
+    /// The retention is read on each purge (reloadable), the task stops once the server stops serving
+    pub fn spawn_trash_purge(self: Arc<Self>, runtime: SharedConfig) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if !self.serving.load(Ordering::Relaxed) {
+                    break;
+                }
+                // No purge without retention, nor with one reaching past the earliest date
+                let Some(deleted_before) = runtime
+                    .get()
+                    .options
+                    .trash_retention()
+                    .and_then(|retention| chrono::Duration::from_std(retention).ok())
+                    .and_then(|retention| Utc::now().checked_sub_signed(retention))
+                else {
+                    continue;
+                };
+                match self.purge_trash(deleted_before).await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::info!(count, "Trash purged"),
+                    Err(err) => tracing::warn!("Trash not purged. {err}"),
+                }
+            }
+        })
+    }
+    /// Run the controller of the current source
+    /// Fails with `ServerError::Unavailable` while the database is unreachable or its schema not checked yet
     pub fn exec_controller<'a, T, M, N>(
         &'a self,
         mock_fn: M,
         db_fn: N,
-    ) -> Pin<Box<(dyn Future<Output = Result<T, AppError>> + Send + 'a)>>
+    ) -> Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 'a>>
     where
-        M: Fn(&'a MockSource) -> Pin<Box<(dyn Future<Output = Result<T, AppError>> + Send + 'a)>>,
-        N: Fn(&'a DbSource) -> Pin<Box<(dyn Future<Output = Result<T, AppError>> + Send + 'a)>>,
+        M: Fn(&'a MockSource) -> Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 'a>>,
+        N: Fn(&'a DbSource) -> Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 'a>>,
     {
         match &self.source {
             SourceType::Mock(data_source) => mock_fn(data_source),
+            SourceType::DB(data_source)
+                if !data_source.db.is_available()
+                    || self.schema_pending.load(Ordering::Relaxed) =>
+            {
+                Box::pin(async { Err(AppError::new(Errors::Server(ServerError::Unavailable))) })
+            }
             SourceType::DB(data_source) => db_fn(data_source),
         }
     }
@@ -57,6 +251,7 @@ pub enum MockData {
 pub struct MockSource {
     pub accounts: TokioRwLock<Vec<Account>>,
     pub cats: TokioRwLock<Vec<Cat>>,
+    pub audit_log: TokioRwLock<Vec<AuditEntry>>,
 }
 
 impl MockSource {
@@ -64,6 +259,7 @@ impl MockSource {
         MockSource {
             accounts: TokioRwLock::new(Account::mock_data()),
             cats: TokioRwLock::new(Cat::mock_data()),
+            audit_log: TokioRwLock::default(),
         }
     }
     pub fn set(mut self, data: MockData) -> Self {
@@ -81,9 +277,54 @@ pub struct DbSource {
 }
 
 impl DbSource {
-    pub async fn new() -> Self {
-        DbSource {
-            db: DbStore::create_postgres_store().await,
+    pub async fn new(config: &DbConfig) -> Result<Self, AppError> {
+        Ok(DbSource {
+            db: DbStore::create_postgres_store(config).await?,
+        })
+    }
+
+    /// Connection of the pool for a query or a transaction, the wait for it is recorded in the pool metrics
+    pub async fn acquire(&self) -> Result<PoolConnection<Postgres>, sqlx::Error> {
+        let started = Instant::now();
+        let connection = self.db.connection.acquire().await;
+        metrics::record_pool_acquire(started.elapsed());
+        connection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test(migrations = false)]
+    async fn test_deferred_schema_check_holds_data_until_migrated(pool: PgPool) {
+        // Arrange
+        let data_source = DataSource {
+            source: SourceType::DB(DbSource {
+                db: DbStore::from_pool(pool.clone()),
+            }),
+            serving: AtomicBool::new(true),
+            schema_pending: Arc::default(),
+        };
+
+        // Act
+        data_source.defer_schema_check(true);
+        let pending = data_source.is_ready();
+        let mut ready = false;
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            ready = data_source.is_ready();
+            if ready {
+                break;
+            }
         }
+
+        // Assert
+        let status = migration::status(&pool).await.unwrap();
+        assert!(!pending);
+        assert!(ready);
+        assert!(status.iter().all(|migration| migration.applied));
     }
 }
@@ -0,0 +1,2 @@
+pub mod controller_db;
+pub mod models;
@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+use common::metrics;
+
+use crate::{
+    data_source::DbSource,
+    health::models::{DatabaseCheck, HealthStatus, MigrationCheck, PoolStats},
+    migration,
+};
+
+/// Time given to each database check before reporting it failed
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Run `SELECT 1` and compare the applied migrations against the embedded ones
+/// The database is unavailable when a check fails, times out, or a migration is pending
+pub async fn check(source: &DbSource) -> DatabaseCheck {
+    let pool = &source.db.connection;
+
+    let started = Instant::now();
+    let ping = tokio::time::timeout(CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let (latency_ms, error) = match ping {
+        Ok(Ok(_)) => (Some(latency_ms), None),
+        Ok(Err(err)) => (None, Some(err.to_string())),
+        Err(_) => (None, Some(format!("No answer within {CHECK_TIMEOUT:?}"))),
+    };
+
+    let migrations = match error {
+        Some(_) => None,
+        None => match tokio::time::timeout(CHECK_TIMEOUT, migration::status(pool)).await {
+            Ok(Ok(status)) => {
+                let pending: Vec<String> = status
+                    .into_iter()
+                    .filter(|migration| !migration.applied || migration.modified)
+                    .map(|migration| format!("{}_{}", migration.version, migration.description))
+                    .collect();
+                Some(MigrationCheck {
+                    up_to_date: pending.is_empty(),
+                    pending,
+                })
+            }
+            _ => None,
+        },
+    };
+
+    let status = match (&error, &migrations) {
+        (None, Some(migrations)) if migrations.up_to_date => HealthStatus::Ok,
+        _ => HealthStatus::Unavailable,
+    };
+
+    DatabaseCheck {
+        status,
+        latency_ms,
+        error,
+        pool: pool_stats(source),
+        migrations,
+    }
+}
+
+/// Sample the pool size, the acquire waits are recorded as queries get their connection
+/// (see `DbSource::acquire`)
+pub fn record_pool_metrics(source: &DbSource) {
+    let stats = pool_stats(source);
+    metrics::record_pool(stats.size, stats.idle, stats.max);
+}
+
+fn pool_stats(source: &DbSource) -> PoolStats {
+    let pool = &source.db.connection;
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+    let max = source.db.max_connections();
+    let saturation = match max {
+        0 => 0.0,
+        max => size.saturating_sub(idle) as f32 / max as f32,
+    };
+
+    PoolStats {
+        size,
+        idle,
+        max,
+        saturation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use setup::db_store::DbStore;
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_pool_metrics_sampled_without_acquiring(pool: PgPool) {
+        // Arrange
+        let source = DbSource {
+            db: DbStore::from_pool(pool),
+        };
+        drop(source.acquire().await.unwrap());
+        let size = source.db.connection.size();
+
+        // Act
+        record_pool_metrics(&source);
+
+        // Assert
+        let metrics = metrics::render();
+        assert_eq!(source.db.connection.size(), size);
+        assert!(metrics.contains(&format!(r#"db_pool_connections{{state="size"}} {size}"#)));
+        assert!(metrics.contains("db_pool_acquire_duration_seconds_count"));
+        assert!(!metrics.contains("db_pool_acquire_duration_seconds_count 0"));
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use setup::config::app_config::DataMode;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+}
+
+/// Build of the running instance
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Commit the binary was built from (GIT_SHA at build time, `unknown` outside a git checkout)
+    pub git_sha: String,
+    /// Active data mode (file or database)
+    pub data_mode: String,
+}
+
+impl BuildInfo {
+    pub fn new(data_mode: DataMode) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").into(),
+            git_sha: env!("GIT_SHA").into(),
+            data_mode: <&str>::from(data_mode).into(),
+        }
+    }
+}
+
+/// Connection pool usage, saturation being the share of connections in use out of the max
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: u32,
+    pub max: u32,
+    pub saturation: f32,
+}
+
+/// Migrations embedded in the binary not applied to the database (or modified since)
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MigrationCheck {
+    pub up_to_date: bool,
+    pub pending: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DatabaseCheck {
+    pub status: HealthStatus,
+    /// Round trip of the `SELECT 1` check
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+    pub pool: PoolStats,
+    /// Unknown while the database is unreachable
+    pub migrations: Option<MigrationCheck>,
+}
+
+/// Readiness report, unavailable when shutting down or when a dependency check fails
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readiness {
+    pub status: HealthStatus,
+    /// False once the server is shutting down
+    pub serving: bool,
+    pub build: BuildInfo,
+    /// Only checked in database mode
+    pub database: Option<DatabaseCheck>,
+}
+
+impl Readiness {
+    pub fn new(serving: bool, build: BuildInfo, database: Option<DatabaseCheck>) -> Self {
+        let database_ok = database
+            .as_ref()
+            .is_none_or(|database| database.status == HealthStatus::Ok);
+        let status = match serving && database_ok {
+            true => HealthStatus::Ok,
+            false => HealthStatus::Unavailable,
+        };
+        Self {
+            status,
+            serving,
+            build,
+            database,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status == HealthStatus::Ok
+    }
+}
@@ -1,4 +1,20 @@
+/// `sqlx::query!` (or `query_as!` with a record type) recording its statement
+/// as the `db.statement` field of the current controller span
+macro_rules! traced_query {
+    ($record:ident, $sql:literal $(, $args:expr)* $(,)?) => {{
+        tracing::Span::current().record("db.statement", $sql);
+        sqlx::query_as!($record, $sql $(, $args)*)
+    }};
+    ($sql:literal $(, $args:expr)* $(,)?) => {{
+        tracing::Span::current().record("db.statement", $sql);
+        sqlx::query!($sql $(, $args)*)
+    }};
+}
+
 pub mod account;
+pub mod audit;
 pub mod auth;
 pub mod cat;
 pub mod data_source;
+pub mod health;
+pub mod migration;
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use errors::{AppError, Errors, ServerError};
+use serde::Serialize;
+use sqlx::{
+    migrate::{AppliedMigration, Migrator},
+    PgPool,
+};
+
+/// Migrations of `domains/migrations`, embedded at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Postgres error code of a missing table
+const UNDEFINED_TABLE: &str = "42P01";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    /// Applied migration whose embedded sql has changed since
+    pub modified: bool,
+}
+
+/// Apply all pending migrations
+pub async fn migrate_up(pool: &PgPool) -> Result<(), AppError> {
+    MIGRATOR.run(pool).await?;
+    Ok(())
+}
+
+/// Revert applied migrations down to `target` (excluded)
+/// Without target, only the last applied migration is reverted
+pub async fn migrate_down(pool: &PgPool, target: Option<i64>) -> Result<(), AppError> {
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let applied = applied_migrations(pool).await?;
+            let mut versions: Vec<i64> = applied.iter().map(|m| m.version).collect();
+            versions.sort_unstable();
+            // Keep everything but the last applied migration
+            versions.pop();
+            versions.last().copied().unwrap_or(0)
+        }
+    };
+
+    MIGRATOR.undo(pool, target).await?;
+    Ok(())
+}
+
+/// Status of every embedded migration against the database
+pub async fn status(pool: &PgPool) -> Result<Vec<MigrationStatus>, AppError> {
+    let applied = applied_migrations(pool).await?;
+    Ok(compute_status(&MIGRATOR, &applied))
+}
+
+/// Check that the database schema is up to date, applying the pending migrations if `auto_migrate` is set
+pub async fn prepare_schema(pool: &PgPool, auto_migrate: bool) -> Result<(), AppError> {
+    if auto_migrate {
+        return migrate_up(pool).await;
+    }
+
+    let pending: Vec<String> = status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| !migration.applied || migration.modified)
+        .map(|migration| format!("{}_{}", migration.version, migration.description))
+        .collect();
+
+    match pending.is_empty() {
+        true => Ok(()),
+        false => Err(AppError::new(Errors::Server(ServerError::Migration {
+            reason: format!(
+                "Database schema is behind, pending migrations: {}. Run `wsctl migrate up` or set AUTO_MIGRATE=true.",
+                pending.join(", ")
+            ),
+        }))),
+    }
+}
+
+/// Migrations recorded by sqlx, read only (readiness probes run it): a missing table means none is applied
+async fn applied_migrations(pool: &PgPool) -> Result<Vec<AppliedMigration>, AppError> {
+    let applied = sqlx::query_as::<_, (i64, Vec<u8>)>(
+        "SELECT version, checksum FROM _sqlx_migrations ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await;
+
+    match applied {
+        Ok(applied) => Ok(applied
+            .into_iter()
+            .map(|(version, checksum)| AppliedMigration {
+                version,
+                checksum: checksum.into(),
+            })
+            .collect()),
+        Err(sqlx::Error::Database(err)) if err.code().as_deref() == Some(UNDEFINED_TABLE) => {
+            Ok(vec![])
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn compute_status(migrator: &Migrator, applied: &[AppliedMigration]) -> Vec<MigrationStatus> {
+    let applied: HashMap<i64, &AppliedMigration> = applied.iter().map(|m| (m.version, m)).collect();
+
+    migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| {
+            let applied_migration = applied.get(&migration.version);
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: applied_migration.is_some(),
+                modified: applied_migration
+                    .is_some_and(|applied| applied.checksum != migration.checksum),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::migrate::MigrationType;
+
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_reversible() {
+        let ups: Vec<i64> = MIGRATOR
+            .iter()
+            .filter(|m| m.migration_type == MigrationType::ReversibleUp)
+            .map(|m| m.version)
+            .collect();
+        let downs: Vec<&sqlx::migrate::Migration> = MIGRATOR
+            .iter()
+            .filter(|m| m.migration_type.is_down_migration())
+            .collect();
+
+        assert_eq!(ups.len(), 6);
+        assert_eq!(ups.len(), downs.len());
+        for down in downs {
+            assert!(ups.contains(&down.version));
+            assert!(!down.sql.trim().starts_with("-- None"));
+        }
+    }
+
+    #[test]
+    fn test_compute_status() {
+        let mut ups = MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration());
+        let first = ups.next().unwrap();
+        let second = ups.next().unwrap();
+        let applied = vec![
+            AppliedMigration {
+                version: first.version,
+                checksum: first.checksum.clone(),
+            },
+            AppliedMigration {
+                version: second.version,
+                checksum: vec![0].into(),
+            },
+        ];
+
+        let status = compute_status(&MIGRATOR, &applied);
+
+        assert_eq!(status.len(), 6);
+        assert!(status[0].applied && !status[0].modified);
+        assert!(status[1].applied && status[1].modified);
+        assert!(status[2..].iter().all(|s| !s.applied));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_status_without_migrations_table(pool: PgPool) {
+        // Act
+        let status = status(&pool).await.unwrap();
+
+        // Assert
+        assert_eq!(status.len(), 6);
+        assert!(status.iter().all(|migration| !migration.applied));
+        let table: Option<String> =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations')::text")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(table, None);
+    }
+}
@@ -1,7 +1,7 @@
-use std::{borrow::Cow, io};
+use std::{borrow::Cow, fmt, io};
 
 use actix_web::{error, http::StatusCode, HttpResponse};
-use common::ErrorPayload;
+use common::{metrics, ErrorPayload};
 use derive_more::{Display, Error};
 use validator::{ValidationErrors, ValidationErrorsKind};
 use warp::reject::Reject;
@@ -18,6 +18,21 @@ impl AppError {
     pub fn new(error: Errors) -> Self {
         Self { error }
     }
+
+    /// Kind and variant names of the error, as counted by the metrics
+    pub fn labels(&self) -> (&'static str, &'static str) {
+        match &self.error {
+            Errors::Client(error) => ("client", error.variant()),
+            Errors::Server(error) => ("server", error.variant()),
+            Errors::Config(error) => ("config", error.variant()),
+        }
+    }
+
+    /// Count the error returned to a client
+    pub fn record(&self) {
+        let (kind, variant) = self.labels();
+        metrics::record_error(kind, variant);
+    }
 }
 
 #[derive(Debug, Display, Clone)]
@@ -31,6 +46,12 @@ pub enum Errors {
 pub enum ServerError {
     #[display(fmt = "Internal error. Try again later.")]
     Internal,
+    #[display(fmt = "Database migration error. {}", reason)]
+    Migration { reason: String },
+    #[display(fmt = "Can't connect to the database. {}", reason)]
+    DbConnection { reason: String },
+    #[display(fmt = "Service unavailable. The database is unreachable, try again later.")]
+    Unavailable,
 }
 
 #[derive(Debug, Display, Error, Clone)]
@@ -48,6 +69,10 @@ pub enum ClientError {
     AccountAlreadyExists,
     #[display(fmt = "Can't parse json body.")]
     InvalidJson,
+    #[display(fmt = "Request body exceeds the {} bytes limit.", limit)]
+    PayloadTooLarge {
+        limit: u64,
+    },
     #[display(fmt = "Access forbidden. {}", reason)]
     Forbidden {
         reason: String,
@@ -60,9 +85,69 @@ pub enum ClientError {
     TokenNotFound,
     #[display(fmt = "Invalid Id provided.")]
     InvalidId,
+    #[display(
+        fmt = "Resource: {}/{} changed since it was fetched, If-Match doesn't match its ETag.",
+        resource_name,
+        id
+    )]
+    PreconditionFailed {
+        resource_name: String,
+        id: String,
+    },
+    #[display(
+        fmt = "Resource: {}/{} was modified meanwhile, fetch its current version and retry.",
+        resource_name,
+        id
+    )]
+    Conflict {
+        resource_name: String,
+        id: String,
+    },
     InvalidFields {
         errors: ValidationErrors,
     },
+    #[display(fmt = "The method is not allowed on this route.")]
+    MethodNotAllowed,
+    #[display(fmt = "Invalid request. {}", reason)]
+    InvalidRequest {
+        reason: String,
+    },
+    #[display(fmt = "Unsupported media type, the request body must be json.")]
+    UnsupportedMediaType,
+}
+
+impl ServerError {
+    fn variant(&self) -> &'static str {
+        match self {
+            ServerError::Internal => "Internal",
+            ServerError::Migration { .. } => "Migration",
+            ServerError::DbConnection { .. } => "DbConnection",
+            ServerError::Unavailable => "Unavailable",
+        }
+    }
+}
+
+impl ClientError {
+    fn variant(&self) -> &'static str {
+        match self {
+            ClientError::ResourceNotFound { .. } => "ResourceNotFound",
+            ClientError::RouteUnknown => "RouteUnknown",
+            ClientError::InvalidCredentials => "InvalidCredentials",
+            ClientError::AccountAlreadyExists => "AccountAlreadyExists",
+            ClientError::InvalidJson => "InvalidJson",
+            ClientError::PayloadTooLarge { .. } => "PayloadTooLarge",
+            ClientError::Forbidden { .. } => "Forbidden",
+            ClientError::Unauthorized { .. } => "Unauthorized",
+            ClientError::TokenNotFound => "TokenNotFound",
+            ClientError::InvalidId => "InvalidId",
+            ClientError::PreconditionFailed { .. } => "PreconditionFailed",
+            ClientError::Conflict { .. } => "Conflict",
+            ClientError::InvalidFields { .. } => "InvalidFields",
+            ClientError::MethodNotAllowed => "MethodNotAllowed",
+            ClientError::InvalidRequest { .. } => "InvalidRequest",
+            ClientError::UnsupportedMediaType => "UnsupportedMediaType",
+        }
+    }
 }
 
 #[derive(Debug, Display, Error, Clone)]
@@ -73,6 +158,97 @@ pub enum ConfigError {
     InvalidEnvMode { invalid_env_mode: String },
     #[display(fmt = "{} is not a valid data mode.", invalid_data_mode)]
     InvalidDataMode { invalid_data_mode: String },
+    #[display(fmt = "{} is not set.", key)]
+    MissingKey { key: String },
+    #[display(fmt = "{} does not contain a valid {}.", key, expected)]
+    InvalidValue { key: String, expected: String },
+    #[display(fmt = "Can't read the configuration file. {}", reason)]
+    UnreadableFile { reason: String },
+    #[display(
+        fmt = "No configuration file found in {}, set one with --config <path> or CONFIG_FILE.",
+        searched
+    )]
+    MissingFile { searched: String },
+    #[display(fmt = "Can't read the {} secret file. {}", key, reason)]
+    UnreadableSecretFile { key: String, reason: String },
+    #[display(fmt = "{} is not a usable TLS file. {}", key, reason)]
+    InvalidCertificate { key: String, reason: String },
+    #[display(fmt = "Invalid command line arguments. {}", reason)]
+    InvalidArguments { reason: String },
+    #[display(fmt = "Invalid configuration:\n{}", errors)]
+    Report { errors: ConfigErrors },
+}
+
+/// Every problem found while loading the configuration
+#[derive(Debug, Clone, Default)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl ConfigErrors {
+    /// Keep the error of `result` (flattening nested reports) and return its value if any
+    pub fn check<T>(&mut self, result: Result<T, ConfigError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(ConfigError::Report { errors }) => {
+                self.0.extend(errors.0);
+                None
+            }
+            Err(err) => {
+                self.0.push(err);
+                None
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Fail with a report of the collected errors, if any
+    pub fn into_result(self) -> Result<(), ConfigError> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self.into()),
+        }
+    }
+}
+
+impl ConfigError {
+    fn variant(&self) -> &'static str {
+        match self {
+            ConfigError::InvalidConfigSource { .. } => "InvalidConfigSource",
+            ConfigError::InvalidEnvMode { .. } => "InvalidEnvMode",
+            ConfigError::InvalidDataMode { .. } => "InvalidDataMode",
+            ConfigError::MissingKey { .. } => "MissingKey",
+            ConfigError::InvalidValue { .. } => "InvalidValue",
+            ConfigError::UnreadableFile { .. } => "UnreadableFile",
+            ConfigError::MissingFile { .. } => "MissingFile",
+            ConfigError::UnreadableSecretFile { .. } => "UnreadableSecretFile",
+            ConfigError::InvalidCertificate { .. } => "InvalidCertificate",
+            ConfigError::InvalidArguments { .. } => "InvalidArguments",
+            ConfigError::Report { .. } => "Report",
+        }
+    }
+}
+
+impl From<ConfigErrors> for ConfigError {
+    fn from(errors: ConfigErrors) -> Self {
+        ConfigError::Report { errors }
+    }
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self.0.iter().map(|err| format!("  - {err}")).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl From<ConfigError> for AppError {
+    fn from(err: ConfigError) -> Self {
+        AppError {
+            error: Errors::Config(err),
+        }
+    }
 }
 
 impl Reject for AppError {} // warp marker trait
@@ -94,12 +270,31 @@ impl From<validator::ValidationErrors> for AppError {
 }
 
 impl From<sqlx::Error> for AppError {
-    fn from(_err: sqlx::Error) -> Self {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            // The database can't be reached (yet)
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed => AppError {
+                error: Errors::Server(ServerError::Unavailable),
+            },
+            _ => AppError {
+                error: Errors::Server(ServerError::Internal),
+            },
+        }
+    }
+}
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(err: sqlx::migrate::MigrateError) -> Self {
         AppError {
-            error: Errors::Server(ServerError::Internal),
+            error: Errors::Server(ServerError::Migration {
+                reason: err.to_string(),
+            }),
         }
     }
 }
+
 impl From<jsonwebtoken::errors::Error> for AppError {
     fn from(_err: jsonwebtoken::errors::Error) -> Self {
         AppError {
@@ -132,9 +327,11 @@ impl From<io::Error> for AppError {
 pub const PASSWORD_BAD_FORMAT: &str = "Password must have 8 characters minimum, contains at least one uppercase letter, at least one lowercase letter, at least one number and at least one punctuation character";
 
 // Actix-web specific
-impl error::ResponseError for AppError {
-    fn error_response(&self) -> HttpResponse {
-        let payload = match &self.error {
+impl AppError {
+    /// Messages of the error payload, one per invalid field for a validation error
+    /// Shared by the actix-web and warp adapters
+    pub fn messages(&self) -> Vec<String> {
+        match &self.error {
             Errors::Client(ClientError::InvalidFields { errors }) => {
                 let errors = errors.errors().iter();
                 let mut return_errors: Vec<String> = vec![];
@@ -202,20 +399,35 @@ impl error::ResponseError for AppError {
                                             .to_string(),
                                         );
                                     }
-                                    _ => panic!(),
+                                    _ => return_errors.push(
+                                        FieldErrorMessages::Invalid {
+                                            field: field.to_string(),
+                                        }
+                                        .to_string(),
+                                    ),
                                 };
                             }
                         }
-                        _ => {
-                            unimplemented!()
-                        }
+                        // Nested struct or list of structs
+                        _ => return_errors.push(
+                            FieldErrorMessages::Invalid {
+                                field: field.to_string(),
+                            }
+                            .to_string(),
+                        ),
                     }
                 }
                 return_errors
             }
             _ => [self.to_string()].to_vec(),
-        };
-        HttpResponse::build(self.status_code()).json(ErrorPayload { errors: payload })
+        }
+    }
+}
+
+impl error::ResponseError for AppError {
+    fn error_response(&self) -> HttpResponse {
+        self.record();
+        HttpResponse::build(self.status_code()).json(ErrorPayload::new(self.messages()))
     }
 
     fn status_code(&self) -> StatusCode {
@@ -224,22 +436,27 @@ impl error::ResponseError for AppError {
             Errors::Client(ClientError::RouteUnknown) => StatusCode::NOT_FOUND,
             Errors::Client(ClientError::InvalidCredentials) => StatusCode::BAD_REQUEST,
             Errors::Client(ClientError::InvalidJson) => StatusCode::BAD_REQUEST,
+            Errors::Client(ClientError::PayloadTooLarge { .. }) => StatusCode::PAYLOAD_TOO_LARGE,
             Errors::Client(ClientError::Forbidden { .. }) => StatusCode::FORBIDDEN,
             Errors::Client(ClientError::Unauthorized { .. }) => StatusCode::UNAUTHORIZED,
             Errors::Client(ClientError::TokenNotFound) => StatusCode::UNAUTHORIZED,
             Errors::Client(ClientError::AccountAlreadyExists) => StatusCode::CONFLICT,
             Errors::Client(ClientError::InvalidId) => StatusCode::UNPROCESSABLE_ENTITY,
+            Errors::Client(ClientError::PreconditionFailed { .. }) => {
+                StatusCode::PRECONDITION_FAILED
+            }
+            Errors::Client(ClientError::Conflict { .. }) => StatusCode::CONFLICT,
             Errors::Client(ClientError::InvalidFields { .. }) => StatusCode::BAD_REQUEST,
+            Errors::Client(ClientError::MethodNotAllowed) => StatusCode::METHOD_NOT_ALLOWED,
+            Errors::Client(ClientError::InvalidRequest { .. }) => StatusCode::BAD_REQUEST,
+            Errors::Client(ClientError::UnsupportedMediaType) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             //
             Errors::Server(ServerError::Internal) => StatusCode::INTERNAL_SERVER_ERROR,
+            Errors::Server(ServerError::Migration { .. }) => StatusCode::INTERNAL_SERVER_ERROR,
+            Errors::Server(ServerError::DbConnection { .. }) => StatusCode::INTERNAL_SERVER_ERROR,
+            Errors::Server(ServerError::Unavailable) => StatusCode::SERVICE_UNAVAILABLE,
             //
-            Errors::Config(ConfigError::InvalidConfigSource { .. }) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
-            Errors::Config(ConfigError::InvalidEnvMode { .. }) => StatusCode::INTERNAL_SERVER_ERROR,
-            Errors::Config(ConfigError::InvalidDataMode { .. }) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            Errors::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
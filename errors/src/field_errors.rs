@@ -31,4 +31,6 @@ pub enum FieldErrorMessages {
     },
     #[display(fmt = "The field '{}' is not a valid email address.", field)]
     Email { field: String },
+    #[display(fmt = "The field '{}' is invalid.", field)]
+    Invalid { field: String },
 }
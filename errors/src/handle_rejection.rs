@@ -1,36 +1,134 @@
 use std::convert::Infallible;
 
+use actix_web::ResponseError;
 use common::ErrorPayload;
 use warp::body::BodyDeserializeError;
 use warp::cors::CorsForbidden;
+use warp::http::StatusCode;
+use warp::reject::{
+    InvalidHeader, InvalidQuery, MethodNotAllowed, MissingHeader, PayloadTooLarge,
+    UnsupportedMediaType,
+};
 
 use warp::{Rejection, Reply};
 
 use crate::{AppError, ClientError, Errors, ServerError};
 
 // Warp specific
+// Same statuses and payloads as the actix-web adapter
 pub async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Infallible> {
-    let payload;
+    let (error, status) = rejection_error(&rejection);
+    error.record();
+    let status = status.unwrap_or_else(|| {
+        StatusCode::from_u16(error.status_code().as_u16())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    });
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ErrorPayload::new(error.messages())),
+        status,
+    ))
+}
+
+/// Error of a rejection, with its status when it differs from the error one
+/// A rejection not handled here is an internal error
+fn rejection_error(rejection: &Rejection) -> (AppError, Option<StatusCode>) {
+    let client = |error| (AppError::new(Errors::Client(error)), None);
+    let invalid_request = |reason: String| client(ClientError::InvalidRequest { reason });
+
     if rejection.is_not_found() {
-        payload = [ClientError::RouteUnknown.to_string()].to_vec();
-    } else if let Some(AppError { error }) = rejection.find() {
-        payload = match error {
-            Errors::Client(ClientError::InvalidFields { errors }) => {
-                unimplemented!()
-            }
-            _ => [error.to_string()].to_vec(),
-        };
-    } else if let Some(_error) = rejection.find::<BodyDeserializeError>() {
-        payload = [ClientError::InvalidJson.to_string()].to_vec();
+        client(ClientError::RouteUnknown)
+    } else if let Some(app_error) = rejection.find::<AppError>() {
+        (app_error.clone(), None)
+    } else if rejection.find::<BodyDeserializeError>().is_some() {
+        client(ClientError::InvalidJson)
     } else if let Some(error) = rejection.find::<CorsForbidden>() {
-        payload = [ClientError::Forbidden {
+        client(ClientError::Forbidden {
             reason: error.to_string(),
-        }
-        .to_string()]
-        .to_vec();
+        })
+    } else if rejection.find::<MethodNotAllowed>().is_some() {
+        client(ClientError::MethodNotAllowed)
+    } else if let Some(error) = rejection.find::<InvalidHeader>() {
+        invalid_request(error.to_string())
+    } else if let Some(error) = rejection.find::<MissingHeader>() {
+        invalid_request(error.to_string())
+    } else if let Some(error) = rejection.find::<InvalidQuery>() {
+        invalid_request(error.to_string())
+    } else if rejection.find::<UnsupportedMediaType>().is_some() {
+        client(ClientError::UnsupportedMediaType)
+    } else if let Some(error) = rejection.find::<PayloadTooLarge>() {
+        // Limit set by a warp filter, not known here
+        let (error, _) = invalid_request(error.to_string());
+        (error, Some(StatusCode::PAYLOAD_TOO_LARGE))
     } else {
-        payload = [ServerError::Internal.to_string()].to_vec();
+        (AppError::new(Errors::Server(ServerError::Internal)), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use validator::{ValidationError, ValidationErrors};
+    use warp::{hyper::body::to_bytes, Filter};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_invalid_fields_bad_request() {
+        // Arrange
+        let mut errors = ValidationErrors::new();
+        errors.add("email", ValidationError::new("email"));
+        let rejection =
+            warp::reject::custom(AppError::new(Errors::Client(ClientError::InvalidFields {
+                errors,
+            })));
+
+        // Act
+        let response = handle_rejection(rejection).await.unwrap().into_response();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(
+            body.contains("The field 'email' is not a valid email address."),
+            "{body}"
+        );
     }
 
-    Ok(warp::reply::json(&ErrorPayload { errors: payload }))
+    #[tokio::test]
+    async fn test_warp_rejections_client_errors() {
+        // Arrange
+        let route = warp::path("cats")
+            .and(warp::post())
+            .and(warp::header::<u32>("x-count"))
+            .and(warp::body::content_length_limit(4))
+            .and(warp::body::json::<serde_json::Value>())
+            .map(|_, _| "ok")
+            .recover(handle_rejection);
+        let request = || warp::test::request().method("POST").path("/cats");
+
+        // Act
+        let wrong_method = request().method("PATCH").reply(&route).await;
+        let missing_header = request().reply(&route).await;
+        let invalid_header = request().header("x-count", "a").reply(&route).await;
+        let too_large = request()
+            .header("x-count", "1")
+            .header("content-type", "application/json")
+            .body("[1, 2, 3]")
+            .reply(&route)
+            .await;
+        let wrong_media = request()
+            .header("x-count", "1")
+            .header("content-type", "text/plain")
+            .body("[]")
+            .reply(&route)
+            .await;
+
+        // Assert
+        assert_eq!(wrong_method.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(missing_header.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(invalid_header.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(too_large.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(wrong_media.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
 }
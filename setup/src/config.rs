@@ -1,4 +1,15 @@
 pub mod app_config;
 pub mod auth_config;
+pub mod config_env;
+pub mod cors_options;
 pub mod db_config;
+pub mod db_options;
+pub mod layered_config;
+pub mod listen_options;
+pub mod runtime_config;
+pub mod secret;
+pub mod security_options;
 pub mod server_config;
+pub mod server_options;
+pub mod telemetry_options;
+pub mod tls_options;
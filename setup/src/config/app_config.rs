@@ -1,7 +1,12 @@
-use errors::AppError;
-use std::{env, str::FromStr};
+use errors::{ConfigError, ConfigErrors};
+use std::str::FromStr;
 
-use super::{db_config::DbConfig, server_config::ServerConfig};
+use crate::helpers;
+
+use super::{
+    config_env::ConfigEnv, db_config::DbConfig, layered_config::LayeredConfig,
+    server_config::ServerConfig,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct AppConfig {
@@ -11,10 +16,14 @@ pub struct AppConfig {
     pub database: DbConfig,
     /// Server and database config source (file, command, both, env variables)
     pub config_source: ConfigSource,
-    /// env mode (development or production)
+    /// env mode (development, staging, test or production), also selects the config file profile
     pub env_mode: EnvMode,
     /// data mode (file or database)
     pub data_mode: DataMode,
+    /// Apply pending database migrations on startup
+    pub auto_migrate: bool,
+    /// Merged config layers, when loaded from both sources (reports which layer supplied each value)
+    pub layers: Option<LayeredConfig>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -26,7 +35,7 @@ pub enum ConfigSource {
 }
 
 impl FromStr for ConfigSource {
-    type Err = AppError;
+    type Err = ConfigError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
@@ -34,34 +43,44 @@ impl FromStr for ConfigSource {
             "command_line" => Ok(Self::CommandLine),
             "both" => Ok(Self::Both),
             "env_var" => Ok(Self::EnvVar),
-            _invalid_source => panic!("Invalid config source"),
+            _invalid_source => Err(ConfigError::InvalidConfigSource {
+                invalid_source: s.into(),
+            }),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EnvMode {
     Development,
+    Staging,
+    Test,
     Production,
 }
 
 impl FromStr for EnvMode {
-    type Err = AppError;
+    type Err = ConfigError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "development" => Ok(Self::Development),
+            "staging" => Ok(Self::Staging),
+            "test" => Ok(Self::Test),
             "production" => Ok(Self::Production),
-            _invalid_env => panic!("Invalid env mode"),
+            _invalid_env => Err(ConfigError::InvalidEnvMode {
+                invalid_env_mode: s.into(),
+            }),
         }
     }
 }
 
-impl<'a> Into<&'a str> for EnvMode {
-    fn into(self) -> &'a str {
-        match self {
-            Self::Development => "development",
-            Self::Production => "production",
+impl From<EnvMode> for &str {
+    fn from(val: EnvMode) -> Self {
+        match val {
+            EnvMode::Development => "development",
+            EnvMode::Staging => "staging",
+            EnvMode::Test => "test",
+            EnvMode::Production => "production",
         }
     }
 }
@@ -73,22 +92,24 @@ pub enum DataMode {
 }
 
 impl FromStr for DataMode {
-    type Err = AppError;
+    type Err = ConfigError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "file" => Ok(Self::File),
             "database" => Ok(Self::Database),
-            _invalid_data_mode => panic!("Invalid data mode"),
+            _invalid_data_mode => Err(ConfigError::InvalidDataMode {
+                invalid_data_mode: s.into(),
+            }),
         }
     }
 }
 
-impl<'a> Into<&'a str> for DataMode {
-    fn into(self) -> &'a str {
-        match self {
-            Self::File => "file",
-            Self::Database => "Database",
+impl From<DataMode> for &str {
+    fn from(val: DataMode) -> Self {
+        match val {
+            DataMode::File => "file",
+            DataMode::Database => "database",
         }
     }
 }
@@ -96,101 +117,177 @@ impl<'a> Into<&'a str> for DataMode {
 const DEFAULT_ENV_MODE: EnvMode = EnvMode::Development;
 const DEFAULT_CONFIG_SOURCE: ConfigSource = ConfigSource::File;
 const DEFAULT_DATA_MODE: DataMode = DataMode::File;
+const DEFAULT_AUTO_MIGRATE: bool = false;
 
 impl AppConfig {
-    pub fn new() -> Result<Self, AppError> {
-        let env_mode = match env::var("ENV_MODE") {
-            Ok(mode) => EnvMode::from_str(mode.as_str())?,
-            Err(_) => DEFAULT_ENV_MODE,
-        };
+    /// Load the whole config from the process env and arguments
+    pub fn new() -> Result<Self, ConfigError> {
+        Self::from_env(&ConfigEnv::from_process())
+    }
 
-        let config_source = match env::var("CONFIG_SOURCE") {
-            Ok(source) => ConfigSource::from_str(source.as_str())?,
-            Err(_) => DEFAULT_CONFIG_SOURCE,
-        };
+    /// Load the whole config, every missing or invalid value is collected into one report
+    pub fn from_env(env: &ConfigEnv) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
 
-        let data_mode = match env::var("DATA_MODE") {
-            Ok(mode) => DataMode::from_str(mode.as_str())?,
-            Err(_) => DEFAULT_DATA_MODE,
+        let env_mode = errors
+            .check(env_or_default(env, "ENV_MODE", DEFAULT_ENV_MODE))
+            .unwrap_or(DEFAULT_ENV_MODE);
+
+        let config_source = errors
+            .check(env_or_default(env, "CONFIG_SOURCE", DEFAULT_CONFIG_SOURCE))
+            .unwrap_or(DEFAULT_CONFIG_SOURCE);
+
+        let data_mode = errors
+            .check(env_or_default(env, "DATA_MODE", DEFAULT_DATA_MODE))
+            .unwrap_or(DEFAULT_DATA_MODE);
+
+        let auto_migrate = match env.var("AUTO_MIGRATE") {
+            Some(flag) => matches!(flag.to_lowercase().as_str(), "true" | "1"),
+            None => DEFAULT_AUTO_MIGRATE,
         };
 
+        let with_db = data_mode == DataMode::Database;
+
         if env_mode == EnvMode::Production {
             // Force all configs to come from env variables
-            let server_config = ServerConfig::from_env_var();
-            let mut db_config = DbConfig::default();
-            if data_mode == DataMode::Database {
-                db_config = DbConfig::from_env_var();
-            }
+            let server_config = errors.check(ServerConfig::from_env_var(env));
+            let db_config = match with_db {
+                true => errors.check(DbConfig::from_env_var(env)),
+                false => Some(DbConfig::default()),
+            };
+            errors.into_result()?;
+
             return Ok(Self {
-                server: server_config,
-                database: db_config,
+                server: server_config.unwrap_or_default(),
+                database: db_config.unwrap_or_default(),
                 config_source: ConfigSource::EnvVar,
                 env_mode,
                 data_mode,
+                auto_migrate,
+                layers: None,
             });
         }
 
-        // Development Mode
-        let server_config;
-        let mut db_config = DbConfig::default();
-        match config_source {
-            ConfigSource::File => {
-                server_config = ServerConfig::from_file();
-                if data_mode == DataMode::Database {
-                    db_config = DbConfig::from_file();
-                }
-            }
+        // Development, staging and test modes
+        // The config file is overlaid by its env mode profile (e.g config.staging.toml)
+        let read_config_file = || helpers::read_config_file(env, env_mode.into());
+        let mut layers = None;
+        let (server_config, db_config) = match config_source {
+            ConfigSource::File => match errors.check(read_config_file()) {
+                Some(config_map) => (
+                    errors.check(ServerConfig::from_file(&config_map)),
+                    with_db.then(|| DbConfig::from_file(&config_map)),
+                ),
+                None => (None, None),
+            },
+            // Same flags as the command line layer (e.g --server-port), over the defaults
             ConfigSource::CommandLine => {
-                server_config = ServerConfig::from_command_line();
-                if data_mode == DataMode::Database {
-                    db_config = DbConfig::from_command_line();
+                match errors.check(LayeredConfig::from_command_line(env, env_mode)) {
+                    Some(layered_config) => (
+                        errors.check(ServerConfig::from_layers(&layered_config)),
+                        with_db.then(|| DbConfig::from_layers(&layered_config)),
+                    ),
+                    None => (None, None),
                 }
             }
             ConfigSource::Both => {
-                unimplemented!()
-            }
-            ConfigSource::EnvVar => {
-                server_config = ServerConfig::from_env_var();
-                if data_mode == DataMode::Database {
-                    db_config = DbConfig::from_env_var();
-                }
+                // Defaults, then file, then env variables, then command line
+                let layered_config = errors.check(read_config_file()).and_then(|config_map| {
+                    errors.check(LayeredConfig::load(env, env_mode, config_map))
+                });
+                let configs = match &layered_config {
+                    Some(layered_config) => (
+                        errors.check(ServerConfig::from_layers(layered_config)),
+                        with_db.then(|| DbConfig::from_layers(layered_config)),
+                    ),
+                    None => (None, None),
+                };
+                layers = layered_config;
+                configs
             }
-        }
-        let app_config = Self {
-            server: server_config,
-            database: db_config,
+            ConfigSource::EnvVar => (
+                errors.check(ServerConfig::from_env_var(env)),
+                with_db.then(|| DbConfig::from_env_var(env)),
+            ),
+        };
+        let db_config = match db_config {
+            Some(db_config) => errors.check(db_config),
+            None => Some(DbConfig::default()),
+        };
+        errors.into_result()?;
+
+        Ok(Self {
+            server: server_config.unwrap_or_default(),
+            database: db_config.unwrap_or_default(),
             config_source,
             env_mode,
             data_mode,
-        };
+            auto_migrate,
+            layers,
+        })
+    }
+}
 
-        println!("{:?}", app_config);
-        Ok(app_config)
+/// Mode read from an env variable, `default` when unset
+fn env_or_default<T: FromStr<Err = ConfigError>>(
+    env: &ConfigEnv,
+    name: &str,
+    default: T,
+) -> Result<T, ConfigError> {
+    match env.var(name) {
+        Some(value) => T::from_str(value),
+        None => Ok(default),
     }
 }
 
-/// WARN: Don't use parallelism when running these tests
-/// These tests would interfere with each other because they use env variables
-/// To execute them consecutively use --test-threads=1 (e.g cargo test -- --test-threads=1)
 #[cfg(test)]
 mod app_config_tests {
+    use std::{fs, net::IpAddr, path::Path};
+
+    use tempfile::TempDir;
+
     use super::*;
 
+    /// Write config files into a temp directory, removed when dropped
+    fn config_dir(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (file, content) in files {
+            fs::write(dir.path().join(file), content).unwrap();
+        }
+        dir
+    }
+
+    /// Environment looking the config file up in the setup crate
+    fn source_tree_env() -> ConfigEnv {
+        ConfigEnv::default().with_config_dirs(&[Path::new(env!("CARGO_MANIFEST_DIR"))])
+    }
+
+    fn production_env() -> ConfigEnv {
+        ConfigEnv::default()
+            .with_var("ENV_MODE", EnvMode::Production.into())
+            .with_var("LOG_LEVEL", "warn")
+            .with_var("SERVER_HOST_IP", "127.0.0.1")
+            .with_var("SERVER_PORT", "4500")
+    }
+
     #[test]
     fn test_default_config() {
         // Arrange
-        env::remove_var("ENV_MODE");
-        env::remove_var("CONFIG_SOURCE");
-        env::remove_var("DATA_MODE");
+        let env = source_tree_env();
         // Act
-        let config = AppConfig::new().unwrap();
+        let config = AppConfig::from_env(&env).unwrap();
         // Assert
         let expected = AppConfig {
-            server: ServerConfig::from_file(),
+            server: ServerConfig::from_file(
+                &helpers::read_config_file(&env, DEFAULT_ENV_MODE.into()).unwrap(),
+            )
+            .unwrap(),
             database: DbConfig::default(),
             config_source: DEFAULT_CONFIG_SOURCE,
             env_mode: DEFAULT_ENV_MODE,
             data_mode: DEFAULT_DATA_MODE,
+            auto_migrate: DEFAULT_AUTO_MIGRATE,
+            layers: None,
         };
         assert_eq!(config, expected);
     }
@@ -198,59 +295,166 @@ mod app_config_tests {
     #[test]
     fn test_production_unset_server_var_fail() {
         // Arrange
-        env::set_var::<&str, &str>("ENV_MODE", EnvMode::Production.into());
-        env::set_var("LOG_LEVEL", "");
-        env::set_var("SERVER_HOST_IP", "");
-        env::set_var("SERVER_PORT", "");
+        let env = ConfigEnv::default()
+            .with_var("ENV_MODE", EnvMode::Production.into())
+            .with_var("LOG_LEVEL", "")
+            .with_var("SERVER_HOST_IP", "")
+            .with_var("SERVER_PORT", "");
         // Act
-        let config = std::panic::catch_unwind(|| AppConfig::new());
+        let config = AppConfig::from_env(&env);
         // Assert
-        assert!(config.is_err());
+        assert!(matches!(config, Err(ConfigError::Report { .. })));
     }
 
     #[test]
     fn test_production_unset_database_var_fail() {
         // Arrange
-        env::set_var::<&str, &str>("ENV_MODE", EnvMode::Production.into());
-        env::set_var("LOG_LEVEL", "warn");
-        env::set_var("SERVER_HOST_IP", "127.0.0.1");
-        env::set_var("SERVER_PORT", "4500");
-        env::set_var::<&str, &str>("DATA_MODE", DataMode::Database.into());
-        env::set_var("DATABASE_USER", "");
-        env::set_var("DATABASE_PASSWORD", "");
-        env::set_var("DATABASE_PORT", "");
-        env::set_var("DATABASE_HOST", "");
-        env::set_var("DATABASE_NAME", "");
+        let env = production_env()
+            .with_var("DATA_MODE", DataMode::Database.into())
+            .with_var("DATABASE_USER", "")
+            .with_var("DATABASE_PASSWORD", "")
+            .with_var("DATABASE_PORT", "")
+            .with_var("DATABASE_HOST", "")
+            .with_var("DATABASE_NAME", "");
         // Act
-        let config = std::panic::catch_unwind(|| AppConfig::new());
+        let config = AppConfig::from_env(&env);
         // Assert
-        assert!(config.is_err());
+        assert!(matches!(config, Err(ConfigError::Report { .. })));
     }
 
     #[test]
     fn test_production_with_db_source() {
         // Arrange
-        env::set_var::<&str, &str>("ENV_MODE", EnvMode::Production.into());
-        env::set_var("LOG_LEVEL", "warn");
-        env::set_var("SERVER_HOST_IP", "127.0.0.1");
-        env::set_var("SERVER_PORT", "4500");
-        env::set_var::<&str, &str>("DATA_MODE", DataMode::Database.into());
-        env::set_var("DATABASE_USER", "user");
-        env::set_var("DATABASE_PASSWORD", "password");
-        env::set_var("DATABASE_PORT", "6889");
-        env::set_var("DATABASE_HOST", "localhost");
-        env::set_var("DATABASE_NAME", "test");
+        let env = production_env()
+            .with_var("DATA_MODE", DataMode::Database.into())
+            .with_var("DATABASE_USER", "user")
+            .with_var("DATABASE_PASSWORD", "password")
+            .with_var("DATABASE_PORT", "6889")
+            .with_var("DATABASE_HOST", "localhost")
+            .with_var("DATABASE_NAME", "test");
         // Act
-        let config = AppConfig::new().unwrap();
-        // Assert
+        let config = AppConfig::from_env(&env).unwrap();
         // Assert
         let expected = AppConfig {
-            server: ServerConfig::from_env_var(),
-            database: DbConfig::from_env_var(),
+            server: ServerConfig::from_env_var(&env).unwrap(),
+            database: DbConfig::from_env_var(&env).unwrap(),
             config_source: ConfigSource::EnvVar,
             env_mode: EnvMode::Production,
             data_mode: DataMode::Database,
+            auto_migrate: DEFAULT_AUTO_MIGRATE,
+            layers: None,
         };
         assert_eq!(config, expected);
     }
+
+    #[test]
+    fn test_both_sources_layered() {
+        // Arrange
+        let env = source_tree_env()
+            .with_var("CONFIG_SOURCE", "both")
+            .with_var("SERVER_PORT", "4600")
+            .with_args(&["server", "--log-level", "error"]);
+        // Act
+        let config = AppConfig::from_env(&env).unwrap();
+        // Assert
+        assert_eq!(config.server.port, 4600);
+        assert_eq!(config.server.log_level, "error");
+        assert!(config.layers.is_some());
+    }
+
+    #[test]
+    fn test_invalid_modes_reported_together() {
+        // Arrange
+        let env = source_tree_env()
+            .with_var("ENV_MODE", "unknown")
+            .with_var("DATA_MODE", "unknown");
+        // Act
+        let config = AppConfig::from_env(&env);
+        // Assert
+        match config {
+            Err(ConfigError::Report { errors }) => assert_eq!(errors.0.len(), 2),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_config_file_profile_overlay() {
+        // Arrange
+        let dir = config_dir(&[
+            (
+                "config.yaml",
+                "log_level: info\nserver_host_ip: 127.0.0.1\nserver_port: 4700\n",
+            ),
+            ("config.staging.yaml", "server_port: 4800\n"),
+        ]);
+        let path = dir.path().join("config.yaml");
+        let env = ConfigEnv::default().with_var("CONFIG_FILE", path.to_str().unwrap());
+        // Act
+        let staging = AppConfig::from_env(&env.clone().with_var("ENV_MODE", "staging")).unwrap();
+        let test = AppConfig::from_env(&env.with_var("ENV_MODE", "test")).unwrap();
+        // Assert
+        assert_eq!(staging.server.port, 4800);
+        assert_eq!(test.server.port, 4700);
+        assert_eq!(test.env_mode, EnvMode::Test);
+    }
+
+    #[test]
+    fn test_config_file_argument() {
+        // Arrange
+        let dir = config_dir(&[(
+            "ws.json",
+            r#"{"log_level": "warn", "server_host_ip": "0.0.0.0", "server_port": 4900}"#,
+        )]);
+        let path = dir.path().join("ws.json");
+        let env = ConfigEnv::default()
+            .with_var("CONFIG_FILE", "/missing/config.toml")
+            .with_args(&["server", "--config", path.to_str().unwrap()]);
+        // Act
+        let config = AppConfig::from_env(&env).unwrap();
+        // Assert
+        assert_eq!(config.server.host_ip, IpAddr::from([0, 0, 0, 0]));
+        assert_eq!(config.server.port, 4900);
+    }
+
+    #[test]
+    fn test_missing_config_file_fail() {
+        // Arrange
+        let env = ConfigEnv::default().with_var("CONFIG_FILE", "/missing/config.toml");
+        // Act
+        let config = AppConfig::from_env(&env);
+        // Assert
+        assert!(matches!(
+            config,
+            Err(ConfigError::Report { errors }) if matches!(errors.0[..], [ConfigError::UnreadableFile { .. }])
+        ));
+    }
+
+    #[test]
+    fn test_config_file_looked_up_in_config_dirs() {
+        // Arrange
+        let empty = config_dir(&[]);
+        let dir = config_dir(&[(
+            "config.yaml",
+            "log_level: info\nserver_host_ip: 127.0.0.1\nserver_port: 5100\n",
+        )]);
+        let env = ConfigEnv::default().with_config_dirs(&[empty.path(), dir.path()]);
+        // Act
+        let config = AppConfig::from_env(&env).unwrap();
+        // Assert
+        assert_eq!(config.server.port, 5100);
+    }
+
+    #[test]
+    fn test_no_config_file_found_fail() {
+        // Arrange
+        let empty = config_dir(&[]);
+        let env = ConfigEnv::default().with_config_dirs(&[empty.path()]);
+        // Act
+        let config = AppConfig::from_env(&env);
+        // Assert
+        assert!(matches!(
+            config,
+            Err(ConfigError::Report { errors }) if matches!(errors.0[..], [ConfigError::MissingFile { .. }])
+        ));
+    }
 }
@@ -1,16 +1,21 @@
-use std::env;
-
 use chrono::{Duration, Utc};
-use errors::{AppError, ClientError, Errors};
+use errors::{AppError, ClientError, ConfigError, Errors};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 
-use crate::APP_CONFIG;
+use super::{
+    app_config::AppConfig, config_env::ConfigEnv, layered_config, secret::Secret,
+    server_config::ServerConfig,
+};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct AuthConfig {
     /// Jwt token secret
-    jwt_secret: String,
+    jwt_secret: Secret,
+    /// Token issuer (web server name)
+    issuer: String,
+    /// Token audience (api url)
+    audience: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,16 +29,29 @@ pub struct Claims {
 }
 
 impl AuthConfig {
-    pub fn new() -> Self {
-        let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET not set");
+    pub fn new(jwt_secret: &str, issuer: &str, server: &ServerConfig) -> Self {
+        Self {
+            jwt_secret: Secret::new(jwt_secret),
+            issuer: issuer.into(),
+            audience: format!("{}/api/", server.format_url()),
+        }
+    }
+
+    /// Secret read from the config layers when loaded, from the JWT_SECRET env variable otherwise
+    pub fn from_env(env: &ConfigEnv, app_config: &AppConfig) -> Result<Self, ConfigError> {
+        let jwt_secret = match &app_config.layers {
+            Some(layers) => layers.require(layered_config::JWT_SECRET)?,
+            None => env.value("JWT_SECRET")?,
+        };
+        let issuer = env.var("WEB_SERVER").unwrap_or("wsstudy");
 
-        Self { jwt_secret }
+        Ok(Self::new(&jwt_secret, issuer, &app_config.server))
     }
 
     pub fn decode_claims(&self, token: &str) -> Result<Claims, AppError> {
         match decode::<Claims>(
             token,
-            &DecodingKey::from_secret(self.jwt_secret.as_ref()),
+            &DecodingKey::from_secret(self.jwt_secret.expose().as_ref()),
             &Validation::default(),
         ) {
             Ok(c) => Ok(c.claims),
@@ -49,17 +67,17 @@ impl AuthConfig {
         let exp = (now + Duration::minutes(60)).timestamp() as usize;
         let claims: Claims = Claims {
             iat,
-            iss: env::var("WEB_SERVER").map_or_else(|_| "wsstudy".to_string(), |v| v),
+            iss: self.issuer.clone(),
             sub: entity_id,
             exp,
-            aud: Some(format!("{}/api/", &APP_CONFIG.server.format_url())),
+            aud: Some(self.audience.clone()),
             nbf: None,
         };
 
         let token = encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_ref()),
+            &EncodingKey::from_secret(self.jwt_secret.expose().as_ref()),
         )?;
 
         Ok(token)
@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use errors::ConfigError;
+
+const CONFIG_FLAG: &str = "--config";
+const FILE_SUFFIX: &str = "_FILE";
+
+/// Env variables and command line arguments the config is built from
+/// Built from the process by default, or by hand so tests don't depend on (nor mutate) the process env
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigEnv {
+    vars: HashMap<String, String>,
+    /// Command line arguments, including the program name
+    args: Vec<String>,
+    /// Directories a `config.*` file is looked up in when none is set
+    config_dirs: Vec<PathBuf>,
+}
+
+impl ConfigEnv {
+    /// The config file is looked up next to the executable, then in the working directory
+    pub fn from_process() -> Self {
+        let exe_dir = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        Self {
+            vars: env::vars().collect(),
+            args: env::args().collect(),
+            config_dirs: [exe_dir, env::current_dir().ok()]
+                .into_iter()
+                .flatten()
+                .collect(),
+        }
+    }
+
+    pub fn with_var(mut self, name: &str, value: &str) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args = args.iter().map(|arg| arg.to_string()).collect();
+        self
+    }
+
+    pub fn with_config_dirs(mut self, dirs: &[&Path]) -> Self {
+        self.config_dirs = dirs.iter().map(|dir| dir.to_path_buf()).collect();
+        self
+    }
+
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    /// Value of an env variable that must be set, empty variables are considered unset
+    /// Otherwise read from the file set by its `*_FILE` variant (e.g Docker or Kubernetes secrets)
+    pub fn value(&self, name: &str) -> Result<String, ConfigError> {
+        let file_name = format!("{name}{FILE_SUFFIX}");
+        match (self.var(name), self.var(&file_name)) {
+            (Some(value), _) if !value.is_empty() => Ok(value.to_owned()),
+            (_, Some(path)) if !path.is_empty() => fs::read_to_string(path)
+                .map(|content| content.trim_end_matches(['\r', '\n']).to_owned())
+                .map_err(|err| ConfigError::UnreadableSecretFile {
+                    key: file_name,
+                    reason: err.to_string(),
+                }),
+            _ => Err(ConfigError::MissingKey { key: name.into() }),
+        }
+    }
+
+    /// Whether an env variable or its `*_FILE` variant is set
+    pub fn is_set(&self, name: &str) -> bool {
+        [name.to_owned(), format!("{name}{FILE_SUFFIX}")]
+            .iter()
+            .any(|name| self.var(name).is_some_and(|value| !value.is_empty()))
+    }
+
+    /// Files set by the `*_FILE` variables
+    pub fn secret_files(&self) -> Vec<PathBuf> {
+        self.vars
+            .iter()
+            .filter(|(name, path)| name.ends_with(FILE_SUFFIX) && !path.is_empty())
+            .map(|(_, path)| PathBuf::from(path))
+            .collect()
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.vars
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn config_dirs(&self) -> &[PathBuf] {
+        &self.config_dirs
+    }
+
+    /// Config file set by the `--config <path>` argument or the CONFIG_FILE env variable
+    pub fn config_path(&self) -> Option<String> {
+        let from_args = self.args.iter().enumerate().find_map(|(index, arg)| {
+            match arg.strip_prefix(CONFIG_FLAG) {
+                Some("") => self.args.get(index + 1).cloned(),
+                Some(value) => value.strip_prefix('=').map(str::to_owned),
+                None => None,
+            }
+        });
+
+        from_args.or_else(|| self.var("CONFIG_FILE").map(str::to_owned))
+    }
+
+    /// Arguments without the `--config` one, left to the command line config parsers
+    pub fn args_without_config(&self) -> Vec<String> {
+        let mut args = vec![];
+        let mut skip_value = false;
+        for arg in &self.args {
+            match arg.strip_prefix(CONFIG_FLAG) {
+                _ if skip_value => skip_value = false,
+                Some("") => skip_value = true,
+                Some(value) if value.starts_with('=') => {}
+                _ => args.push(arg.clone()),
+            }
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_path() {
+        // Arrange
+        let env = ConfigEnv::default().with_var("CONFIG_FILE", "/etc/ws/config.yaml");
+        let with_arg =
+            env.clone()
+                .with_args(&["server", "--config", "ws.json", "--server-port=80"]);
+        let with_inline_arg = env.clone().with_args(&["server", "--config=ws.toml"]);
+
+        // Act & Assert
+        assert_eq!(env.config_path().as_deref(), Some("/etc/ws/config.yaml"));
+        assert_eq!(with_arg.config_path().as_deref(), Some("ws.json"));
+        assert_eq!(
+            with_arg.args_without_config(),
+            vec!["server", "--server-port=80"]
+        );
+        assert_eq!(with_inline_arg.config_path().as_deref(), Some("ws.toml"));
+        assert_eq!(with_inline_arg.args_without_config(), vec!["server"]);
+    }
+
+    #[test]
+    fn test_value_from_file() {
+        // Arrange
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ws_secret");
+        fs::write(&path, "file_secret\n").unwrap();
+        let env = ConfigEnv::default()
+            .with_var("JWT_SECRET_FILE", path.to_str().unwrap())
+            .with_var("DATABASE_PASSWORD_FILE", "/missing/secret");
+
+        // Act & Assert
+        assert_eq!(env.value("JWT_SECRET").unwrap(), "file_secret");
+        assert!(env.is_set("JWT_SECRET"));
+        assert!(matches!(
+            env.value("DATABASE_PASSWORD"),
+            Err(ConfigError::UnreadableSecretFile { .. })
+        ));
+        assert!(matches!(
+            env.value("DATABASE_USER"),
+            Err(ConfigError::MissingKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+
+use crate::helpers;
+
+use super::layered_config;
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_CORS_ORIGINS)
+pub const ORIGINS: &str = "server_cors_origins";
+pub const METHODS: &str = "server_cors_methods";
+pub const HEADERS: &str = "server_cors_headers";
+pub const CREDENTIALS: &str = "server_cors_credentials";
+pub const MAX_AGE: &str = "server_cors_max_age_secs";
+
+pub const DEFAULT_ORIGINS: &str = "http://localhost:8080";
+pub const DEFAULT_METHODS: &str = "GET,POST,PATCH,PUT,DELETE";
+pub const DEFAULT_HEADERS: &str =
+    "content-type,authorization,accept,x-request-id,if-match,if-none-match";
+pub const DEFAULT_CREDENTIALS: &str = "true";
+pub const DEFAULT_MAX_AGE: &str = "3600";
+
+const ORIGINS_EXPECTED: &str =
+    "comma separated origins, `*` or scheme://host[:port] where host may start with `*.`";
+const METHODS_EXPECTED: &str = "comma separated http methods (e.g GET,POST)";
+const HEADERS_EXPECTED: &str = "comma separated header names (e.g content-type,authorization)";
+const CREDENTIALS_EXPECTED: &str = "boolean";
+const CREDENTIALS_ORIGINS_EXPECTED: &str =
+    "explicit origins when credentials are allowed, `*` would let any site send credentialed requests";
+const MAX_AGE_EXPECTED: &str = "duration (u64)";
+
+/// Cross-origin policy shared by the actix and warp servers
+/// Origins are checked on each request and can be reloaded,
+/// the other settings are part of the preflight layer built at startup
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CorsOptions {
+    /// Origins allowed to send cross-origin requests, `*` allows any origin (only without credentials)
+    /// and `https://*.cats.dev` any subdomain of cats.dev
+    #[serde(rename = "cors_origins")]
+    pub origins: Vec<String>,
+    /// Methods allowed in cross-origin requests
+    #[serde(rename = "cors_methods")]
+    pub methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests
+    #[serde(rename = "cors_headers")]
+    pub headers: Vec<String>,
+    /// Whether cross-origin requests may carry cookies and authorization headers
+    #[serde(rename = "cors_credentials")]
+    pub credentials: bool,
+    /// Seconds browsers may cache a preflight response
+    #[serde(rename = "cors_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsOptions {
+    fn default() -> Self {
+        Self::from_lookup(|_| None).expect("Default cors options are valid")
+    }
+}
+
+impl CorsOptions {
+    /// Options read with `lookup` (by option key), defaults for the unset ones
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let value = |key: &str| layered_config::value_or_default(&lookup, key);
+        let mut errors = ConfigErrors::default();
+
+        let origins = errors.check(parse_list(
+            ORIGINS,
+            &value(ORIGINS),
+            ORIGINS_EXPECTED,
+            |origin| is_valid_origin(origin).then(|| origin.to_owned()),
+        ));
+        let methods = errors.check(parse_list(
+            METHODS,
+            &value(METHODS),
+            METHODS_EXPECTED,
+            |method| {
+                method
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic())
+                    .then(|| method.to_ascii_uppercase())
+            },
+        ));
+        let headers = errors.check(parse_list(
+            HEADERS,
+            &value(HEADERS),
+            HEADERS_EXPECTED,
+            |header| {
+                header
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                    .then(|| header.to_ascii_lowercase())
+            },
+        ));
+        let credentials = errors.check(helpers::parse_value(
+            CREDENTIALS,
+            value(CREDENTIALS),
+            CREDENTIALS_EXPECTED,
+        ));
+        let max_age_secs = errors.check(helpers::parse_value(
+            MAX_AGE,
+            value(MAX_AGE),
+            MAX_AGE_EXPECTED,
+        ));
+
+        // Browsers get the request origin echoed back, `*` would then expose credentials to any site
+        let origins = match (origins, credentials) {
+            (Some(origins), Some(true)) if origins.iter().any(|origin| origin == "*") => errors
+                .check(Err(ConfigError::InvalidValue {
+                    key: ORIGINS.into(),
+                    expected: CREDENTIALS_ORIGINS_EXPECTED.into(),
+                })),
+            (origins, _) => origins,
+        };
+
+        match (origins, methods, headers, credentials, max_age_secs) {
+            (
+                Some(origins),
+                Some(methods),
+                Some(headers),
+                Some(credentials),
+                Some(max_age_secs),
+            ) => Ok(Self {
+                origins,
+                methods,
+                headers,
+                credentials,
+                max_age_secs,
+            }),
+            _ => Err(errors.into()),
+        }
+    }
+
+    /// Whether cross-origin requests from `origin` are allowed
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.origins
+            .iter()
+            .any(|allowed| origin_matches(allowed, origin))
+    }
+
+    /// Whether both policies build the same preflight layer (origins aside, they are checked live)
+    pub fn same_layer(&self, other: &Self) -> bool {
+        self.methods == other.methods
+            && self.headers == other.headers
+            && self.credentials == other.credentials
+            && self.max_age_secs == other.max_age_secs
+    }
+}
+
+/// Items of a comma separated list mapped by `parse`, which returns None for an invalid item
+fn parse_list(
+    key: &str,
+    value: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<String>, ConfigError> {
+    helpers::split_list(value)
+        .iter()
+        .map(|item| parse(item))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| ConfigError::InvalidValue {
+            key: key.into(),
+            expected: expected.into(),
+        })
+}
+
+/// `*` or `scheme://host[:port]`, the host may be a `*.` subdomain pattern
+fn is_valid_origin(origin: &str) -> bool {
+    if origin == "*" {
+        return true;
+    }
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host);
+
+    !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphabetic())
+        && !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && port.is_none_or(|port| port.parse::<u16>().is_ok())
+}
+
+/// `https://*.cats.dev` matches the subdomains of cats.dev (e.g `https://a.cats.dev`) but not cats.dev itself
+fn origin_matches(allowed: &str, origin: &str) -> bool {
+    match allowed.split_once("://*.") {
+        _ if allowed == "*" || allowed == origin => true,
+        Some((scheme, domain)) => origin
+            .strip_prefix(scheme)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .and_then(|host| host.strip_suffix(domain))
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cors_options_are_normalized() {
+        // Act
+        let options = CorsOptions::from_lookup(|key| match key {
+            ORIGINS => Some(" http://a.dev, ,https://*.cats.dev:8443".into()),
+            METHODS => Some("get, patch".into()),
+            HEADERS => Some("Authorization".into()),
+            CREDENTIALS => Some("false".into()),
+            _ => None,
+        })
+        .unwrap();
+
+        // Assert
+        assert_eq!(options.origins, ["http://a.dev", "https://*.cats.dev:8443"]);
+        assert_eq!(options.methods, ["GET", "PATCH"]);
+        assert_eq!(options.headers, ["authorization"]);
+        assert!(!options.credentials);
+        assert_eq!(options.max_age_secs, 3600);
+    }
+
+    #[test]
+    fn test_invalid_cors_options_are_reported_together() {
+        // Act
+        let options = CorsOptions::from_lookup(|key| match key {
+            ORIGINS => Some("cats.dev".into()),
+            METHODS => Some("GET,PA TCH".into()),
+            MAX_AGE => Some("-1".into()),
+            _ => None,
+        });
+
+        // Assert
+        match options {
+            Err(ConfigError::Report { errors }) => assert_eq!(errors.0.len(), 3),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_any_origin_requires_credentials_off() {
+        // Act
+        let with_credentials = CorsOptions::from_lookup(|key| match key {
+            ORIGINS => Some("*".into()),
+            _ => None,
+        });
+        let without_credentials = CorsOptions::from_lookup(|key| match key {
+            ORIGINS => Some("*".into()),
+            CREDENTIALS => Some("false".into()),
+            _ => None,
+        });
+
+        // Assert
+        match with_credentials {
+            Err(ConfigError::Report { errors }) => assert!(matches!(
+                &errors.0[..],
+                [ConfigError::InvalidValue { key, .. }] if key == ORIGINS
+            )),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+        assert!(without_credentials
+            .unwrap()
+            .allows_origin("https://any.dev"));
+    }
+
+    #[test]
+    fn test_allows_origin() {
+        // Arrange
+        let options = CorsOptions::from_lookup(|key| match key {
+            ORIGINS => Some("http://localhost:8080,https://*.cats.dev".into()),
+            _ => None,
+        })
+        .unwrap();
+
+        // Assert
+        assert!(options.allows_origin("http://localhost:8080"));
+        assert!(options.allows_origin("https://a.cats.dev"));
+        assert!(options.allows_origin("https://a.b.cats.dev"));
+        assert!(!options.allows_origin("https://cats.dev"));
+        assert!(!options.allows_origin("https://evilcats.dev"));
+        assert!(!options.allows_origin("http://a.cats.dev"));
+        assert!(!options.allows_origin("https://a.cats.dev.evil.io"));
+        assert!(!options.allows_origin("http://localhost:3000"));
+    }
+}
@@ -0,0 +1,362 @@
+use std::{path::Path, str::FromStr, time::Duration};
+
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+use sqlx::postgres::PgSslMode;
+
+use crate::helpers;
+
+use super::layered_config;
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g DATABASE_MAX_CONNECTIONS)
+pub const MIN_CONNECTIONS: &str = "database_min_connections";
+pub const MAX_CONNECTIONS: &str = "database_max_connections";
+pub const ACQUIRE_TIMEOUT: &str = "database_acquire_timeout_secs";
+pub const IDLE_TIMEOUT: &str = "database_idle_timeout_secs";
+pub const MAX_LIFETIME: &str = "database_max_lifetime_secs";
+pub const STATEMENT_TIMEOUT: &str = "database_statement_timeout_ms";
+pub const SSL_MODE: &str = "database_ssl_mode";
+pub const SSL_ROOT_CERT: &str = "database_ssl_root_cert";
+pub const CONNECT_RETRIES: &str = "database_connect_retries";
+pub const RETRY_DELAY: &str = "database_retry_delay_ms";
+pub const RETRY_MAX_DELAY: &str = "database_retry_max_delay_ms";
+pub const DEGRADED_START: &str = "database_degraded_start";
+
+pub const DEFAULT_MIN_CONNECTIONS: &str = "0";
+pub const DEFAULT_MAX_CONNECTIONS: &str = "4";
+pub const DEFAULT_ACQUIRE_TIMEOUT: &str = "30";
+pub const DEFAULT_IDLE_TIMEOUT: &str = "600";
+pub const DEFAULT_MAX_LIFETIME: &str = "1800";
+pub const DEFAULT_STATEMENT_TIMEOUT: &str = "0";
+pub const DEFAULT_SSL_MODE: &str = "prefer";
+pub const DEFAULT_CONNECT_RETRIES: &str = "5";
+pub const DEFAULT_RETRY_DELAY: &str = "500";
+pub const DEFAULT_RETRY_MAX_DELAY: &str = "10000";
+pub const DEFAULT_DEGRADED_START: &str = "false";
+
+const COUNT_EXPECTED: &str = "number (u32)";
+const DURATION_EXPECTED: &str = "duration (u64)";
+const FLAG_EXPECTED: &str = "boolean (true or false)";
+const SSL_MODE_EXPECTED: &str =
+    "ssl mode (disable, allow, prefer, require, verify-ca or verify-full)";
+
+/// Connection pool, timeouts and TLS options of the database
+/// Every option is optional and falls back to its default
+/// A zero idle timeout, max lifetime or statement timeout disables it
+/// Connecting at startup is retried with an exponential backoff (delay doubled on each retry, up to the max delay)
+/// With degraded start, the server still starts when every retry failed and reconnects in the background
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct DbOptions {
+    /// Minimum number of connections kept in the pool
+    pub min_connections: u32,
+    /// Maximum number of connections of the pool
+    pub max_connections: u32,
+    /// Seconds to wait for a connection before failing
+    pub acquire_timeout_secs: u64,
+    /// Seconds before an idle connection is closed
+    pub idle_timeout_secs: u64,
+    /// Seconds before a connection is closed and replaced
+    pub max_lifetime_secs: u64,
+    /// Milliseconds before a statement is aborted by the database
+    pub statement_timeout_ms: u64,
+    /// TLS mode (disable, allow, prefer, require, verify-ca or verify-full)
+    pub ssl_mode: String,
+    /// CA certificate file used to verify the database server
+    pub ssl_root_cert: Option<String>,
+    /// Connection retries at startup before giving up
+    pub connect_retries: u32,
+    /// Milliseconds before the first retry
+    pub retry_delay_ms: u64,
+    /// Maximum milliseconds between two retries
+    pub retry_max_delay_ms: u64,
+    /// Start without database when every retry failed, then reconnect in the background
+    pub degraded_start: bool,
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        Self::from_lookup(|_| None).expect("Default database options are valid")
+    }
+}
+
+impl DbOptions {
+    /// Options read with `lookup` (by option key), defaults for the unset ones
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let value = |key: &str| layered_config::value_or_default(&lookup, key);
+        let mut errors = ConfigErrors::default();
+
+        let max_connections = errors.check(
+            helpers::parse_value(MAX_CONNECTIONS, value(MAX_CONNECTIONS), COUNT_EXPECTED).and_then(
+                |max: u32| match max {
+                    0 => Err(ConfigError::InvalidValue {
+                        key: MAX_CONNECTIONS.into(),
+                        expected: "number greater than 0".into(),
+                    }),
+                    max => Ok(max),
+                },
+            ),
+        );
+        let min_connections = errors.check(
+            helpers::parse_value(MIN_CONNECTIONS, value(MIN_CONNECTIONS), COUNT_EXPECTED).and_then(
+                |min| match max_connections {
+                    Some(max) => check_min_connections(min, max),
+                    None => Ok(min),
+                },
+            ),
+        );
+        let acquire_timeout_secs = errors.check(helpers::parse_value(
+            ACQUIRE_TIMEOUT,
+            value(ACQUIRE_TIMEOUT),
+            DURATION_EXPECTED,
+        ));
+        let idle_timeout_secs = errors.check(helpers::parse_value(
+            IDLE_TIMEOUT,
+            value(IDLE_TIMEOUT),
+            DURATION_EXPECTED,
+        ));
+        let max_lifetime_secs = errors.check(helpers::parse_value(
+            MAX_LIFETIME,
+            value(MAX_LIFETIME),
+            DURATION_EXPECTED,
+        ));
+        let statement_timeout_ms = errors.check(helpers::parse_value(
+            STATEMENT_TIMEOUT,
+            value(STATEMENT_TIMEOUT),
+            DURATION_EXPECTED,
+        ));
+        let ssl_mode =
+            errors.check(
+                parse_ssl_mode(&value(SSL_MODE)).map_err(|_| ConfigError::InvalidValue {
+                    key: SSL_MODE.into(),
+                    expected: SSL_MODE_EXPECTED.into(),
+                }),
+            );
+        let ssl_root_cert = errors.check(match lookup(SSL_ROOT_CERT) {
+            Some(path) if !Path::new(&path).is_file() => Err(ConfigError::InvalidValue {
+                key: SSL_ROOT_CERT.into(),
+                expected: "path to an existing certificate file".into(),
+            }),
+            path => Ok(path),
+        });
+        let connect_retries = errors.check(helpers::parse_value(
+            CONNECT_RETRIES,
+            value(CONNECT_RETRIES),
+            COUNT_EXPECTED,
+        ));
+        let retry_delay_ms = errors.check(helpers::parse_value(
+            RETRY_DELAY,
+            value(RETRY_DELAY),
+            DURATION_EXPECTED,
+        ));
+        let retry_max_delay_ms = errors.check(helpers::parse_value(
+            RETRY_MAX_DELAY,
+            value(RETRY_MAX_DELAY),
+            DURATION_EXPECTED,
+        ));
+        let degraded_start = errors.check(helpers::parse_value(
+            DEGRADED_START,
+            value(DEGRADED_START),
+            FLAG_EXPECTED,
+        ));
+
+        match (
+            min_connections,
+            max_connections,
+            acquire_timeout_secs,
+            idle_timeout_secs,
+            max_lifetime_secs,
+            statement_timeout_ms,
+            ssl_mode,
+            ssl_root_cert,
+            connect_retries,
+            retry_delay_ms,
+            retry_max_delay_ms,
+            degraded_start,
+        ) {
+            (
+                Some(min_connections),
+                Some(max_connections),
+                Some(acquire_timeout_secs),
+                Some(idle_timeout_secs),
+                Some(max_lifetime_secs),
+                Some(statement_timeout_ms),
+                Some(ssl_mode),
+                Some(ssl_root_cert),
+                Some(connect_retries),
+                Some(retry_delay_ms),
+                Some(retry_max_delay_ms),
+                Some(degraded_start),
+            ) => Ok(Self {
+                min_connections,
+                max_connections,
+                acquire_timeout_secs,
+                idle_timeout_secs,
+                max_lifetime_secs,
+                statement_timeout_ms,
+                ssl_mode,
+                ssl_root_cert,
+                connect_retries,
+                retry_delay_ms,
+                retry_max_delay_ms,
+                degraded_start,
+            }),
+            _ => Err(errors.into()),
+        }
+    }
+
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_secs)
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        non_zero(Duration::from_secs(self.idle_timeout_secs))
+    }
+
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        non_zero(Duration::from_secs(self.max_lifetime_secs))
+    }
+
+    pub fn statement_timeout(&self) -> Option<Duration> {
+        non_zero(Duration::from_millis(self.statement_timeout_ms))
+    }
+
+    pub fn pg_ssl_mode(&self) -> Result<PgSslMode, ConfigError> {
+        PgSslMode::from_str(&self.ssl_mode).map_err(|_| ConfigError::InvalidValue {
+            key: SSL_MODE.into(),
+            expected: SSL_MODE_EXPECTED.into(),
+        })
+    }
+
+    /// Delay before the retry following `attempt` failed attempts (exponential backoff)
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .retry_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt));
+        Duration::from_millis(delay.min(self.retry_max_delay_ms))
+    }
+
+    pub fn retry_max_delay(&self) -> Duration {
+        Duration::from_millis(self.retry_max_delay_ms)
+    }
+}
+
+/// The pool can't keep more connections than it may open
+pub fn check_min_connections(min: u32, max: u32) -> Result<u32, ConfigError> {
+    match min <= max {
+        true => Ok(min),
+        false => Err(ConfigError::InvalidValue {
+            key: MIN_CONNECTIONS.into(),
+            expected: format!("number up to {MAX_CONNECTIONS} ({max})"),
+        }),
+    }
+}
+
+fn non_zero(duration: Duration) -> Option<Duration> {
+    (!duration.is_zero()).then_some(duration)
+}
+
+fn parse_ssl_mode(value: &str) -> Result<String, String> {
+    PgSslMode::from_str(value)
+        .map(|_| value.to_lowercase())
+        .map_err(|_| format!("{value} is not a valid {SSL_MODE_EXPECTED}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_default_options() {
+        // Act
+        let options = DbOptions::default();
+
+        // Assert
+        assert_eq!(options.max_connections, 4);
+        assert_eq!(options.acquire_timeout(), Duration::from_secs(30));
+        assert_eq!(options.statement_timeout(), None);
+        assert_eq!(options.ssl_mode, "prefer");
+        assert!(!options.degraded_start);
+    }
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        // Arrange
+        let options = DbOptions {
+            retry_delay_ms: 500,
+            retry_max_delay_ms: 3000,
+            ..DbOptions::default()
+        };
+
+        // Act
+        let delays: Vec<u64> = (0..5)
+            .map(|attempt| options.retry_delay(attempt).as_millis() as u64)
+            .collect();
+
+        // Assert
+        assert_eq!(delays, [500, 1000, 2000, 3000, 3000]);
+    }
+
+    #[test]
+    fn test_invalid_options_reported_together() {
+        // Arrange
+        let values = HashMap::from([
+            (MAX_CONNECTIONS, "0"),
+            (IDLE_TIMEOUT, "soon"),
+            (SSL_MODE, "always"),
+            (SSL_ROOT_CERT, "/missing/ca.pem"),
+            (STATEMENT_TIMEOUT, "5000"),
+            (DEGRADED_START, "maybe"),
+        ]);
+
+        // Act
+        let options = DbOptions::from_lookup(|key| values.get(key).map(|v| v.to_string()));
+
+        // Assert
+        match options {
+            Err(ConfigError::Report { errors }) => assert_eq!(errors.0.len(), 5),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_min_connections_over_max_fail() {
+        // Act
+        let options = DbOptions::from_lookup(|key| match key {
+            MIN_CONNECTIONS => Some("8".into()),
+            MAX_CONNECTIONS => Some("4".into()),
+            _ => None,
+        });
+
+        // Assert
+        match options {
+            Err(ConfigError::Report { errors }) => assert!(matches!(
+                &errors.0[..],
+                [ConfigError::InvalidValue { key, .. }] if key == MIN_CONNECTIONS
+            )),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_ssl_mode_fail() {
+        // Arrange
+        let options = DbOptions {
+            ssl_mode: "always".into(),
+            ..DbOptions::default()
+        };
+
+        // Act
+        let ssl_mode = options.pg_ssl_mode();
+
+        // Assert
+        assert!(matches!(
+            ssl_mode,
+            Err(ConfigError::InvalidValue { key, .. }) if key == SSL_MODE
+        ));
+        assert!(DbOptions::default().pg_ssl_mode().is_ok());
+    }
+}
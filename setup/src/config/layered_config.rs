@@ -0,0 +1,468 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use clap::{Arg, ArgAction, Command};
+use errors::ConfigError;
+
+use crate::helpers;
+
+use super::{
+    app_config::EnvMode, config_env::ConfigEnv, cors_options, db_config, db_options,
+    listen_options, security_options, server_config, server_options, telemetry_options,
+    tls_options,
+};
+
+// Configuration keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_PORT)
+// and the command line flag the kebab-cased key (e.g --server-port)
+pub const LOG_LEVEL: &str = "log_level";
+pub const SERVER_HOST_IP: &str = "server_host_ip";
+pub const SERVER_PORT: &str = "server_port";
+pub const DATABASE_URL: &str = "database_url";
+pub const DATABASE_USER: &str = "database_user";
+pub const DATABASE_PASSWORD: &str = "database_password";
+pub const DATABASE_HOST: &str = "database_host";
+pub const DATABASE_PORT: &str = "database_port";
+pub const DATABASE_NAME: &str = "database_name";
+pub const JWT_SECRET: &str = "jwt_secret";
+
+/// Every configuration key with its default, `None` when it has none
+/// (the database password and the security headers only have defaults by env mode)
+pub const OPTIONS: &[(&str, Option<&str>)] = &[
+    (LOG_LEVEL, Some(server_config::DEFAULT_LOG_LEVEL)),
+    (SERVER_HOST_IP, Some(server_config::DEFAULT_HOST_IP)),
+    (SERVER_PORT, Some(server_config::DEFAULT_PORT)),
+    (cors_options::ORIGINS, Some(cors_options::DEFAULT_ORIGINS)),
+    (cors_options::METHODS, Some(cors_options::DEFAULT_METHODS)),
+    (cors_options::HEADERS, Some(cors_options::DEFAULT_HEADERS)),
+    (
+        cors_options::CREDENTIALS,
+        Some(cors_options::DEFAULT_CREDENTIALS),
+    ),
+    (cors_options::MAX_AGE, Some(cors_options::DEFAULT_MAX_AGE)),
+    (security_options::HSTS, None),
+    (security_options::CSP, None),
+    (security_options::DOCS_CSP, None),
+    (security_options::FRAME_OPTIONS, None),
+    (security_options::REFERRER_POLICY, None),
+    (
+        server_options::BODY_LIMIT,
+        Some(server_options::DEFAULT_BODY_LIMIT),
+    ),
+    (
+        server_options::FEATURES,
+        Some(server_options::DEFAULT_FEATURES),
+    ),
+    (
+        server_options::SHUTDOWN_DELAY,
+        Some(server_options::DEFAULT_SHUTDOWN_DELAY),
+    ),
+    (
+        server_options::SHUTDOWN_GRACE,
+        Some(server_options::DEFAULT_SHUTDOWN_GRACE),
+    ),
+    (
+        server_options::TRASH_RETENTION,
+        Some(server_options::DEFAULT_TRASH_RETENTION),
+    ),
+    (tls_options::CERT, None),
+    (tls_options::KEY, None),
+    (tls_options::CLIENT_CA, None),
+    (tls_options::REDIRECT_PORT, None),
+    (listen_options::LISTEN, None),
+    (
+        listen_options::UNIX_SOCKET_MODE,
+        Some(listen_options::DEFAULT_UNIX_SOCKET_MODE),
+    ),
+    (telemetry_options::OTLP_ENDPOINT, None),
+    (
+        telemetry_options::OTLP_SERVICE_NAME,
+        Some(telemetry_options::DEFAULT_OTLP_SERVICE_NAME),
+    ),
+    (
+        telemetry_options::OTLP_SAMPLE_RATIO,
+        Some(telemetry_options::DEFAULT_OTLP_SAMPLE_RATIO),
+    ),
+    (DATABASE_URL, None),
+    (DATABASE_USER, Some(db_config::DEFAULT_USER)),
+    (DATABASE_PASSWORD, None),
+    (DATABASE_HOST, Some(db_config::DEFAULT_HOST)),
+    (DATABASE_PORT, Some(db_config::DEFAULT_PORT)),
+    (DATABASE_NAME, Some(db_config::DEFAULT_NAME)),
+    (
+        db_options::MIN_CONNECTIONS,
+        Some(db_options::DEFAULT_MIN_CONNECTIONS),
+    ),
+    (
+        db_options::MAX_CONNECTIONS,
+        Some(db_options::DEFAULT_MAX_CONNECTIONS),
+    ),
+    (
+        db_options::ACQUIRE_TIMEOUT,
+        Some(db_options::DEFAULT_ACQUIRE_TIMEOUT),
+    ),
+    (
+        db_options::IDLE_TIMEOUT,
+        Some(db_options::DEFAULT_IDLE_TIMEOUT),
+    ),
+    (
+        db_options::MAX_LIFETIME,
+        Some(db_options::DEFAULT_MAX_LIFETIME),
+    ),
+    (
+        db_options::STATEMENT_TIMEOUT,
+        Some(db_options::DEFAULT_STATEMENT_TIMEOUT),
+    ),
+    (db_options::SSL_MODE, Some(db_options::DEFAULT_SSL_MODE)),
+    (db_options::SSL_ROOT_CERT, None),
+    (
+        db_options::CONNECT_RETRIES,
+        Some(db_options::DEFAULT_CONNECT_RETRIES),
+    ),
+    (
+        db_options::RETRY_DELAY,
+        Some(db_options::DEFAULT_RETRY_DELAY),
+    ),
+    (
+        db_options::RETRY_MAX_DELAY,
+        Some(db_options::DEFAULT_RETRY_MAX_DELAY),
+    ),
+    (
+        db_options::DEGRADED_START,
+        Some(db_options::DEFAULT_DEGRADED_START),
+    ),
+    (JWT_SECRET, None),
+];
+
+/// Layer a configuration value comes from, by increasing precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigLayer {
+    Default,
+    File,
+    EnvVar,
+    CommandLine,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layer = match self {
+            ConfigLayer::Default => "default",
+            ConfigLayer::File => "file",
+            ConfigLayer::EnvVar => "env variable",
+            ConfigLayer::CommandLine => "command line",
+        };
+        write!(f, "{layer}")
+    }
+}
+
+/// Configuration values merged from every layer (defaults, config file, env variables then command line)
+/// Each key keeps the value of the highest layer that sets it
+#[derive(Default, Clone, PartialEq)]
+pub struct LayeredConfig {
+    values: BTreeMap<&'static str, (String, ConfigLayer)>,
+}
+
+/// Values are left out of the debug output since some of them are secrets
+impl fmt::Debug for LayeredConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.values.iter().map(|(key, (_, layer))| (key, layer)))
+            .finish()
+    }
+}
+
+impl fmt::Display for LayeredConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .report()
+            .iter()
+            .map(|(key, layer)| format!("{key}: {layer}"))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl LayeredConfig {
+    /// Load every layer (config file values, env variables and arguments)
+    pub fn load(
+        env: &ConfigEnv,
+        env_mode: EnvMode,
+        config_map: HashMap<String, String>,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self::defaults(env_mode)
+            .with_layer(ConfigLayer::File, config_map)
+            .with_layer(ConfigLayer::EnvVar, env_layer(env)?)
+            .with_layer(
+                ConfigLayer::CommandLine,
+                command_line_layer(env.args_without_config())?,
+            ))
+    }
+
+    /// Load the arguments only, over the defaults
+    pub fn from_command_line(env: &ConfigEnv, env_mode: EnvMode) -> Result<Self, ConfigError> {
+        Ok(Self::defaults(env_mode).with_layer(
+            ConfigLayer::CommandLine,
+            command_line_layer(env.args_without_config())?,
+        ))
+    }
+
+    /// Defaults of the env mode
+    /// The database password only has a default in development, it must be set by a layer otherwise
+    fn defaults(env_mode: EnvMode) -> Self {
+        match env_mode {
+            EnvMode::Development => Self::default().with_defaults().with_layer(
+                ConfigLayer::Default,
+                [(DATABASE_PASSWORD.into(), db_config::DEFAULT_PASSWORD.into())],
+            ),
+            _ => Self::default().with_defaults(),
+        }
+    }
+
+    pub fn with_defaults(self) -> Self {
+        let defaults = OPTIONS.iter().filter_map(|(key, default)| {
+            default.map(|default| (key.to_string(), default.to_string()))
+        });
+
+        self.with_layer(ConfigLayer::Default, defaults)
+    }
+
+    /// Override the current values with the known keys of `values`
+    /// Layers must be added by increasing precedence
+    pub fn with_layer(
+        mut self,
+        layer: ConfigLayer,
+        values: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        for (key, value) in values {
+            if let Some(key) = keys().find(|known| *known == key) {
+                self.values.insert(key, (value, layer));
+            }
+        }
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|(value, _)| value.as_str())
+    }
+
+    /// Value of a key that must be set by at least one layer
+    pub fn require(&self, key: &str) -> Result<String, ConfigError> {
+        self.get(key)
+            .map(str::to_owned)
+            .ok_or_else(|| ConfigError::MissingKey { key: key.into() })
+    }
+
+    pub fn parse<T: FromStr>(&self, key: &str, expected: &str) -> Result<T, ConfigError> {
+        helpers::parse_value(key, self.require(key)?, expected)
+    }
+
+    pub fn layer(&self, key: &str) -> Option<ConfigLayer> {
+        self.values.get(key).map(|(_, layer)| *layer)
+    }
+
+    /// Layer which supplied each value
+    pub fn report(&self) -> Vec<(&'static str, ConfigLayer)> {
+        self.values
+            .iter()
+            .map(|(key, (_, layer))| (*key, *layer))
+            .collect()
+    }
+}
+
+/// Every configuration key, see `OPTIONS`
+pub fn keys() -> impl Iterator<Item = &'static str> {
+    OPTIONS.iter().map(|(key, _)| *key)
+}
+
+/// Value of `key` read with `lookup`, its default (see `OPTIONS`) when unset, empty without default
+pub fn value_or_default(lookup: impl Fn(&str) -> Option<String>, key: &str) -> String {
+    lookup(key).unwrap_or_else(|| {
+        OPTIONS
+            .iter()
+            .find(|(known, _)| *known == key)
+            .and_then(|(_, default)| *default)
+            .unwrap_or_default()
+            .to_owned()
+    })
+}
+
+/// Known keys set by env variables (e.g SERVER_PORT) or their `*_FILE` variant, empty variables are ignored
+pub fn env_layer(env: &ConfigEnv) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut values = vec![];
+    for key in keys() {
+        let name = key.to_uppercase();
+        if env.is_set(&name) {
+            values.push((key.to_owned(), env.value(&name)?));
+        }
+    }
+    Ok(values)
+}
+
+/// Known keys set by command line flags (e.g `--server-port 3000` or `--server-port=3000`)
+/// Parsed by clap with one flag per key, an unknown flag or a flag without value is an error
+/// Empty values are ignored
+pub fn command_line_layer(
+    args: impl IntoIterator<Item = String>,
+) -> Result<Vec<(String, String)>, ConfigError> {
+    let command = Command::new("server")
+        .about("Web server structure study")
+        .version(env!("CARGO_PKG_VERSION"));
+    let command = keys().fold(command, |command, key| {
+        command.arg(
+            Arg::new(key)
+                .long(key.replace('_', "-"))
+                .action(ArgAction::Set),
+        )
+    });
+    let matches =
+        command
+            .try_get_matches_from(args)
+            .map_err(|err| ConfigError::InvalidArguments {
+                reason: err.to_string(),
+            })?;
+
+    Ok(keys()
+        .filter_map(|key| {
+            matches
+                .get_one::<String>(key)
+                .filter(|value| !value.is_empty())
+                .map(|value| (key.to_string(), value.clone()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::server_config::ServerConfig;
+
+    use super::*;
+
+    fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layers_precedence() {
+        // Arrange
+        let file = pairs(&[(SERVER_PORT, "3001"), (DATABASE_NAME, "file_db")]);
+        let env = ConfigEnv::default()
+            .with_var("SERVER_PORT", "3002")
+            .with_var("DATABASE_USER", "env_user");
+        let args = ["server", "--server-port", "3003", "--jwt-secret=secret"].map(String::from);
+
+        // Act
+        let config = LayeredConfig::default()
+            .with_defaults()
+            .with_layer(ConfigLayer::File, file)
+            .with_layer(ConfigLayer::EnvVar, env_layer(&env).unwrap())
+            .with_layer(ConfigLayer::CommandLine, command_line_layer(args).unwrap());
+
+        // Assert
+        assert_eq!(config.get(SERVER_PORT), Some("3003"));
+        assert_eq!(config.layer(SERVER_PORT), Some(ConfigLayer::CommandLine));
+        assert_eq!(config.get(DATABASE_USER), Some("env_user"));
+        assert_eq!(config.layer(DATABASE_USER), Some(ConfigLayer::EnvVar));
+        assert_eq!(config.get(DATABASE_NAME), Some("file_db"));
+        assert_eq!(config.layer(DATABASE_NAME), Some(ConfigLayer::File));
+        assert_eq!(config.layer(LOG_LEVEL), Some(ConfigLayer::Default));
+        assert_eq!(config.layer(JWT_SECRET), Some(ConfigLayer::CommandLine));
+        // Every key with a default, and the JWT secret
+        let defaults = OPTIONS.iter().filter(|(_, default)| default.is_some());
+        assert_eq!(config.report().len(), defaults.count() + 1);
+    }
+
+    #[test]
+    fn test_unknown_and_empty_values_are_ignored() {
+        // Arrange
+        let env = ConfigEnv::default()
+            .with_var("SERVER_HOST_IP", "")
+            .with_var("PATH", "/bin");
+        let args = ["server", "--log-level="].map(String::from);
+
+        // Act
+        let config = LayeredConfig::default()
+            .with_layer(ConfigLayer::EnvVar, env_layer(&env).unwrap())
+            .with_layer(ConfigLayer::CommandLine, command_line_layer(args).unwrap());
+
+        // Assert
+        assert!(config.report().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_flag_or_missing_value_fail() {
+        // Arrange
+        let unknown = ["server", "--server-prot", "3000"].map(String::from);
+        let missing_value = ["server", "--log-level"].map(String::from);
+
+        // Act
+        let unknown = command_line_layer(unknown);
+        let missing_value = command_line_layer(missing_value);
+
+        // Assert
+        assert!(matches!(unknown, Err(ConfigError::InvalidArguments { .. })));
+        assert!(matches!(
+            missing_value,
+            Err(ConfigError::InvalidArguments { .. })
+        ));
+    }
+
+    #[test]
+    fn test_database_password_default_in_development_only() {
+        // Arrange
+        let env = ConfigEnv::default().with_args(&["server", "--config", "ws.toml"]);
+
+        // Act
+        let development = LayeredConfig::load(&env, EnvMode::Development, HashMap::new()).unwrap();
+        let staging = LayeredConfig::load(&env, EnvMode::Staging, HashMap::new()).unwrap();
+
+        // Assert
+        assert_eq!(
+            development.get(DATABASE_PASSWORD),
+            Some(db_config::DEFAULT_PASSWORD)
+        );
+        assert_eq!(staging.get(DATABASE_PASSWORD), None);
+    }
+
+    #[test]
+    fn test_invalid_values_are_reported_together() {
+        // Arrange
+        let config = LayeredConfig::default().with_layer(
+            ConfigLayer::EnvVar,
+            pairs(&[(LOG_LEVEL, "info"), (SERVER_PORT, "not_a_port")]),
+        );
+
+        // Act
+        let result = ServerConfig::from_layers(&config);
+
+        // Assert
+        match result {
+            Err(ConfigError::Report { errors }) => {
+                assert_eq!(
+                    errors.to_string(),
+                    "  - server_host_ip is not set.\n  - server_port does not contain a valid number (u16)."
+                );
+            }
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_debug_hides_values() {
+        // Arrange
+        let config = LayeredConfig::default()
+            .with_layer(ConfigLayer::EnvVar, pairs(&[(JWT_SECRET, "top_secret")]));
+
+        // Act
+        let debug = format!("{config:?}");
+
+        // Assert
+        assert!(!debug.contains("top_secret"));
+        assert_eq!(config.to_string(), "jwt_secret: env variable");
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fmt, net::SocketAddr, path::PathBuf, str::FromStr};
+
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+
+use super::layered_config;
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_LISTEN)
+pub const LISTEN: &str = "server_listen";
+pub const UNIX_SOCKET_MODE: &str = "server_unix_socket_mode";
+
+pub const DEFAULT_UNIX_SOCKET_MODE: &str = "660";
+
+const UNIX_PREFIX: &str = "unix:";
+
+const LISTEN_EXPECTED: &str =
+    "comma separated list of ip:port ([::1]:3000 for IPv6) or unix:/path/to.sock";
+const MODE_EXPECTED: &str = "octal permissions between 0 and 777";
+
+/// Address a server listens on
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    /// Unix domain socket file, served over plain HTTP (e.g behind a reverse proxy)
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.strip_prefix(UNIX_PREFIX) {
+            Some(path) if !path.is_empty() => Ok(Self::Unix(PathBuf::from(path))),
+            Some(_) => Err("Missing unix socket path".into()),
+            None => value
+                .parse()
+                .map(Self::Tcp)
+                .map_err(|_| format!("Invalid listen address: {value}")),
+        }
+    }
+}
+
+impl TryFrom<String> for ListenAddr {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Unix(path) => write!(f, "{UNIX_PREFIX}{}", path.display()),
+        }
+    }
+}
+
+/// Addresses the server binds, `host_ip:port` of the server config when empty
+/// Lists are comma separated (e.g `127.0.0.1:3000,[::1]:3000,unix:/run/ws.sock`)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ListenOptions {
+    /// Listen addresses (ip:port or unix:/path/to.sock), replace host ip and port
+    pub listen: Vec<ListenAddr>,
+    /// Permissions of the unix socket files (octal)
+    pub unix_socket_mode: u32,
+}
+
+impl Default for ListenOptions {
+    fn default() -> Self {
+        Self::from_lookup(|_| None).expect("Default listen options are valid")
+    }
+}
+
+impl ListenOptions {
+    /// Options read with `lookup` (by option key), defaults for the unset ones
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+
+        let listen = errors.check(
+            lookup(LISTEN)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|addr| !addr.is_empty())
+                .map(|addr| {
+                    addr.parse().map_err(|_| ConfigError::InvalidValue {
+                        key: LISTEN.into(),
+                        expected: LISTEN_EXPECTED.into(),
+                    })
+                })
+                .collect(),
+        );
+        let unix_socket_mode = errors.check(
+            parse_mode(&layered_config::value_or_default(&lookup, UNIX_SOCKET_MODE)).map_err(
+                |_| ConfigError::InvalidValue {
+                    key: UNIX_SOCKET_MODE.into(),
+                    expected: MODE_EXPECTED.into(),
+                },
+            ),
+        );
+
+        match (listen, unix_socket_mode) {
+            (Some(listen), Some(unix_socket_mode)) => Ok(Self {
+                listen,
+                unix_socket_mode,
+            }),
+            _ => Err(errors.into()),
+        }
+    }
+}
+
+/// Octal file permissions (e.g 660 or 0660)
+fn parse_mode(value: &str) -> Result<u32, String> {
+    match u32::from_str_radix(value, 8) {
+        Ok(mode) if mode <= 0o777 => Ok(mode),
+        _ => Err(MODE_EXPECTED.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_addresses() {
+        // Act
+        let options = ListenOptions::from_lookup(|key| match key {
+            LISTEN => Some("127.0.0.1:3000, [::1]:3000,unix:/tmp/ws.sock".into()),
+            UNIX_SOCKET_MODE => Some("0600".into()),
+            _ => None,
+        })
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            options.listen,
+            [
+                ListenAddr::Tcp(([127, 0, 0, 1], 3000).into()),
+                ListenAddr::Tcp(([0, 0, 0, 0, 0, 0, 0, 1], 3000).into()),
+                ListenAddr::Unix("/tmp/ws.sock".into()),
+            ]
+        );
+        assert_eq!(options.listen[1].to_string(), "[::1]:3000");
+        assert_eq!(options.unix_socket_mode, 0o600);
+    }
+
+    #[test]
+    fn test_invalid_listen_options_are_reported_together() {
+        // Act
+        let options = ListenOptions::from_lookup(|key| match key {
+            LISTEN => Some("localhost:3000".into()),
+            UNIX_SOCKET_MODE => Some("999".into()),
+            _ => None,
+        });
+
+        // Assert
+        match options {
+            Err(ConfigError::Report { errors }) => assert_eq!(errors.0.len(), 2),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use std::{
+    fmt, fs,
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use errors::ConfigError;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+};
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::{helpers, telemetry};
+
+use super::{
+    app_config::{AppConfig, ConfigSource, EnvMode},
+    config_env::ConfigEnv,
+    security_options::SecurityHeaders,
+    server_config::ServerConfig,
+    server_options::ServerOptions,
+};
+
+/// Delay between two checks of the config file modification time
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Settings applied to the running server, replaced as a whole on reload
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeConfig {
+    pub log_level: LevelFilter,
+    pub options: ServerOptions,
+    pub security_headers: SecurityHeaders,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::new(&ServerConfig::default(), EnvMode::Development)
+    }
+}
+
+impl RuntimeConfig {
+    /// Reloadable part of the server config, an unknown log level falls back to info
+    /// Security headers take the defaults of `env_mode`
+    pub fn new(server: &ServerConfig, env_mode: EnvMode) -> Self {
+        Self {
+            log_level: LevelFilter::from_str(&server.log_level).unwrap_or(LevelFilter::INFO),
+            options: server.options.clone(),
+            security_headers: SecurityHeaders::new(&server.options.security, env_mode),
+        }
+    }
+
+    /// Whether cross-origin requests from `origin` are allowed
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.options.cors.allows_origin(origin)
+    }
+
+    pub fn body_limit(&self) -> u64 {
+        self.options.body_limit_bytes
+    }
+
+    /// Whether the `feature` flag is listed in the server features (see `server_options::FEATURE_SIGNUP`)
+    pub fn is_enabled(&self, feature: &str) -> bool {
+        self.options
+            .features
+            .iter()
+            .any(|enabled| enabled == feature)
+    }
+
+    /// Set the max level of the tracing subscriber
+    pub fn apply_log_level(&self) {
+        telemetry::set_level(self.log_level);
+    }
+}
+
+/// Runtime config shared by the server workers
+/// Readers get a snapshot, a reload swaps the whole config at once
+#[derive(Debug, Clone, Default)]
+pub struct SharedConfig(Arc<RwLock<Arc<RuntimeConfig>>>);
+
+impl SharedConfig {
+    pub fn new(config: RuntimeConfig) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    pub fn get(&self) -> Arc<RuntimeConfig> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn replace(&self, config: RuntimeConfig) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+}
+
+/// Outcome of a config reload
+#[derive(Debug, PartialEq)]
+pub struct ReloadReport {
+    /// Whether the runtime config changed
+    pub reloaded: bool,
+    /// Changed settings only applied after a restart
+    pub restart_required: Vec<&'static str>,
+}
+
+impl fmt::Display for ReloadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reloaded {
+            true => write!(f, "🔄 Config reloaded")?,
+            false => write!(f, "🔄 Config unchanged")?,
+        }
+        if !self.restart_required.is_empty() {
+            write!(
+                f,
+                "\n⚠️ Restart required to apply: {}",
+                self.restart_required.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reload the config of a running server
+/// Log level and server options are swapped into the shared runtime config,
+/// other changes (bind address, database, modes) are only reported
+pub struct ConfigReloader {
+    running: AppConfig,
+    shared: SharedConfig,
+    env: ConfigEnv,
+}
+
+impl ConfigReloader {
+    /// Reloader of the process environment (see `with_env`)
+    pub fn new(running: AppConfig, shared: SharedConfig) -> Self {
+        Self {
+            running,
+            shared,
+            env: ConfigEnv::from_process(),
+        }
+    }
+
+    /// Environment the spawned reloader loads the config from
+    pub fn with_env(mut self, env: ConfigEnv) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Load the config again from `env`, an invalid config leaves the running one untouched
+    pub fn reload(&mut self, env: &ConfigEnv) -> Result<ReloadReport, ConfigError> {
+        let config = AppConfig::from_env(env)?;
+        let restart_required = self.restart_required(&config);
+
+        let runtime = RuntimeConfig::new(&config.server, self.running.env_mode);
+        let reloaded = *self.shared.get() != runtime;
+        if reloaded {
+            runtime.apply_log_level();
+            self.shared.replace(runtime);
+            self.running.server.log_level = config.server.log_level;
+            self.running.server.options = config.server.options;
+        }
+
+        Ok(ReloadReport {
+            reloaded,
+            restart_required,
+        })
+    }
+
+    /// Reload on SIGHUP, and when a file the config is read from changes: the config file or its profile
+    /// overlay, the `*_FILE` secrets (the only files read in production, see `AppConfig::from_env`)
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => Some(hangup),
+                Err(err) => {
+                    eprintln!("⚠️ SIGHUP config reload unavailable: {err}");
+                    None
+                }
+            };
+            let mut modified = self.files_modified();
+            let mut interval = tokio::time::interval(FILE_POLL_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    Some(_) = async { hangup.as_mut()?.recv().await } => {
+                        println!("🔄 SIGHUP received, reloading config");
+                    }
+                    _ = interval.tick() => {
+                        let now = self.files_modified();
+                        if now == modified {
+                            continue;
+                        }
+                        modified = now;
+                        println!("🔄 Config file changed, reloading config");
+                    }
+                }
+
+                let env = self.env.clone();
+                match self.reload(&env) {
+                    Ok(report) => println!("{report}"),
+                    Err(err) => eprintln!("⚠️ Config not reloaded, keeping the running one. {err}"),
+                }
+            }
+        })
+    }
+
+    fn restart_required(&self, config: &AppConfig) -> Vec<&'static str> {
+        let running = &self.running;
+        [
+            (
+                "server_host_ip",
+                running.server.host_ip != config.server.host_ip,
+            ),
+            ("server_port", running.server.port != config.server.port),
+            ("server_tls", running.server.tls != config.server.tls),
+            (
+                "server_listen",
+                running.server.listen != config.server.listen,
+            ),
+            (
+                "server_otlp",
+                running.server.telemetry != config.server.telemetry,
+            ),
+            (
+                "server_cors",
+                !running
+                    .server
+                    .options
+                    .cors
+                    .same_layer(&config.server.options.cors),
+            ),
+            (
+                "server_shutdown_delay_secs",
+                running.server.options.shutdown_delay_secs
+                    != config.server.options.shutdown_delay_secs,
+            ),
+            (
+                "server_shutdown_grace_secs",
+                running.server.options.shutdown_grace_secs
+                    != config.server.options.shutdown_grace_secs,
+            ),
+            ("database", running.database != config.database),
+            (
+                "config_source",
+                running.config_source != config.config_source,
+            ),
+            ("env_mode", running.env_mode != config.env_mode),
+            ("data_mode", running.data_mode != config.data_mode),
+            ("auto_migrate", running.auto_migrate != config.auto_migrate),
+        ]
+        .into_iter()
+        .filter_map(|(setting, changed)| changed.then_some(setting))
+        .collect()
+    }
+
+    /// Modification times of the files the running config is read from
+    fn files_modified(&self) -> Vec<Option<SystemTime>> {
+        let config_files = match self.running.config_source {
+            ConfigSource::File | ConfigSource::Both => {
+                helpers::config_files(&self.env, self.running.env_mode.into())
+            }
+            ConfigSource::CommandLine | ConfigSource::EnvVar => vec![],
+        };
+        let mut secret_files = self.env.secret_files();
+        secret_files.sort();
+
+        config_files
+            .iter()
+            .chain(&secret_files)
+            .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn env(log_level: &str, port: &str, cors_origins: &str) -> ConfigEnv {
+        ConfigEnv::default()
+            .with_var("CONFIG_SOURCE", "env_var")
+            .with_var("LOG_LEVEL", log_level)
+            .with_var("SERVER_HOST_IP", "127.0.0.1")
+            .with_var("SERVER_PORT", port)
+            .with_var("SERVER_CORS_ORIGINS", cors_origins)
+    }
+
+    #[test]
+    fn test_reload_swaps_runtime_config() {
+        // Arrange
+        let running = AppConfig::from_env(&env("info", "3000", "http://a.dev")).unwrap();
+        let shared = SharedConfig::new(RuntimeConfig::new(&running.server, running.env_mode));
+        let mut reloader = ConfigReloader::new(running, shared.clone());
+
+        // Act
+        let report = reloader
+            .reload(&env("debug", "3000", "http://b.dev"))
+            .unwrap();
+
+        // Assert
+        assert!(report.reloaded);
+        assert!(report.restart_required.is_empty());
+        assert_eq!(shared.get().log_level, LevelFilter::DEBUG);
+        assert!(shared.get().allows_origin("http://b.dev"));
+        assert!(!shared.get().allows_origin("http://a.dev"));
+    }
+
+    #[test]
+    fn test_reload_reports_restart_required() {
+        // Arrange
+        let running = AppConfig::from_env(&env("info", "3000", "http://a.dev")).unwrap();
+        let shared = SharedConfig::new(RuntimeConfig::new(&running.server, running.env_mode));
+        let mut reloader = ConfigReloader::new(running, shared.clone());
+
+        // Act
+        let report = reloader
+            .reload(&env("info", "4000", "http://a.dev"))
+            .unwrap();
+
+        // Assert
+        assert!(!report.reloaded);
+        assert_eq!(report.restart_required, ["server_port"]);
+    }
+
+    #[test]
+    fn test_reload_reports_cors_layer_change() {
+        // Arrange
+        let running = AppConfig::from_env(&env("info", "3000", "http://a.dev")).unwrap();
+        let shared = SharedConfig::new(RuntimeConfig::new(&running.server, running.env_mode));
+        let mut reloader = ConfigReloader::new(running, shared.clone());
+
+        // Act
+        let report = reloader
+            .reload(
+                &env("info", "3000", "https://*.cats.dev").with_var("SERVER_CORS_METHODS", "GET"),
+            )
+            .unwrap();
+
+        // Assert
+        assert!(report.reloaded);
+        assert_eq!(report.restart_required, ["server_cors"]);
+        assert!(shared.get().allows_origin("https://a.cats.dev"));
+    }
+
+    #[test]
+    fn test_invalid_reload_keeps_running_config() {
+        // Arrange
+        let running = AppConfig::from_env(&env("info", "3000", "http://a.dev")).unwrap();
+        let shared = SharedConfig::new(RuntimeConfig::new(&running.server, running.env_mode));
+        let mut reloader = ConfigReloader::new(running, shared.clone());
+
+        // Act
+        let report = reloader.reload(&env("debug", "not_a_port", "http://b.dev"));
+
+        // Assert
+        assert!(report.is_err());
+        assert_eq!(shared.get().log_level, LevelFilter::INFO);
+    }
+
+    #[tokio::test]
+    async fn test_secret_file_watched_in_production() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("log_level");
+        fs::write(&file, "info\n").unwrap();
+        let env = ConfigEnv::default()
+            .with_var("ENV_MODE", EnvMode::Production.into())
+            .with_var("LOG_LEVEL_FILE", &file.to_string_lossy())
+            .with_var("SERVER_HOST_IP", "127.0.0.1")
+            .with_var("SERVER_PORT", "3000");
+        let running = AppConfig::from_env(&env).unwrap();
+        let shared = SharedConfig::new(RuntimeConfig::new(&running.server, running.env_mode));
+        let reloader = ConfigReloader::new(running, shared.clone())
+            .with_env(env)
+            .spawn();
+
+        // Act
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        fs::write(&file, "debug\n").unwrap();
+        tokio::time::sleep(FILE_POLL_INTERVAL + Duration::from_secs(1)).await;
+        reloader.abort();
+
+        // Assert
+        assert_eq!(shared.get().log_level, LevelFilter::DEBUG);
+    }
+}
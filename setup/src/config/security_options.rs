@@ -0,0 +1,191 @@
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+
+use super::app_config::EnvMode;
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_HSTS)
+pub const HSTS: &str = "server_hsts";
+pub const CSP: &str = "server_csp";
+pub const DOCS_CSP: &str = "server_docs_csp";
+pub const FRAME_OPTIONS: &str = "server_frame_options";
+pub const REFERRER_POLICY: &str = "server_referrer_policy";
+
+/// JSON responses load nothing and are never framed
+pub const DEFAULT_CSP: &str = "default-src 'none'; frame-ancestors 'none'";
+/// Swagger UI loads its own scripts and the spec, with inline styles and data images
+pub const DEFAULT_DOCS_CSP: &str =
+    "default-src 'self'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; frame-ancestors 'none'";
+pub const DEFAULT_FRAME_OPTIONS: &str = "DENY";
+pub const DEFAULT_HSTS: &str = "max-age=31536000; includeSubDomains";
+pub const DEFAULT_REFERRER_POLICY: &str = "no-referrer";
+pub const DEFAULT_DEV_REFERRER_POLICY: &str = "strict-origin-when-cross-origin";
+
+const VALUE_EXPECTED: &str = "header value of visible ascii characters, empty to omit the header";
+
+/// Hardening headers of the responses, the unset ones take the default of the env mode
+/// An empty value omits the header
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct SecurityOptions {
+    /// Strict-Transport-Security, only sent by default in staging and production
+    pub hsts: Option<String>,
+    /// Content-Security-Policy of the API responses
+    pub csp: Option<String>,
+    /// Content-Security-Policy of the docs UI
+    pub docs_csp: Option<String>,
+    /// X-Frame-Options
+    pub frame_options: Option<String>,
+    /// Referrer-Policy
+    pub referrer_policy: Option<String>,
+}
+
+impl SecurityOptions {
+    /// Options read with `lookup` (by option key)
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+        let mut value = |key: &str| {
+            errors
+                .check(
+                    lookup(key)
+                        .map(
+                            |value| match value.chars().all(|c| matches!(c, ' '..='~')) {
+                                true => Ok(value.trim().to_owned()),
+                                false => Err(ConfigError::InvalidValue {
+                                    key: key.into(),
+                                    expected: VALUE_EXPECTED.into(),
+                                }),
+                            },
+                        )
+                        .transpose(),
+                )
+                .flatten()
+        };
+
+        let options = Self {
+            hsts: value(HSTS),
+            csp: value(CSP),
+            docs_csp: value(DOCS_CSP),
+            frame_options: value(FRAME_OPTIONS),
+            referrer_policy: value(REFERRER_POLICY),
+        };
+
+        errors.into_result().map(|_| options)
+    }
+}
+
+/// Security headers sent by the servers, resolved from the options and the env mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityHeaders {
+    hsts: Option<String>,
+    csp: Option<String>,
+    docs_csp: Option<String>,
+    frame_options: Option<String>,
+    referrer_policy: Option<String>,
+}
+
+impl SecurityHeaders {
+    /// HSTS is left out of development and test, where the servers usually run on plain HTTP localhost
+    pub fn new(options: &SecurityOptions, env_mode: EnvMode) -> Self {
+        let (hsts, referrer_policy) = match env_mode {
+            EnvMode::Staging | EnvMode::Production => (DEFAULT_HSTS, DEFAULT_REFERRER_POLICY),
+            EnvMode::Development | EnvMode::Test => ("", DEFAULT_DEV_REFERRER_POLICY),
+        };
+        let resolve = |value: &Option<String>, default: &str| {
+            Some(value.as_deref().unwrap_or(default).to_owned()).filter(|value| !value.is_empty())
+        };
+
+        Self {
+            hsts: resolve(&options.hsts, hsts),
+            csp: resolve(&options.csp, DEFAULT_CSP),
+            docs_csp: resolve(&options.docs_csp, DEFAULT_DOCS_CSP),
+            frame_options: resolve(&options.frame_options, DEFAULT_FRAME_OPTIONS),
+            referrer_policy: resolve(&options.referrer_policy, referrer_policy),
+        }
+    }
+
+    /// Headers (lowercase name, value) of a response, the docs UI has its own CSP
+    pub fn headers(&self, docs_ui: bool) -> Vec<(&'static str, &str)> {
+        let csp = match docs_ui {
+            true => &self.docs_csp,
+            false => &self.csp,
+        };
+        [
+            ("strict-transport-security", self.hsts.as_deref()),
+            ("x-content-type-options", Some("nosniff")),
+            ("x-frame-options", self.frame_options.as_deref()),
+            ("referrer-policy", self.referrer_policy.as_deref()),
+            ("content-security-policy", csp.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_follow_env_mode() {
+        // Arrange
+        let options = SecurityOptions::default();
+
+        // Act
+        let production = SecurityHeaders::new(&options, EnvMode::Production);
+        let development = SecurityHeaders::new(&options, EnvMode::Development);
+
+        // Assert
+        assert!(production
+            .headers(false)
+            .contains(&("strict-transport-security", DEFAULT_HSTS)));
+        assert!(!development
+            .headers(false)
+            .iter()
+            .any(|(name, _)| *name == "strict-transport-security"));
+        assert!(development
+            .headers(false)
+            .contains(&("referrer-policy", DEFAULT_DEV_REFERRER_POLICY)));
+        assert!(development
+            .headers(true)
+            .contains(&("content-security-policy", DEFAULT_DOCS_CSP)));
+    }
+
+    #[test]
+    fn test_options_override_defaults() {
+        // Arrange
+        let options = SecurityOptions::from_lookup(|key| match key {
+            FRAME_OPTIONS => Some("SAMEORIGIN".into()),
+            CSP => Some("".into()),
+            _ => None,
+        })
+        .unwrap();
+
+        // Act
+        let headers = SecurityHeaders::new(&options, EnvMode::Production);
+
+        // Assert
+        assert_eq!(
+            headers.headers(false),
+            [
+                ("strict-transport-security", DEFAULT_HSTS),
+                ("x-content-type-options", "nosniff"),
+                ("x-frame-options", "SAMEORIGIN"),
+                ("referrer-policy", DEFAULT_REFERRER_POLICY),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_header_value() {
+        // Act
+        let options = SecurityOptions::from_lookup(|key| match key {
+            CSP => Some("default-src 'none'\n".into()),
+            _ => None,
+        });
+
+        // Assert
+        assert!(matches!(options, Err(ConfigError::Report { .. })));
+    }
+}
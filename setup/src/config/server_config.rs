@@ -1,78 +1,169 @@
-use std::env;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+};
 
-use clap::Parser;
+use errors::{ConfigError, ConfigErrors};
 use serde::Deserialize;
 
 use crate::helpers;
 
+use super::{
+    config_env::ConfigEnv,
+    layered_config::{self, LayeredConfig},
+    listen_options::{ListenAddr, ListenOptions},
+    server_options::ServerOptions,
+    telemetry_options::TelemetryOptions,
+    tls_options::TlsOptions,
+};
+
 pub const DEFAULT_LOG_LEVEL: &str = "info";
 pub const DEFAULT_HOST_IP: &str = "127.0.0.1";
 pub const DEFAULT_PORT: &str = "3000";
 
-#[derive(Debug, Parser, Deserialize, Default, PartialEq)]
-#[clap(author, version, about, long_about = None)]
+const PORT_EXPECTED: &str = "number (u16)";
+const IP_EXPECTED: &str = "ip address (v4 or v6)";
+
+#[derive(Debug, Deserialize, PartialEq)]
 /// Web server structure study
 pub struct ServerConfig {
     /// Log level
     /// Decide which kind of errors we want to log (info, warn, error)
-    #[clap(long, default_value = DEFAULT_LOG_LEVEL)]
     pub log_level: String,
-    /// Web server ip addr host
-    #[clap(long, default_value = DEFAULT_HOST_IP)]
-    pub host_ip: String,
+    /// Web server ip addr host (v4 or v6)
+    pub host_ip: IpAddr,
     /// Web server port
-    #[clap(long, default_value = DEFAULT_PORT, value_parser = clap::value_parser!(u16).range(1..))]
     pub port: u16,
+    /// Options that can be reloaded while running
+    #[serde(flatten)]
+    pub options: ServerOptions,
+    /// HTTPS options
+    #[serde(flatten)]
+    pub tls: TlsOptions,
+    /// Additional listen addresses and unix sockets
+    #[serde(flatten)]
+    pub listen: ListenOptions,
+    /// OpenTelemetry trace export
+    #[serde(flatten)]
+    pub telemetry: TelemetryOptions,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self::from_layers(&LayeredConfig::default().with_defaults())
+            .expect("Default server config is valid")
+    }
 }
 
 impl ServerConfig {
-    pub fn from_file() -> Self {
-        let config_map = helpers::read_config_file();
-        let log_level = config_map
-            .get("log_level")
-            .ok_or_else(|| "log_level is not set")
-            .map_err(|err| panic!("{err}"));
-        let host_ip = config_map
-            .get("server_host_ip")
-            .ok_or_else(|| "server_host_ip is not set")
-            .map_err(|err| panic!("{err}"));
-        let port = config_map
-            .get("server_port")
-            .ok_or_else(|| "server_port is not set")
-            .map_err(|err| panic!("{err}"));
-
-        Self {
-            log_level: log_level.unwrap().to_owned(),
-            host_ip: host_ip.unwrap().to_owned(),
-            port: port
-                .unwrap()
-                .to_owned()
-                .parse::<u16>()
-                .expect("server_port does not contain a valid number (u16)"),
-        }
+    pub fn from_file(config_map: &HashMap<String, String>) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+        let log_level = errors.check(helpers::file_value(config_map, "log_level"));
+        let host_ip = errors.check(
+            helpers::file_value(config_map, "server_host_ip")
+                .and_then(|ip| helpers::parse_value("server_host_ip", ip, IP_EXPECTED)),
+        );
+        let port = errors.check(
+            helpers::file_value(config_map, "server_port")
+                .and_then(|port| helpers::parse_value("server_port", port, PORT_EXPECTED)),
+        );
+        let lookup = |key: &str| config_map.get(key).cloned();
+        let options = errors.check(ServerOptions::from_lookup(lookup));
+        let tls = errors.check(TlsOptions::from_lookup(lookup));
+        let listen = errors.check(ListenOptions::from_lookup(lookup));
+        let telemetry = errors.check(TelemetryOptions::from_lookup(lookup));
+
+        Self::build(
+            errors, log_level, host_ip, port, options, tls, listen, telemetry,
+        )
     }
 
-    pub fn from_command_line() -> Self {
-        let config = Self::parse();
+    pub fn from_env_var(env: &ConfigEnv) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+        let log_level = errors.check(env.value("LOG_LEVEL"));
+        let host_ip = errors.check(
+            env.value("SERVER_HOST_IP")
+                .and_then(|ip| helpers::parse_value("SERVER_HOST_IP", ip, IP_EXPECTED)),
+        );
+        let port = errors.check(
+            env.value("SERVER_PORT")
+                .and_then(|port| helpers::parse_value("SERVER_PORT", port, PORT_EXPECTED)),
+        );
+        let lookup = |key: &str| {
+            env.var(&key.to_uppercase())
+                .filter(|value| !value.is_empty())
+                .map(str::to_owned)
+        };
+        let options = errors.check(ServerOptions::from_lookup(lookup));
+        let tls = errors.check(TlsOptions::from_lookup(lookup));
+        let listen = errors.check(ListenOptions::from_lookup(lookup));
+        let telemetry = errors.check(TelemetryOptions::from_lookup(lookup));
 
-        config
+        Self::build(
+            errors, log_level, host_ip, port, options, tls, listen, telemetry,
+        )
     }
 
-    pub fn from_env_var() -> Self {
-        let log_level = env::var("LOG_LEVEL").expect("LOG_LEVEL env variable not set");
-        let host_ip = env::var("SERVER_HOST_IP").expect("SERVER_HOST_IP env variable not set");
-        let port = env::var("SERVER_PORT").expect("SERVER_PORT env variable not set");
-
-        Self {
-            log_level,
-            host_ip,
-            port: port
-                .parse::<u16>()
-                .expect("SERVER_PORT does not contain a valid number (u16)"),
+    pub fn from_layers(layers: &LayeredConfig) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+        let log_level = errors.check(layers.require(layered_config::LOG_LEVEL));
+        let host_ip = errors.check(layers.parse(layered_config::SERVER_HOST_IP, IP_EXPECTED));
+        let port = errors.check(layers.parse(layered_config::SERVER_PORT, PORT_EXPECTED));
+        let lookup = |key: &str| layers.get(key).map(str::to_owned);
+        let options = errors.check(ServerOptions::from_lookup(lookup));
+        let tls = errors.check(TlsOptions::from_lookup(lookup));
+        let listen = errors.check(ListenOptions::from_lookup(lookup));
+        let telemetry = errors.check(TelemetryOptions::from_lookup(lookup));
+
+        Self::build(
+            errors, log_level, host_ip, port, options, tls, listen, telemetry,
+        )
+    }
+
+    /// Config made of the loaded values, or the report of every missing or invalid one
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        errors: ConfigErrors,
+        log_level: Option<String>,
+        host_ip: Option<IpAddr>,
+        port: Option<u16>,
+        options: Option<ServerOptions>,
+        tls: Option<TlsOptions>,
+        listen: Option<ListenOptions>,
+        telemetry: Option<TelemetryOptions>,
+    ) -> Result<Self, ConfigError> {
+        match (log_level, host_ip, port, options, tls, listen, telemetry) {
+            (
+                Some(log_level),
+                Some(host_ip),
+                Some(port),
+                Some(options),
+                Some(tls),
+                Some(listen),
+                Some(telemetry),
+            ) => Ok(Self {
+                log_level,
+                host_ip,
+                port,
+                options,
+                tls,
+                listen,
+                telemetry,
+            }),
+            _ => Err(errors.into()),
         }
     }
 
+    /// Host and port, IPv6 hosts between brackets (e.g [::1]:3000)
     pub fn format_url(&self) -> String {
-        format!("{}:{}", self.host_ip, self.port,)
+        SocketAddr::new(self.host_ip, self.port).to_string()
+    }
+
+    /// Addresses to bind: the listen list when set, host ip and port otherwise
+    pub fn listen_addrs(&self) -> Vec<ListenAddr> {
+        match self.listen.listen.is_empty() {
+            true => vec![ListenAddr::Tcp(SocketAddr::new(self.host_ip, self.port))],
+            false => self.listen.listen.clone(),
+        }
     }
 }
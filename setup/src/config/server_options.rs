@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+
+use crate::helpers;
+
+use super::{cors_options::CorsOptions, layered_config, security_options::SecurityOptions};
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_BODY_LIMIT_BYTES)
+pub const BODY_LIMIT: &str = "server_body_limit_bytes";
+pub const FEATURES: &str = "server_features";
+pub const SHUTDOWN_DELAY: &str = "server_shutdown_delay_secs";
+pub const SHUTDOWN_GRACE: &str = "server_shutdown_grace_secs";
+pub const TRASH_RETENTION: &str = "server_trash_retention_days";
+
+pub const DEFAULT_BODY_LIMIT: &str = "16384";
+pub const DEFAULT_FEATURES: &str = FEATURE_SIGNUP;
+pub const DEFAULT_SHUTDOWN_DELAY: &str = "0";
+pub const DEFAULT_SHUTDOWN_GRACE: &str = "30";
+pub const DEFAULT_TRASH_RETENTION: &str = "30";
+
+// Feature flags, toggled by listing them in `server_features`
+/// Public sign up of new accounts
+pub const FEATURE_SIGNUP: &str = "signup";
+
+/// Hard cap of request bodies, the configured limit can't exceed it
+pub const MAX_BODY_LIMIT: u64 = 1024 * 1024;
+
+const BODY_LIMIT_EXPECTED: &str = "number of bytes between 1 and 1048576";
+const DURATION_EXPECTED: &str = "duration (u64)";
+const RETENTION_EXPECTED: &str = "number of days (u64), 0 keeps trashed cats until purged";
+
+/// Server options that can be changed without restarting (see `RuntimeConfig`)
+/// except the shutdown delay and grace period, read when the server starts
+/// Lists are comma separated (e.g `http://localhost:8080,https://cats.dev`)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ServerOptions {
+    /// Cross-origin policy
+    #[serde(flatten)]
+    pub cors: CorsOptions,
+    /// Security headers
+    #[serde(flatten)]
+    pub security: SecurityOptions,
+    /// Maximum size of request bodies in bytes
+    pub body_limit_bytes: u64,
+    /// Enabled feature flags (e.g `signup`)
+    pub features: Vec<String>,
+    /// Seconds the listeners stay open on shutdown, readiness failing, before the server stops
+    /// (lets load balancers stop routing to the instance)
+    pub shutdown_delay_secs: u64,
+    /// Seconds given to in-flight requests to finish on shutdown, once the server stops
+    pub shutdown_grace_secs: u64,
+    /// Days trashed cats are kept before being purged automatically, 0 disables the purge
+    pub trash_retention_days: u64,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self::from_lookup(|_| None).expect("Default server options are valid")
+    }
+}
+
+impl ServerOptions {
+    /// Options read with `lookup` (by option key), defaults for the unset ones
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let value = |key: &str| layered_config::value_or_default(&lookup, key);
+        let mut errors = ConfigErrors::default();
+
+        let cors = errors.check(CorsOptions::from_lookup(&lookup));
+        let security = errors.check(SecurityOptions::from_lookup(&lookup));
+        let body_limit_bytes = errors.check(
+            helpers::parse_value(BODY_LIMIT, value(BODY_LIMIT), BODY_LIMIT_EXPECTED).and_then(
+                |limit: u64| match limit {
+                    1..=MAX_BODY_LIMIT => Ok(limit),
+                    _ => Err(ConfigError::InvalidValue {
+                        key: BODY_LIMIT.into(),
+                        expected: BODY_LIMIT_EXPECTED.into(),
+                    }),
+                },
+            ),
+        );
+        let features = helpers::split_list(&value(FEATURES));
+        let shutdown_delay_secs = errors.check(helpers::parse_value(
+            SHUTDOWN_DELAY,
+            value(SHUTDOWN_DELAY),
+            DURATION_EXPECTED,
+        ));
+        let shutdown_grace_secs = errors.check(helpers::parse_value(
+            SHUTDOWN_GRACE,
+            value(SHUTDOWN_GRACE),
+            DURATION_EXPECTED,
+        ));
+        let trash_retention_days = errors.check(helpers::parse_value(
+            TRASH_RETENTION,
+            value(TRASH_RETENTION),
+            RETENTION_EXPECTED,
+        ));
+
+        match (
+            cors,
+            security,
+            body_limit_bytes,
+            shutdown_delay_secs,
+            shutdown_grace_secs,
+            trash_retention_days,
+        ) {
+            (
+                Some(cors),
+                Some(security),
+                Some(body_limit_bytes),
+                Some(shutdown_delay_secs),
+                Some(shutdown_grace_secs),
+                Some(trash_retention_days),
+            ) => Ok(Self {
+                cors,
+                security,
+                body_limit_bytes,
+                features,
+                shutdown_delay_secs,
+                shutdown_grace_secs,
+                trash_retention_days,
+            }),
+            _ => Err(errors.into()),
+        }
+    }
+
+    pub fn shutdown_delay(&self) -> Duration {
+        Duration::from_secs(self.shutdown_delay_secs)
+    }
+
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+
+    /// How long trashed cats are kept, None when they are only purged on demand
+    pub fn trash_retention(&self) -> Option<Duration> {
+        match self.trash_retention_days {
+            0 => None,
+            days => Some(Duration::from_secs(days * 24 * 60 * 60)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_lists() {
+        // Act
+        let options = ServerOptions::from_lookup(|key| match key {
+            FEATURES => Some(" signup, ,audit".into()),
+            _ => None,
+        })
+        .unwrap();
+
+        // Assert
+        assert_eq!(options.features, ["signup", "audit"]);
+        assert_eq!(options.body_limit_bytes, 16384);
+        assert_eq!(ServerOptions::default().features, [FEATURE_SIGNUP]);
+    }
+
+    #[test]
+    fn test_trash_retention() {
+        // Act
+        let default = ServerOptions::default();
+        let disabled = ServerOptions::from_lookup(|key| match key {
+            TRASH_RETENTION => Some("0".into()),
+            _ => None,
+        })
+        .unwrap();
+        let invalid = ServerOptions::from_lookup(|key| match key {
+            TRASH_RETENTION => Some("-1".into()),
+            _ => None,
+        });
+
+        // Assert
+        assert_eq!(
+            default.trash_retention(),
+            Some(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(disabled.trash_retention(), None);
+        assert!(invalid.is_err());
+    }
+}
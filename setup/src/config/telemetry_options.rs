@@ -0,0 +1,117 @@
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+use url::Url;
+
+use super::layered_config;
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_OTLP_ENDPOINT)
+pub const OTLP_ENDPOINT: &str = "server_otlp_endpoint";
+pub const OTLP_SERVICE_NAME: &str = "server_otlp_service_name";
+pub const OTLP_SAMPLE_RATIO: &str = "server_otlp_sample_ratio";
+
+pub const DEFAULT_OTLP_SERVICE_NAME: &str = "rust-webservice-study";
+pub const DEFAULT_OTLP_SAMPLE_RATIO: &str = "1.0";
+
+const ENDPOINT_EXPECTED: &str = "http(s) url of an OTLP/HTTP collector (e.g http://localhost:4318)";
+const SERVICE_NAME_EXPECTED: &str = "non empty service name";
+const RATIO_EXPECTED: &str = "ratio between 0.0 and 1.0";
+
+/// OpenTelemetry export of the request and controller spans, disabled when no endpoint is set
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct TelemetryOptions {
+    /// OTLP/HTTP collector base url, spans are sent to its `/v1/traces`
+    pub otlp_endpoint: Option<String>,
+    /// Service name of the exported spans
+    pub otlp_service_name: String,
+    /// Share of the new traces exported, traces started upstream (traceparent) follow their sampling decision
+    pub otlp_sample_ratio: f64,
+}
+
+impl Default for TelemetryOptions {
+    fn default() -> Self {
+        Self::from_lookup(|_| None).expect("Default telemetry options are valid")
+    }
+}
+
+impl TelemetryOptions {
+    /// Options read with `lookup` (by option key), defaults for the unset ones
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+        let invalid = |key: &str, expected: &str| ConfigError::InvalidValue {
+            key: key.into(),
+            expected: expected.into(),
+        };
+
+        let otlp_endpoint = errors.check(
+            lookup(OTLP_ENDPOINT)
+                .map(|endpoint| {
+                    parse_endpoint(&endpoint).map_err(|_| invalid(OTLP_ENDPOINT, ENDPOINT_EXPECTED))
+                })
+                .transpose(),
+        );
+        let otlp_service_name = errors.check(
+            match layered_config::value_or_default(&lookup, OTLP_SERVICE_NAME) {
+                name if name.trim().is_empty() => {
+                    Err(invalid(OTLP_SERVICE_NAME, SERVICE_NAME_EXPECTED))
+                }
+                name => Ok(name),
+            },
+        );
+        let otlp_sample_ratio = errors.check(
+            parse_ratio(&layered_config::value_or_default(
+                &lookup,
+                OTLP_SAMPLE_RATIO,
+            ))
+            .map_err(|_| invalid(OTLP_SAMPLE_RATIO, RATIO_EXPECTED)),
+        );
+
+        match (otlp_endpoint, otlp_service_name, otlp_sample_ratio) {
+            (Some(otlp_endpoint), Some(otlp_service_name), Some(otlp_sample_ratio)) => Ok(Self {
+                otlp_endpoint,
+                otlp_service_name,
+                otlp_sample_ratio,
+            }),
+            _ => Err(errors.into()),
+        }
+    }
+}
+
+fn parse_endpoint(value: &str) -> Result<String, String> {
+    match Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {
+            Ok(value.trim_end_matches('/').to_owned())
+        }
+        _ => Err(ENDPOINT_EXPECTED.into()),
+    }
+}
+
+fn parse_ratio(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(RATIO_EXPECTED.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_telemetry_options_are_reported_together() {
+        // Act
+        let options = TelemetryOptions::from_lookup(|key| match key {
+            OTLP_ENDPOINT => Some("localhost:4318".into()),
+            OTLP_SAMPLE_RATIO => Some("1.5".into()),
+            _ => None,
+        });
+
+        // Assert
+        match options {
+            Err(ConfigError::Report { errors }) => assert_eq!(errors.0.len(), 2),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+        assert_eq!(TelemetryOptions::default().otlp_endpoint, None);
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use errors::{ConfigError, ConfigErrors};
+use serde::Deserialize;
+
+use crate::helpers;
+
+// Option keys, as named in the config file
+// The matching env variable is the uppercased key (e.g SERVER_TLS_CERT)
+pub const CERT: &str = "server_tls_cert";
+pub const KEY: &str = "server_tls_key";
+pub const CLIENT_CA: &str = "server_tls_client_ca";
+pub const REDIRECT_PORT: &str = "server_tls_redirect_port";
+
+const PORT_EXPECTED: &str = "number (u16)";
+const FILE_EXPECTED: &str = "path to an existing PEM file";
+
+/// HTTPS options of the server, plain HTTP when no certificate is set
+/// The certificate and key files are reloaded when they change, their paths require a restart
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct TlsOptions {
+    /// Certificate chain file (PEM), enables HTTPS with the key
+    pub tls_cert: Option<String>,
+    /// Private key file (PEM)
+    pub tls_key: Option<String>,
+    /// CA file (PEM) required to sign client certificates (mTLS)
+    pub tls_client_ca: Option<String>,
+    /// Plain HTTP port redirecting to HTTPS
+    pub tls_redirect_port: Option<u16>,
+}
+
+impl TlsOptions {
+    /// Options read with `lookup` (by option key)
+    /// Every invalid value is collected into one report
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut errors = ConfigErrors::default();
+
+        let tls_cert = errors.check(pem_file(CERT, lookup(CERT)));
+        let tls_key = errors.check(pem_file(KEY, lookup(KEY)));
+        let tls_client_ca = errors.check(pem_file(CLIENT_CA, lookup(CLIENT_CA)));
+        let tls_redirect_port = errors.check(
+            lookup(REDIRECT_PORT)
+                .map(|port| helpers::parse_value(REDIRECT_PORT, port, PORT_EXPECTED))
+                .transpose(),
+        );
+
+        let (Some(tls_cert), Some(tls_key), Some(tls_client_ca), Some(tls_redirect_port)) =
+            (tls_cert, tls_key, tls_client_ca, tls_redirect_port)
+        else {
+            return Err(errors.into());
+        };
+
+        // Every option needs the certificate and its key
+        let with_cert = tls_cert.is_some() && tls_key.is_some();
+        let missing = match (&tls_cert, &tls_key) {
+            (Some(_), None) => Some(KEY),
+            (None, Some(_)) => Some(CERT),
+            _ if !with_cert && (tls_client_ca.is_some() || tls_redirect_port.is_some()) => {
+                Some(CERT)
+            }
+            _ => None,
+        };
+        if let Some(key) = missing {
+            return Err(ConfigError::MissingKey { key: key.into() });
+        }
+
+        Ok(Self {
+            tls_cert,
+            tls_key,
+            tls_client_ca,
+            tls_redirect_port,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.tls_cert.is_some()
+    }
+}
+
+/// Optional path, which must be an existing file when set
+fn pem_file(key: &str, path: Option<String>) -> Result<Option<String>, ConfigError> {
+    match path {
+        Some(path) if !Path::new(&path).is_file() => Err(ConfigError::InvalidValue {
+            key: key.into(),
+            expected: FILE_EXPECTED.into(),
+        }),
+        path => Ok(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/tls");
+
+    #[test]
+    fn test_tls_needs_cert_and_key() {
+        // Act
+        let options = TlsOptions::from_lookup(|key| match key {
+            CERT => Some(format!("{FIXTURES}/cert.pem")),
+            REDIRECT_PORT => Some("8080".into()),
+            _ => None,
+        });
+
+        // Assert
+        match options {
+            Err(ConfigError::MissingKey { key }) => assert_eq!(key, KEY),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+}
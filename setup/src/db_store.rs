@@ -1,31 +1,202 @@
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::sync::Arc;
 
-use crate::APP_CONFIG;
+use errors::{AppError, ConfigError, Errors, ServerError};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    PgPool,
+};
+use tokio::sync::watch;
+
+use crate::config::{db_config::DbConfig, db_options::DbOptions};
 
 #[derive(Debug, Clone)]
 pub struct DbStore {
     pub connection: PgPool,
+    /// Whether the database answered the last check
+    available: Arc<watch::Sender<bool>>,
+    /// Pool size limit, sqlx doesn't expose it once the pool is built
+    max_connections: u32,
 }
 
 impl DbStore {
-    pub async fn create_postgres_store() -> DbStore {
-        let db_url = APP_CONFIG.database.format_postgres_url();
-        let db_store = DbStore::new_postgres(&db_url).await.unwrap();
+    /// Create the connection pool with the configured pool, timeouts and TLS options
+    /// Connecting is retried with an exponential backoff. When every retry failed:
+    /// - with degraded start, the store is created unavailable and reconnects in the background
+    /// - otherwise, a `ServerError::DbConnection` is returned
+    pub async fn create_postgres_store(config: &DbConfig) -> Result<DbStore, AppError> {
+        let options = &config.options;
+        let connect_options = Self::connect_options(config)?;
+
+        let mut attempt = 0;
+        let db_store = loop {
+            match Self::pool_options(options)
+                .connect_with(connect_options.clone())
+                .await
+            {
+                Ok(connection) => break Self::new(connection, true, options),
+                Err(err) if attempt < options.connect_retries => {
+                    let delay = options.retry_delay(attempt);
+                    attempt += 1;
+                    eprintln!(
+                        "🛢️ Database unreachable ({err}), retry {attempt}/{} in {delay:?}",
+                        options.connect_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) if options.degraded_start => {
+                    eprintln!("⚠️ Database unreachable ({err}), starting in degraded mode");
+                    let connection = Self::pool_options(options).connect_lazy_with(connect_options);
+                    break Self::new(connection, false, options);
+                }
+                Err(err) => {
+                    return Err(AppError::new(Errors::Server(ServerError::DbConnection {
+                        reason: err.to_string(),
+                    })))
+                }
+            }
+        };
 
-        db_store
+        if options.degraded_start {
+            db_store.watch(options.clone());
+        }
+
+        Ok(db_store)
     }
-    pub async fn new_postgres(db_url: &str) -> Result<Self, sqlx::Error> {
-        let db_pool = match PgPoolOptions::new()
-            .max_connections(4)
-            .connect(db_url)
+
+    fn new(connection: PgPool, available: bool, options: &DbOptions) -> Self {
+        DbStore {
+            connection,
+            available: Arc::new(watch::Sender::new(available)),
+            max_connections: options.max_connections,
+        }
+    }
+
+    /// Store of an already connected pool (e.g a test database), with the default pool options
+    pub fn from_pool(connection: PgPool) -> Self {
+        Self::new(connection, true, &DbOptions::default())
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        self.max_connections
+    }
+
+    /// Whether the database can currently serve requests
+    pub fn is_available(&self) -> bool {
+        *self.available.borrow()
+    }
+
+    /// Wait until the database is reachable, right away when it already is
+    pub async fn wait_available(&self) {
+        let mut available = self.available.subscribe();
+        // The sender lives as long as the store, the wait can't fail
+        let _ = available.wait_for(|available| *available).await;
+    }
+
+    /// Check the database in the background until the pool is closed
+    /// Checks follow the retry backoff while unreachable, and the max retry delay otherwise
+    fn watch(&self, options: DbOptions) {
+        let connection = self.connection.clone();
+        let available = self.available.clone();
+
+        tokio::spawn(async move {
+            let mut attempt = 0;
+            while !connection.is_closed() {
+                let reachable = sqlx::query("SELECT 1").execute(&connection).await.is_ok();
+                if available.send_replace(reachable) != reachable {
+                    match reachable {
+                        true => println!("🛢️ Database reachable again"),
+                        false => eprintln!("⚠️ Database unreachable, running in degraded mode"),
+                    }
+                }
+
+                let delay = match reachable {
+                    true => {
+                        attempt = 0;
+                        options.retry_max_delay()
+                    }
+                    false => {
+                        attempt += 1;
+                        options.retry_delay(attempt - 1)
+                    }
+                };
+                tokio::time::sleep(delay).await;
+            }
+        });
+    }
+
+    /// An invalid ssl mode (e.g set by hand rather than parsed) is a config error, never a silent fallback
+    fn connect_options(config: &DbConfig) -> Result<PgConnectOptions, ConfigError> {
+        let options = &config.options;
+
+        let mut connect_options = PgConnectOptions::new()
+            .host(&config.host)
+            .port(config.port)
+            .username(&config.user)
+            .password(config.password.expose())
+            .database(&config.name)
+            .ssl_mode(options.pg_ssl_mode()?);
+        if let Some(ssl_root_cert) = &options.ssl_root_cert {
+            connect_options = connect_options.ssl_root_cert(ssl_root_cert);
+        }
+        if let Some(statement_timeout) = options.statement_timeout() {
+            let statement_timeout = statement_timeout.as_millis().to_string();
+            connect_options = connect_options.options([("statement_timeout", statement_timeout)]);
+        }
+
+        Ok(connect_options)
+    }
+
+    fn pool_options(options: &DbOptions) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .min_connections(options.min_connections)
+            .max_connections(options.max_connections)
+            .acquire_timeout(options.acquire_timeout())
+            .idle_timeout(options.idle_timeout())
+            .max_lifetime(options.max_lifetime())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreachable_config(degraded_start: bool) -> DbConfig {
+        DbConfig {
+            host: "127.0.0.1".into(),
+            port: 1,
+            options: DbOptions {
+                connect_retries: 1,
+                retry_delay_ms: 10,
+                acquire_timeout_secs: 1,
+                degraded_start,
+                ..DbOptions::default()
+            },
+            ..DbConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_fails() {
+        // Act
+        let db_store = DbStore::create_postgres_store(&unreachable_config(false)).await;
+
+        // Assert
+        match db_store {
+            Err(AppError {
+                error: Errors::Server(ServerError::DbConnection { .. }),
+            }) => (),
+            other => panic!("Unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_degraded_start() {
+        // Act
+        let db_store = DbStore::create_postgres_store(&unreachable_config(true))
             .await
-        {
-            Ok(pool) => pool,
-            Err(e) => panic!("Couldn't establish DB connection: {}", e),
-        };
+            .unwrap();
 
-        Ok(DbStore {
-            connection: db_pool,
-        })
+        // Assert
+        assert!(!db_store.is_available());
     }
 }
@@ -1,12 +1,120 @@
 use config::Config;
-use std::collections::HashMap;
+use errors::ConfigError;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-pub fn read_config_file() -> HashMap<String, String> {
-    let file = concat!(env!("CARGO_MANIFEST_DIR"), "/config");
-    let config = Config::builder()
-        .add_source(config::File::with_name(file))
+use crate::config::config_env::ConfigEnv;
+
+/// Config file looked up when none is set (any supported extension)
+const DEFAULT_CONFIG_FILE: &str = "config";
+const CONFIG_FILE_EXTENSIONS: [&str; 4] = ["toml", "yaml", "yml", "json"];
+
+/// Config file path, by priority:
+/// `--config <path>` argument, CONFIG_FILE env variable, then `config.*` in the executable directory
+/// or the working directory. Fails when none of them is set nor found
+/// Paths without extension are resolved against the supported formats (toml, yaml, json)
+pub fn config_file_path(env: &ConfigEnv) -> Result<PathBuf, ConfigError> {
+    if let Some(path) = env.config_path() {
+        return Ok(PathBuf::from(path));
+    }
+
+    env.config_dirs()
+        .iter()
+        .map(|dir| dir.join(DEFAULT_CONFIG_FILE))
+        .find(|path| {
+            CONFIG_FILE_EXTENSIONS
+                .iter()
+                .any(|ext| path.with_extension(ext).is_file())
+        })
+        .ok_or_else(|| ConfigError::MissingFile {
+            searched: env
+                .config_dirs()
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+}
+
+/// Profile overlay of a config file (e.g config.staging.toml for config.toml and the staging profile)
+pub fn profile_file_path(path: &Path, profile: &str) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension().map(|ext| ext.to_string_lossy()) {
+        Some(ext) if CONFIG_FILE_EXTENSIONS.contains(&ext.as_ref()) => {
+            format!("{stem}.{profile}.{ext}")
+        }
+        // The format is resolved by the config loader (e.g config.staging.toml)
+        _ => format!("{file_name}.{profile}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Files the config can be read from: the config file and its `profile` overlay
+/// Paths without extension are expanded to every supported format, none without config file
+pub fn config_files(env: &ConfigEnv, profile: &str) -> Vec<PathBuf> {
+    let Ok(path) = config_file_path(env) else {
+        return vec![];
+    };
+    let overlay = profile_file_path(&path, profile);
+
+    [path, overlay]
+        .into_iter()
+        .flat_map(|path| match path.extension() {
+            Some(ext) if CONFIG_FILE_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()) => {
+                vec![path]
+            }
+            _ => CONFIG_FILE_EXTENSIONS
+                .iter()
+                .map(|ext| PathBuf::from(format!("{}.{ext}", path.display())))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Read the config file, overridden by its optional `profile` overlay
+pub fn read_config_file(
+    env: &ConfigEnv,
+    profile: &str,
+) -> Result<HashMap<String, String>, ConfigError> {
+    let path = config_file_path(env)?;
+    let overlay = profile_file_path(&path, profile);
+
+    Config::builder()
+        .add_source(config::File::from(path.as_path()))
+        .add_source(config::File::from(overlay.as_path()).required(false))
         .build()
-        .unwrap();
+        .and_then(|config| config.try_deserialize::<HashMap<String, String>>())
+        .map_err(|err| ConfigError::UnreadableFile {
+            reason: err.to_string(),
+        })
+}
+
+/// Value of a config file key
+pub fn file_value(config_map: &HashMap<String, String>, key: &str) -> Result<String, ConfigError> {
+    config_map
+        .get(key)
+        .cloned()
+        .ok_or_else(|| ConfigError::MissingKey { key: key.into() })
+}
+
+/// Parse a raw config value, `expected` describes the type in the error message
+pub fn parse_value<T: FromStr>(key: &str, value: String, expected: &str) -> Result<T, ConfigError> {
+    value.parse::<T>().map_err(|_| ConfigError::InvalidValue {
+        key: key.into(),
+        expected: expected.into(),
+    })
+}
 
-    config.try_deserialize::<HashMap<String, String>>().unwrap()
+/// Trimmed items of a comma separated list, empty items are skipped
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
 }
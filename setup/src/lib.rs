@@ -1,17 +1,26 @@
-#[macro_use]
-extern crate lazy_static;
-
 pub mod config;
 pub mod db_store;
 pub mod helpers;
+pub mod listener;
+pub mod shutdown;
+pub mod telemetry;
+pub mod tls;
+
+use config::{app_config::AppConfig, auth_config::AuthConfig, config_env::ConfigEnv};
+use errors::{ConfigError, ConfigErrors};
 
-pub mod setup_config {
-    use crate::{config::app_config::AppConfig, config::auth_config::AuthConfig};
+/// Build the app and auth configs from the process env and arguments
+/// Every problem of both configs is collected into one report
+pub fn load_config() -> Result<(AppConfig, AuthConfig), ConfigError> {
+    let env = ConfigEnv::from_process();
+    let mut errors = ConfigErrors::default();
+    let app_config = errors.check(AppConfig::from_env(&env));
+    let auth_config = app_config
+        .as_ref()
+        .and_then(|app_config| errors.check(AuthConfig::from_env(&env, app_config)));
 
-    lazy_static! {
-        pub static ref APP_CONFIG: AppConfig = AppConfig::new().expect("Error building app config");
-        pub static ref AUTH_CONFIG: AuthConfig = AuthConfig::new();
+    match (app_config, auth_config) {
+        (Some(app_config), Some(auth_config)) => Ok((app_config, auth_config)),
+        _ => Err(errors.into()),
     }
 }
-
-pub use setup_config::*;
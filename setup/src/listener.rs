@@ -0,0 +1,134 @@
+use std::{
+    fmt, fs, io,
+    net::TcpListener,
+    os::unix::{
+        fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+        net::UnixListener,
+    },
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::config::listen_options::ListenAddr;
+
+/// Listener bound by `bind`, served by either adapter
+pub enum Listener {
+    Tcp(TcpListener),
+    /// Served over plain HTTP, even when TLS is enabled
+    Unix(UnixListener, SocketFile),
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "{addr}"),
+                Err(_) => write!(f, "tcp"),
+            },
+            Self::Unix(_, file) => write!(f, "{}", ListenAddr::Unix(file.0.clone())),
+        }
+    }
+}
+
+impl Listener {
+    /// Url of the listener for startup logs (e.g https://[::1]:3000 or unix:/run/ws.sock)
+    pub fn url(&self, tls: bool) -> String {
+        match (self, tls) {
+            (Self::Tcp(_), true) => format!("https://{self}"),
+            (Self::Tcp(_), false) => format!("http://{self}"),
+            (Self::Unix(..), _) => self.to_string(),
+        }
+    }
+}
+
+/// Socket file of a unix listener, removed once dropped (server stopped)
+pub struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Bind every address, unix socket files get the `unix_socket_mode` permissions
+/// A stale socket file left by a previous run is replaced, any other file is an error
+pub fn bind(addrs: &[ListenAddr], unix_socket_mode: u32) -> io::Result<Vec<Listener>> {
+    addrs
+        .iter()
+        .map(|addr| {
+            let listener = match addr {
+                ListenAddr::Tcp(addr) => TcpListener::bind(addr).map(Listener::Tcp),
+                ListenAddr::Unix(path) => bind_unix(path, unix_socket_mode),
+            };
+            listener.map_err(|err| io::Error::new(err.kind(), format!("{addr}: {err}")))
+        })
+        .collect()
+}
+
+fn bind_unix(path: &Path, mode: u32) -> io::Result<Listener> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "File exists and is not a socket",
+            ))
+        }
+        Err(_) => {}
+    }
+
+    // Bound in a private directory, then moved in place once its permissions are set,
+    // so the socket is never reachable with the default permissions
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let private_dir = parent.join(format!(".ws-bind-{}", process::id()));
+    // Left by a previous run that had the same pid
+    let _ = fs::remove_dir_all(&private_dir);
+    fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+    let listener = bind_private(&private_dir, path, mode);
+    let _ = fs::remove_dir_all(&private_dir);
+
+    Ok(Listener::Unix(listener?, SocketFile(path.to_path_buf())))
+}
+
+fn bind_private(private_dir: &Path, path: &Path, mode: u32) -> io::Result<UnixListener> {
+    let private_path = private_dir.join("socket");
+    let listener = UnixListener::bind(&private_path)?;
+    fs::set_permissions(&private_path, fs::Permissions::from_mode(mode))?;
+    fs::rename(&private_path, path)?;
+
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_tcp_and_unix() {
+        // Arrange
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ws-test.sock");
+        let addrs = [
+            ListenAddr::Tcp(([127, 0, 0, 1], 0).into()),
+            ListenAddr::Unix(path.clone()),
+        ];
+
+        // Act
+        let listeners = bind(&addrs, 0o600).unwrap();
+
+        // Assert
+        assert!(listeners[0].url(true).starts_with("https://127.0.0.1:"));
+        assert_eq!(listeners[1].url(true), format!("unix:{}", path.display()));
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // The private bind directory is removed, the moved socket still accepts connections
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+
+        drop(listeners);
+        assert!(!path.exists());
+    }
+}
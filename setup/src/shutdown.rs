@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+
+/// Shutdown trigger shared by a server and its owner
+/// Triggered by SIGTERM/SIGINT (see `listen_signals`) or in-process with `trigger`
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Start the shutdown, every waiter is released
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Wait until the shutdown is triggered (returns right away when it already is)
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, the channel can't be closed here
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Trigger the shutdown on SIGTERM or SIGINT
+    pub fn listen_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let (Ok(mut terminate), Ok(mut interrupt)) = (
+                signal(SignalKind::terminate()),
+                signal(SignalKind::interrupt()),
+            ) else {
+                eprintln!("⚠️ Can't listen to shutdown signals");
+                return;
+            };
+            tokio::select! {
+                _ = terminate.recv() => println!("🛑 SIGTERM received, shutting down"),
+                _ = interrupt.recv() => println!("🛑 SIGINT received, shutting down"),
+            }
+            shutdown.trigger();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trigger_releases_waiters() {
+        // Arrange
+        let shutdown = Shutdown::new();
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.wait().await }
+        });
+
+        // Act
+        shutdown.trigger();
+
+        // Assert
+        assert!(shutdown.is_triggered());
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("Waiter released")
+            .unwrap();
+    }
+}
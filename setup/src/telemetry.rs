@@ -0,0 +1,128 @@
+use std::{error::Error, str::FromStr, sync::OnceLock};
+
+use opentelemetry::{global, trace::TracerProvider as _};
+use opentelemetry_otlp::{ExporterBuildError, Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+    Resource,
+};
+use tracing::Subscriber;
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    fmt,
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use crate::config::{app_config::EnvMode, telemetry_options::TelemetryOptions};
+
+/// Swap the max level of the logs, set by `init`
+static SET_LEVEL: OnceLock<Box<dyn Fn(LevelFilter) + Send + Sync>> = OnceLock::new();
+
+/// Installed tracing subscriber, exported spans are flushed once dropped (end of main)
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(err) = provider.shutdown() {
+                eprintln!("⚠️ Spans not flushed. {err}");
+            }
+        }
+    }
+}
+
+/// Install the global tracing subscriber, logging JSON lines in production and readable lines otherwise
+/// `log` records of the dependencies are forwarded to it, sqlx statements only from the warn level
+/// With an OTLP endpoint, the info spans are exported whatever the log level
+pub fn init(
+    env_mode: EnvMode,
+    level: LevelFilter,
+    options: &TelemetryOptions,
+) -> Result<Telemetry, Box<dyn Error + Send + Sync>> {
+    let provider = tracer_provider(options)?;
+    let (level_filter, handle) = reload::Layer::new(level);
+    let logs = match env_mode {
+        EnvMode::Production => fmt::layer().json().with_current_span(true).boxed(),
+        _ => fmt::layer().boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            Targets::new()
+                .with_default(LevelFilter::TRACE)
+                .with_target("sqlx", LevelFilter::WARN),
+        )
+        .with(provider.as_ref().map(otel_layer))
+        .with(logs.with_filter(level_filter))
+        .try_init()?;
+    propagate_trace_context();
+
+    let _ = SET_LEVEL.set(Box::new(move |level| {
+        let _ = handle.reload(level);
+    }));
+    set_level(level);
+    Ok(Telemetry { provider })
+}
+
+/// Change the max level of the logs, no-op until `init` is called
+pub fn set_level(level: LevelFilter) {
+    if let Some(set_level) = SET_LEVEL.get() {
+        set_level(level);
+    }
+    // Forwarded `log` records are filtered before reaching the subscriber
+    log::set_max_level(
+        log::LevelFilter::from_str(&level.to_string()).unwrap_or(log::LevelFilter::Trace),
+    );
+}
+
+/// Read and forward the W3C `traceparent` and `tracestate` headers
+pub fn propagate_trace_context() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+}
+
+/// OTLP/HTTP exporter of the options, None when no endpoint is set
+/// New traces are sampled by ratio, traces started upstream keep their sampling decision
+pub fn tracer_provider(
+    options: &TelemetryOptions,
+) -> Result<Option<SdkTracerProvider>, ExporterBuildError> {
+    let Some(endpoint) = &options.otlp_endpoint else {
+        return Ok(None);
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpBinary)
+        .with_endpoint(format!("{endpoint}/v1/traces"))
+        .build()?;
+
+    Ok(Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                options.otlp_sample_ratio,
+            ))))
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(options.otlp_service_name.clone())
+                    .build(),
+            )
+            .build(),
+    ))
+}
+
+/// Layer exporting the info spans (request and controllers) to the `provider`
+pub fn otel_layer<S>(provider: &SdkTracerProvider) -> impl Layer<S> + Send + Sync
+where
+    S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("rust-webservice-study"))
+        .with_filter(LevelFilter::INFO)
+}
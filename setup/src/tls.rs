@@ -0,0 +1,255 @@
+use std::{
+    fs,
+    io::BufReader,
+    net::IpAddr,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use errors::ConfigError;
+use rustls::{
+    server::{AllowAnyAuthenticatedClient, ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, RootCertStore,
+};
+use rustls_pemfile::Item;
+use tokio::task::JoinHandle;
+
+use crate::config::tls_options::{self, TlsOptions};
+
+/// Delay between two checks of the certificate files modification time
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// HTTPS config shared by both servers (rustls)
+/// The certificate is reloaded when its files change, without restarting
+#[derive(Clone)]
+pub struct TlsConfig {
+    pub server_config: Arc<rustls::ServerConfig>,
+    /// Plain HTTP port redirecting to HTTPS
+    pub redirect_port: Option<u16>,
+    resolver: Arc<CertResolver>,
+}
+
+impl TlsConfig {
+    /// HTTPS config of the options, None when no certificate is set
+    pub fn load(options: &TlsOptions) -> Result<Option<Self>, ConfigError> {
+        let (Some(cert_path), Some(key_path)) = (&options.tls_cert, &options.tls_key) else {
+            return Ok(None);
+        };
+
+        let resolver = Arc::new(CertResolver {
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            key: RwLock::new(Arc::new(certified_key(cert_path, key_path)?)),
+        });
+
+        let builder = rustls::ServerConfig::builder().with_safe_defaults();
+        let builder = match &options.tls_client_ca {
+            Some(ca_path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(tls_options::CLIENT_CA, ca_path)? {
+                    roots
+                        .add(&cert)
+                        .map_err(|err| ConfigError::InvalidCertificate {
+                            key: tls_options::CLIENT_CA.into(),
+                            reason: err.to_string(),
+                        })?;
+                }
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut server_config = builder.with_cert_resolver(resolver.clone());
+        server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(Some(Self {
+            server_config: Arc::new(server_config),
+            redirect_port: options.tls_redirect_port,
+            resolver,
+        }))
+    }
+
+    /// Read the certificate and key files again
+    /// An invalid pair leaves the current certificate in use
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        self.resolver.reload()
+    }
+
+    /// Reload the certificate when its files change
+    pub fn watch(&self) -> JoinHandle<()> {
+        let resolver = self.resolver.clone();
+        tokio::spawn(async move {
+            let mut modified = resolver.files_modified();
+            let mut interval = tokio::time::interval(FILE_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = resolver.files_modified();
+                if now == modified {
+                    continue;
+                }
+                modified = now;
+                match resolver.reload() {
+                    Ok(()) => println!("🔐 TLS certificate reloaded"),
+                    Err(err) => eprintln!("⚠️ TLS certificate not reloaded. {err}"),
+                }
+            }
+        })
+    }
+}
+
+/// HTTPS url of a plain HTTP request, for the redirect server
+/// The host comes from the Host header (without its port), the `fallback` ip when missing
+pub fn https_location(
+    host: Option<&str>,
+    fallback: IpAddr,
+    https_port: u16,
+    path_and_query: &str,
+) -> String {
+    let fallback = match fallback {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    };
+    let host = host.unwrap_or(&fallback);
+    // Strip the port, keeping IPv6 brackets (e.g [::1]:8080)
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    match https_port {
+        443 => format!("https://{host}{path_and_query}"),
+        port => format!("https://{host}:{port}{path_and_query}"),
+    }
+}
+
+/// Certificate of the current files, swapped on reload
+struct CertResolver {
+    cert_path: String,
+    key_path: String,
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.key
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+}
+
+impl CertResolver {
+    fn reload(&self) -> Result<(), ConfigError> {
+        let key = certified_key(&self.cert_path, &self.key_path)?;
+        *self.key.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(key);
+        Ok(())
+    }
+
+    fn files_modified(&self) -> [Option<SystemTime>; 2] {
+        [&self.cert_path, &self.key_path]
+            .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+    }
+}
+
+fn certified_key(cert_path: &str, key_path: &str) -> Result<CertifiedKey, ConfigError> {
+    let certs = read_certs(tls_options::CERT, cert_path)?;
+    let key = read_key(key_path)?;
+    let key = sign::any_supported_type(&key).map_err(|err| ConfigError::InvalidCertificate {
+        key: tls_options::KEY.into(),
+        reason: err.to_string(),
+    })?;
+    Ok(CertifiedKey::new(certs, key))
+}
+
+fn read_certs(key: &str, path: &str) -> Result<Vec<Certificate>, ConfigError> {
+    let invalid = |reason: String| ConfigError::InvalidCertificate {
+        key: key.into(),
+        reason,
+    };
+    let file = fs::File::open(path).map_err(|err| invalid(err.to_string()))?;
+    let certs =
+        rustls_pemfile::certs(&mut BufReader::new(file)).map_err(|err| invalid(err.to_string()))?;
+
+    match certs.is_empty() {
+        true => Err(invalid("No certificate found.".into())),
+        false => Ok(certs.into_iter().map(Certificate).collect()),
+    }
+}
+
+/// First private key of the file (PKCS#8, PKCS#1 or SEC1)
+fn read_key(path: &str) -> Result<PrivateKey, ConfigError> {
+    let invalid = |reason: String| ConfigError::InvalidCertificate {
+        key: tls_options::KEY.into(),
+        reason,
+    };
+    let file = fs::File::open(path).map_err(|err| invalid(err.to_string()))?;
+    let mut reader = BufReader::new(file);
+
+    loop {
+        match rustls_pemfile::read_one(&mut reader).map_err(|err| invalid(err.to_string()))? {
+            Some(Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key)) => {
+                return Ok(PrivateKey(key))
+            }
+            Some(_) => continue,
+            None => return Err(invalid("No private key found.".into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/tls");
+
+    fn options(key: &str, client_ca: Option<&str>) -> TlsOptions {
+        TlsOptions {
+            tls_cert: Some(format!("{FIXTURES}/cert.pem")),
+            tls_key: Some(format!("{FIXTURES}/{key}")),
+            tls_client_ca: client_ca.map(|ca| format!("{FIXTURES}/{ca}")),
+            tls_redirect_port: None,
+        }
+    }
+
+    #[test]
+    fn test_load_tls_config() {
+        // Act
+        let tls = TlsConfig::load(&options("key.pem", Some("ca.pem"))).unwrap();
+
+        // Assert
+        let tls = tls.expect("TLS enabled");
+        assert!(tls.reload().is_ok());
+        assert!(TlsConfig::load(&TlsOptions::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_key_file() {
+        // Act
+        let tls = TlsConfig::load(&options("cert.pem", None));
+
+        // Assert
+        match tls {
+            Err(ConfigError::InvalidCertificate { key, .. }) => assert_eq!(key, tls_options::KEY),
+            _ => panic!("Expected an invalid certificate error"),
+        }
+    }
+
+    #[test]
+    fn test_https_location() {
+        // Arrange
+        let ipv4 = IpAddr::from([127, 0, 0, 1]);
+        let ipv6 = IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]);
+
+        // Assert
+        assert_eq!(
+            https_location(Some("cats.dev:8080"), ipv4, 443, "/api/cats/?page=2"),
+            "https://cats.dev/api/cats/?page=2"
+        );
+        assert_eq!(
+            https_location(Some("[::1]:8080"), ipv4, 3443, "/"),
+            "https://[::1]:3443/"
+        );
+        assert_eq!(https_location(None, ipv6, 3443, "/"), "https://[::1]:3443/");
+    }
+}
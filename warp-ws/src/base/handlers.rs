@@ -1,10 +1,46 @@
-use std::{convert::Infallible, sync::Arc};
+use std::sync::Arc;
 
-use common::InfoPayload;
+use common::{metrics, InfoPayload};
 use domains::data_source::DataSource;
-use warp::Reply;
+use errors::{AppError, Errors, ServerError};
+use warp::{http::StatusCode, Rejection, Reply};
 
-pub async fn check_health(_data: Arc<DataSource>) -> Result<impl Reply, Infallible> {
+/// Not ready while the database is unreachable (degraded mode)
+pub async fn check_health(data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+    if !data.is_ready() {
+        return Err(warp::reject::custom(AppError::new(Errors::Server(
+            ServerError::Unavailable,
+        ))));
+    }
     let message = "[warp-ws] Instance of Warp server is running".into();
     Ok(warp::reply::json(&InfoPayload { message }))
 }
+
+/// Prometheus metrics, the database pool is sampled on each scrape
+pub async fn render_metrics(data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+    data.record_metrics().await;
+    Ok(warp::reply::with_header(
+        metrics::render(),
+        "content-type",
+        metrics::content_type(),
+    ))
+}
+
+/// Up as long as the process answers, whatever the state of its dependencies
+pub async fn check_liveness() -> Result<impl Reply, Rejection> {
+    let message = "[warp-ws] Instance of Warp server is alive".into();
+    Ok(warp::reply::json(&InfoPayload { message }))
+}
+
+/// Dependencies checked live, the report comes with a 503 when one of them fails
+pub async fn check_readiness(data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+    let readiness = data.readiness().await;
+    let status = match readiness.is_ready() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&readiness),
+        status,
+    ))
+}
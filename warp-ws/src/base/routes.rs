@@ -6,10 +6,20 @@ use warp::{Filter, Rejection, Reply};
 
 use super::handlers;
 
+/// Route templates of the request metrics, `/metrics/` being served outside of the `/api` scope
+pub const ROUTE_TEMPLATES: [&str; 4] = [
+    "/api/health/",
+    "/api/health/live/",
+    "/api/health/ready/",
+    "/metrics/",
+];
+
 pub fn routes_config(
     data: Arc<DataSource>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     get_health(data.clone())
+        .or(get_liveness())
+        .or(get_readiness(data))
 }
 
 pub fn get_health(
@@ -20,3 +30,87 @@ pub fn get_health(
         .and(warp::any().map(move || data.clone()))
         .and_then(handlers::check_health)
 }
+
+/// Served outside of the `/api` scope
+pub fn get_metrics(
+    data: Arc<DataSource>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("metrics")
+        .and(warp::get())
+        .and(warp::any().map(move || data.clone()))
+        .and_then(handlers::render_metrics)
+}
+
+pub fn get_liveness() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("health" / "live")
+        .and(warp::get())
+        .and_then(handlers::check_liveness)
+}
+
+pub fn get_readiness(
+    data: Arc<DataSource>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("health" / "ready")
+        .and(warp::get())
+        .and(warp::any().map(move || data.clone()))
+        .and_then(handlers::check_readiness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readiness_fails_once_shutting_down() {
+        // Arrange
+        let data = Arc::new(DataSource::mock(None));
+        let reply_filter = &routes_config(data.clone());
+
+        // Act
+        let ready = warp::test::request()
+            .path("/health/ready/")
+            .reply(reply_filter)
+            .await;
+        data.stop_serving();
+        let stopping = warp::test::request()
+            .path("/health/ready/")
+            .reply(reply_filter)
+            .await;
+        let live = warp::test::request()
+            .path("/health/live/")
+            .reply(reply_filter)
+            .await;
+
+        // Assert
+        assert_eq!(ready.status(), 200);
+        assert_eq!(stopping.status(), 503);
+        let report: serde_json::Value = serde_json::from_slice(stopping.body()).unwrap();
+        assert_eq!(report["status"], "unavailable");
+        assert_eq!(live.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_requests_by_route() {
+        // Arrange
+        let data = Arc::new(DataSource::mock(None));
+        let templates = Arc::new(vec!["/health/ready/".to_owned()]);
+        let reply_filter = &crate::helpers::start_request_metrics(templates)
+            .and(routes_config(data.clone()).or(get_metrics(data)))
+            .map(crate::helpers::finish_request_metrics);
+
+        // Act
+        warp::test::request()
+            .path("/health/ready/")
+            .reply(reply_filter)
+            .await;
+        let metrics = warp::test::request()
+            .path("/metrics/")
+            .reply(reply_filter)
+            .await;
+
+        // Assert
+        assert_eq!(metrics.status(), 200);
+        let body = String::from_utf8_lossy(metrics.body());
+        assert!(body.contains(r#"route="/health/ready/",status="200""#));
+    }
+}
@@ -1,6 +1,15 @@
 use errors::AppError;
-use setup::{config::app_config::DataMode, APP_CONFIG};
-use std::env;
+use setup::{
+    config::{
+        app_config::{AppConfig, DataMode},
+        runtime_config::{ConfigReloader, RuntimeConfig, SharedConfig},
+    },
+    listener,
+    shutdown::Shutdown,
+    telemetry,
+    tls::TlsConfig,
+};
+use std::{env, process};
 use warp_ws::start;
 
 use domains::data_source::DataSource;
@@ -13,22 +22,78 @@ async fn main() -> Result<(), AppError> {
     let env_file = concat!(env!("CARGO_MANIFEST_DIR"), "/.env");
     dotenv::from_path(env_file).ok();
 
+    // Print every config problem at once and exit
+    let app_config = match AppConfig::new() {
+        Ok(app_config) => app_config,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+
+    // Report which layer supplied each config value
+    if let Some(layers) = &app_config.layers {
+        println!("⚙️ Config layers:\n{layers}");
+    }
+
+    // Init tracing, the max level comes from the (reloadable) runtime config
+    // Exported spans are flushed when `_telemetry` drops, once the server stopped
+    let runtime = RuntimeConfig::new(&app_config.server, app_config.env_mode);
+    let _telemetry = match telemetry::init(
+        app_config.env_mode,
+        runtime.log_level,
+        &app_config.server.telemetry,
+    ) {
+        Ok(telemetry) => Some(telemetry),
+        Err(err) => {
+            eprintln!("⚠️ Tracing not initialized. {err}");
+            None
+        }
+    };
+    let runtime = SharedConfig::new(runtime);
+
     // Data source selection
-    let data_source = match &APP_CONFIG.data_mode {
+    let data_source = match &app_config.data_mode {
         DataMode::File => {
             println!("📄 Data source set to: File");
             DataSource::mock(None)
         }
         DataMode::Database => {
             println!("🛢️ Data source set to: Db");
-            DataSource::db().await
+            DataSource::db(&app_config.database).await?
         }
     };
 
-    let addr = &APP_CONFIG.server.format_url();
+    // Refuse to start on an outdated database schema
+    data_source.prepare_schema(app_config.auto_migrate).await?;
+
+    // HTTPS when a certificate is configured, reloaded when its files change
+    let tls = match TlsConfig::load(&app_config.server.tls) {
+        Ok(tls) => tls,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+    if let Some(tls) = &tls {
+        tls.watch();
+    }
+
+    // Bind every listen address before the config moves into the reloader
+    let listeners = listener::bind(
+        &app_config.server.listen_addrs(),
+        app_config.server.listen.unix_socket_mode,
+    )?;
+
+    // Reload the config on SIGHUP or config file change
+    ConfigReloader::new(app_config, runtime.clone()).spawn();
+
+    // Stop gracefully on SIGTERM/SIGINT
+    let shutdown = Shutdown::new();
+    shutdown.listen_signals();
 
     // Start server-app
-    start(data_source, addr).await?;
+    start(data_source, runtime, shutdown, listeners, tls).await?;
 
     Ok(())
 }
@@ -1,16 +1,22 @@
 use std::sync::Arc;
 
-use common::{InfoPayload, SuccessPayload};
+use common::{etag, InfoPayload, SuccessPayload};
 use domains::{
+    audit::models::AuditContext,
     cat::{
         controller_db, controller_mock,
-        models::{NewCat, ReplaceCat, UpdateCat},
+        models::{Cat, NewCat, ReplaceCat, UpdateCat},
     },
     data_source::DataSource,
 };
-use warp::{Rejection, Reply};
+use warp::{reply::Response, Rejection, Reply};
 
-pub async fn fetch_all(data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+use crate::helpers::conditional_json;
+
+pub async fn fetch_all(
+    data: Arc<DataSource>,
+    if_none_match: Option<String>,
+) -> Result<Response, Rejection> {
     match data
         .exec_controller(
             |data_source| Box::pin(controller_mock::select_all(data_source)),
@@ -18,12 +24,16 @@ pub async fn fetch_all(data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
         )
         .await
     {
-        Ok(cats) => Ok(warp::reply::json(&SuccessPayload { data: cats })),
+        Ok(cats) => Ok(conditional_json(if_none_match, etag::strong(&cats), cats)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
-pub async fn fetch_one(cat_id: i32, data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+pub async fn fetch_one(
+    cat_id: i32,
+    data: Arc<DataSource>,
+    if_none_match: Option<String>,
+) -> Result<Response, Rejection> {
     match data
         .exec_controller(
             |data_source| Box::pin(controller_mock::select_one(cat_id, data_source)),
@@ -31,17 +41,33 @@ pub async fn fetch_one(cat_id: i32, data: Arc<DataSource>) -> Result<impl Reply,
         )
         .await
     {
-        Ok(cat) => Ok(warp::reply::json(&SuccessPayload { data: cat })),
+        Ok(cat) => Ok(conditional_json(if_none_match, cat.etag(), cat)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
 /// Add new cat
-pub async fn add_one(data: Arc<DataSource>, new_cat: NewCat) -> Result<impl Reply, Rejection> {
+pub async fn add_one(
+    data: Arc<DataSource>,
+    audit: AuditContext,
+    new_cat: NewCat,
+) -> Result<impl Reply, Rejection> {
     match data
         .exec_controller(
-            |data_source| Box::pin(controller_mock::create_one(new_cat.clone(), data_source)),
-            |data_source| Box::pin(controller_db::create_one(new_cat.clone(), data_source)),
+            |data_source| {
+                Box::pin(controller_mock::create_one(
+                    new_cat.clone(),
+                    &audit,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::create_one(
+                    new_cat.clone(),
+                    &audit,
+                    data_source,
+                ))
+            },
         )
         .await
     {
@@ -54,14 +80,21 @@ pub async fn add_one(data: Arc<DataSource>, new_cat: NewCat) -> Result<impl Repl
 pub async fn modify_one(
     cat_id: i32,
     data: Arc<DataSource>,
+    audit: AuditContext,
+    if_match: Option<String>,
     update_cat: UpdateCat,
 ) -> Result<impl Reply, Rejection> {
+    let version = Cat::if_match_version(if_match.as_deref(), &data, cat_id)
+        .await
+        .map_err(warp::reject::custom)?;
+    let update_cat = update_cat.expecting(version);
     match data
         .exec_controller(
             |data_source| {
                 Box::pin(controller_mock::update_one(
                     cat_id,
                     update_cat.clone(),
+                    &audit,
                     data_source,
                 ))
             },
@@ -69,14 +102,24 @@ pub async fn modify_one(
                 Box::pin(controller_db::update_one(
                     cat_id,
                     update_cat.clone(),
+                    &audit,
                     data_source,
                 ))
             },
         )
         .await
     {
-        Ok(cat) => Ok(warp::reply::json(&SuccessPayload { data: cat })),
-        Err(e) => Err(warp::reject::custom(e)),
+        Ok(cat) => Ok(warp::reply::with_header(
+            warp::reply::json(&SuccessPayload { data: &cat }),
+            etag::ETAG,
+            cat.etag(),
+        )),
+        Err(e) => Err(warp::reject::custom(Cat::if_match_error(
+            e,
+            version,
+            update_cat.version,
+            cat_id,
+        ))),
     }
 }
 
@@ -84,14 +127,21 @@ pub async fn modify_one(
 pub async fn replace_one(
     cat_id: i32,
     data: Arc<DataSource>,
+    audit: AuditContext,
+    if_match: Option<String>,
     replace_cat: ReplaceCat,
 ) -> Result<impl Reply, Rejection> {
+    let version = Cat::if_match_version(if_match.as_deref(), &data, cat_id)
+        .await
+        .map_err(warp::reject::custom)?;
+    let replace_cat = replace_cat.expecting(version);
     match data
         .exec_controller(
             |data_source| {
                 Box::pin(controller_mock::replace_one(
                     cat_id,
                     replace_cat.clone(),
+                    &audit,
                     data_source,
                 ))
             },
@@ -99,27 +149,97 @@ pub async fn replace_one(
                 Box::pin(controller_db::replace_one(
                     cat_id,
                     replace_cat.clone(),
+                    &audit,
                     data_source,
                 ))
             },
         )
         .await
     {
-        Ok(cat) => Ok(warp::reply::json(&SuccessPayload { data: cat })),
-        Err(e) => Err(warp::reject::custom(e)),
+        Ok(cat) => Ok(warp::reply::with_header(
+            warp::reply::json(&SuccessPayload { data: &cat }),
+            etag::ETAG,
+            cat.etag(),
+        )),
+        Err(e) => Err(warp::reject::custom(Cat::if_match_error(
+            e,
+            version,
+            replace_cat.version,
+            cat_id,
+        ))),
     }
 }
 
-/// Delete existing cat
-pub async fn remove_one(cat_id: i32, data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+/// Move existing cat to the trash
+pub async fn remove_one(
+    cat_id: i32,
+    data: Arc<DataSource>,
+    audit: AuditContext,
+    if_match: Option<String>,
+) -> Result<impl Reply, Rejection> {
+    let version = Cat::if_match_version(if_match.as_deref(), &data, cat_id)
+        .await
+        .map_err(warp::reject::custom)?;
     match data
         .exec_controller(
-            |data_source| Box::pin(controller_mock::delete_one(cat_id, data_source)),
-            |data_source| Box::pin(controller_db::delete_one(cat_id, data_source)),
+            |data_source| {
+                Box::pin(controller_mock::delete_one(
+                    cat_id,
+                    version,
+                    &audit,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::delete_one(
+                    cat_id,
+                    version,
+                    &audit,
+                    data_source,
+                ))
+            },
         )
         .await
     {
         Ok(result) => Ok(warp::reply::json(&InfoPayload { message: result })),
+        Err(e) => Err(warp::reject::custom(Cat::if_match_error(
+            e, version, version, cat_id,
+        ))),
+    }
+}
+
+/// Trashed cats, most recently deleted first
+pub async fn fetch_trash(data: Arc<DataSource>) -> Result<impl Reply, Rejection> {
+    match data
+        .exec_controller(
+            |data_source| Box::pin(controller_mock::select_trash(data_source)),
+            |data_source| Box::pin(controller_db::select_trash(data_source)),
+        )
+        .await
+    {
+        Ok(cats) => Ok(warp::reply::json(&SuccessPayload { data: cats })),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Restore a trashed cat
+pub async fn restore_one(
+    cat_id: i32,
+    data: Arc<DataSource>,
+    audit: AuditContext,
+) -> Result<impl Reply, Rejection> {
+    match data
+        .exec_controller(
+            |data_source| Box::pin(controller_mock::restore_one(cat_id, &audit, data_source)),
+            |data_source| Box::pin(controller_db::restore_one(cat_id, &audit, data_source)),
+        )
+        .await
+    {
+        Ok(cat) => Ok(warp::reply::with_header(
+            warp::reply::json(&SuccessPayload { data: &cat }),
+            etag::ETAG,
+            cat.etag(),
+        )),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
@@ -5,23 +5,36 @@ use domains::{
     data_source::DataSource,
 };
 
+use common::etag;
+use setup::config::runtime_config::SharedConfig;
 use warp::{Filter, Rejection, Reply};
 
-use crate::helpers::{json_body, with_data};
+use crate::helpers::{json_body, with_audit, with_data};
 
 use super::handlers;
 
+/// Route templates of the request metrics
+pub const ROUTE_TEMPLATES: [&str; 4] = [
+    "/api/cats/",
+    "/api/cats/{cat_id}/",
+    "/api/cats/trash/",
+    "/api/cats/{cat_id}/restore/",
+];
+
 pub fn routes_config(
     data: Arc<DataSource>,
+    runtime: SharedConfig,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     let root = warp::path("cats");
     root.and(
         get_all(data.clone())
             .or(get_one(data.clone()))
-            .or(post_one(data.clone()))
-            .or(patch_one(data.clone()))
-            .or(put_one(data.clone()))
-            .or(delete_one(data.clone())),
+            .or(post_one(data.clone(), runtime.clone()))
+            .or(patch_one(data.clone(), runtime.clone()))
+            .or(put_one(data.clone(), runtime.clone()))
+            .or(delete_one(data.clone()))
+            .or(get_trash(data.clone()))
+            .or(restore_one(data.clone())),
     )
 }
 
@@ -31,6 +44,7 @@ pub fn get_all(
     warp::path!()
         .and(warp::get())
         .and(warp::any().map(move || data.clone()))
+        .and(warp::header::optional::<String>(etag::IF_NONE_MATCH))
         .and_then(handlers::fetch_all)
 }
 
@@ -40,36 +54,45 @@ pub fn get_one(
     warp::path!(i32)
         .and(warp::get())
         .and(warp::any().map(move || data.clone()))
+        .and(warp::header::optional::<String>(etag::IF_NONE_MATCH))
         .and_then(handlers::fetch_one)
 }
 
 pub fn post_one(
     data: Arc<DataSource>,
+    runtime: SharedConfig,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     warp::path!()
         .and(warp::post())
         .and(with_data(data))
-        .and(json_body::<NewCat>())
+        .and(with_audit())
+        .and(json_body::<NewCat>(runtime))
         .and_then(handlers::add_one)
 }
 
 pub fn patch_one(
     data: Arc<DataSource>,
+    runtime: SharedConfig,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     warp::path!(i32)
         .and(warp::patch())
         .and(with_data(data))
-        .and(json_body::<UpdateCat>())
+        .and(with_audit())
+        .and(warp::header::optional::<String>(etag::IF_MATCH))
+        .and(json_body::<UpdateCat>(runtime))
         .and_then(handlers::modify_one)
 }
 
 pub fn put_one(
     data: Arc<DataSource>,
+    runtime: SharedConfig,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     warp::path!(i32)
         .and(warp::put())
         .and(with_data(data))
-        .and(json_body::<ReplaceCat>())
+        .and(with_audit())
+        .and(warp::header::optional::<String>(etag::IF_MATCH))
+        .and(json_body::<ReplaceCat>(runtime))
         .and_then(handlers::replace_one)
 }
 
@@ -79,18 +102,45 @@ pub fn delete_one(
     warp::path!(i32)
         .and(warp::delete())
         .and(with_data(data))
+        .and(with_audit())
+        .and(warp::header::optional::<String>(etag::IF_MATCH))
         .and_then(handlers::remove_one)
 }
 
+pub fn get_trash(
+    data: Arc<DataSource>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("trash")
+        .and(warp::get())
+        .and(with_data(data))
+        .and_then(handlers::fetch_trash)
+}
+
+pub fn restore_one(
+    data: Arc<DataSource>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!(i32 / "restore")
+        .and(warp::post())
+        .and(with_data(data))
+        .and(with_audit())
+        .and_then(handlers::restore_one)
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::Utc;
     use common::{InfoPayload, SuccessPayload};
     use domains::{
         cat::models::{Cat, CatId},
-        data_source::{MockData, MockSource},
+        data_source::{MockData, MockSource, SourceType},
+    };
+
+    use setup::config::{
+        db_config::DbConfig, db_options::DbOptions, runtime_config::RuntimeConfig,
     };
 
+    use crate::serve::RemoteAddr;
+
     use super::*;
 
     fn test_data_mock() -> Arc<DataSource> {
@@ -101,6 +151,8 @@ mod tests {
                 age: 1,
                 weight: None,
                 creation_time: Utc::now(),
+                version: 1,
+                deletion_time: None,
             },
             Cat {
                 id: CatId("2".into()),
@@ -108,6 +160,8 @@ mod tests {
                 age: 1,
                 weight: Some(3.0),
                 creation_time: Utc::now(),
+                version: 1,
+                deletion_time: None,
             },
         ]));
         Arc::new(DataSource::mock(Some(data)))
@@ -122,7 +176,7 @@ mod tests {
         // Act
         let res = warp::test::request().reply(reply_filter).await;
         let res_body = res.body();
-        let payload: SuccessPayload<Vec<Cat>> = serde_json::from_slice(&res_body).unwrap();
+        let payload: SuccessPayload<Vec<Cat>> = serde_json::from_slice(res_body).unwrap();
 
         // Assert
         assert_eq!(payload.data.len(), 2);
@@ -137,17 +191,91 @@ mod tests {
         // Act
         let res = warp::test::request().path("/1").reply(reply_filter).await;
         let res_body = res.body();
-        let payload: SuccessPayload<Cat> = serde_json::from_slice(&res_body).unwrap();
+        let payload: SuccessPayload<Cat> = serde_json::from_slice(res_body).unwrap();
 
         // Assert
         assert_eq!(payload.data.id.0, "1".to_string());
     }
 
+    #[tokio::test]
+    async fn test_get_all_not_modified() {
+        // Arrange
+        let data = test_data_mock();
+        let reply_filter = &get_all(data);
+        let etag = warp::test::request().reply(reply_filter).await.headers()[etag::ETAG].clone();
+
+        // Act
+        let res = warp::test::request()
+            .header(etag::IF_NONE_MATCH, etag.clone())
+            .reply(reply_filter)
+            .await;
+
+        // Assert
+        assert_eq!(res.status(), 304);
+        assert_eq!(res.headers()[etag::ETAG], etag);
+        assert!(res.body().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_one_if_match() {
+        // Arrange
+        let data = test_data_mock();
+        let reply_filter = &delete_one(data).recover(errors::handle_rejection);
+
+        // Act
+        let stale = warp::test::request()
+            .method("DELETE")
+            .path("/2")
+            .header(etag::IF_MATCH, "\"stale\"")
+            .reply(reply_filter)
+            .await;
+        let current = warp::test::request()
+            .method("DELETE")
+            .path("/2")
+            .header(etag::IF_MATCH, "*")
+            .reply(reply_filter)
+            .await;
+
+        // Assert
+        assert_eq!(stale.status(), 412);
+        assert_eq!(current.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_delete_one_if_match_unavailable_database() {
+        // Arrange
+        let config = DbConfig {
+            host: "127.0.0.1".into(),
+            port: 1,
+            options: DbOptions {
+                connect_retries: 1,
+                retry_delay_ms: 10,
+                acquire_timeout_secs: 1,
+                degraded_start: true,
+                ..DbOptions::default()
+            },
+            ..DbConfig::default()
+        };
+        let data = Arc::new(DataSource::db(&config).await.unwrap());
+        let reply_filter = &delete_one(data).recover(errors::handle_rejection);
+
+        // Act
+        let res = warp::test::request()
+            .method("DELETE")
+            .path("/2")
+            .header(etag::IF_MATCH, "*")
+            .reply(reply_filter)
+            .await;
+
+        // Assert
+        assert_eq!(res.status(), 503);
+    }
+
     #[tokio::test]
     async fn test_post_one() {
         // Arrange
         let data = test_data_mock();
-        let reply_filter = &post_one(data);
+        let reply_filter = &post_one(data, SharedConfig::default());
 
         // Act
         let res = warp::test::request()
@@ -161,17 +289,40 @@ mod tests {
             .await;
 
         let res_body = res.body();
-        let payload: SuccessPayload<Cat> = serde_json::from_slice(&res_body).unwrap();
+        let payload: SuccessPayload<Cat> = serde_json::from_slice(res_body).unwrap();
 
         // Assert
         assert_eq!(payload.data.id.0, "3".to_string());
     }
 
+    #[tokio::test]
+    async fn test_post_one_body_over_runtime_limit() {
+        // Arrange
+        let mut config = RuntimeConfig::default();
+        config.options.body_limit_bytes = 32;
+        let reply_filter = &post_one(test_data_mock(), SharedConfig::new(config))
+            .recover(errors::handle_rejection);
+        let post = |body: String| {
+            warp::test::request()
+                .method("POST")
+                .body(body)
+                .reply(reply_filter)
+        };
+
+        // Act
+        let large = post(format!(r#"{{"name": "{}", "age": 2}}"#, "C".repeat(40))).await;
+        let invalid = post(r#"{"name": "C"#.into()).await;
+
+        // Assert
+        assert_eq!(large.status(), 413);
+        assert_eq!(invalid.status(), 400);
+    }
+
     #[tokio::test]
     async fn test_patch_one() {
         // Arrange
         let data = test_data_mock();
-        let reply_filter = &patch_one(data);
+        let reply_filter = &patch_one(data, SharedConfig::default());
 
         // Act
         let res = warp::test::request()
@@ -181,12 +332,13 @@ mod tests {
                 name: None,
                 age: Some(3),
                 weight: Some(7.5),
+                version: None,
             })
             .reply(reply_filter)
             .await;
 
         let res_body = res.body();
-        let payload: SuccessPayload<Cat> = serde_json::from_slice(&res_body).unwrap();
+        let payload: SuccessPayload<Cat> = serde_json::from_slice(res_body).unwrap();
 
         // Assert
         assert_eq!(payload.data.name, "A".to_string());
@@ -197,7 +349,7 @@ mod tests {
     async fn test_put_one() {
         // Arrange
         let data = test_data_mock();
-        let reply_filter = &put_one(data);
+        let reply_filter = &put_one(data, SharedConfig::default());
 
         // Act
         let res = warp::test::request()
@@ -207,12 +359,13 @@ mod tests {
                 name: "Z".into(),
                 age: 5,
                 weight: Some(5.4),
+                version: None,
             })
             .reply(reply_filter)
             .await;
 
         let res_body = res.body();
-        let payload: SuccessPayload<Cat> = serde_json::from_slice(&res_body).unwrap();
+        let payload: SuccessPayload<Cat> = serde_json::from_slice(res_body).unwrap();
 
         // Assert
         assert_eq!(payload.data.name, "Z".to_string());
@@ -224,19 +377,91 @@ mod tests {
     async fn test_delete_one() {
         // Arrange
         let data = test_data_mock();
-        let reply_filter = &delete_one(data);
+        let reply_filter = &delete_one(data.clone());
 
         // Act
         let res = warp::test::request()
             .method("DELETE")
             .path("/2")
+            .extension(RemoteAddr(Some(([127, 0, 0, 1], 4000).into())))
             .reply(reply_filter)
             .await;
 
         let res_body = res.body();
-        let payload: InfoPayload = serde_json::from_slice(&res_body).unwrap();
+        let payload: InfoPayload = serde_json::from_slice(res_body).unwrap();
+
+        // Assert
+        assert!(!payload.message.is_empty());
+        let audit_log = match &data.source {
+            SourceType::Mock(data_source) => data_source.audit_log.read().await.clone(),
+            SourceType::DB(_) => unreachable!(),
+        };
+        assert_eq!(audit_log.len(), 1);
+        assert_eq!(audit_log[0].action, "delete");
+        assert_eq!(audit_log[0].ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(audit_log[0].diff["before"]["name"], "B");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_method_not_allowed() {
+        // Arrange
+        let data = test_data_mock();
+        let routes =
+            &routes_config(data, SharedConfig::default()).recover(errors::handle_rejection);
+
+        // Act
+        let resp = warp::test::request()
+            .method("PATCH")
+            .path("/cats")
+            .reply(routes)
+            .await;
+
+        // Assert
+        let payload: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.status(), 405);
+        assert_eq!(
+            payload["errors"][0],
+            "The method is not allowed on this route."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trash_and_restore() {
+        // Arrange
+        let data = test_data_mock();
+        let routes =
+            &routes_config(data.clone(), SharedConfig::default()).recover(errors::handle_rejection);
+        warp::test::request()
+            .method("DELETE")
+            .path("/cats/2")
+            .reply(routes)
+            .await;
+
+        // Act
+        let trash = warp::test::request()
+            .path("/cats/trash")
+            .reply(routes)
+            .await;
+        let trashed = warp::test::request().path("/cats/2").reply(routes).await;
+        let restored = warp::test::request()
+            .method("POST")
+            .path("/cats/2/restore")
+            .reply(routes)
+            .await;
+        let restored_again = warp::test::request()
+            .method("POST")
+            .path("/cats/2/restore")
+            .reply(routes)
+            .await;
 
         // Assert
-        assert_eq!(payload.message.is_empty(), false);
+        let trash: SuccessPayload<Vec<Cat>> = serde_json::from_slice(trash.body()).unwrap();
+        assert_eq!(trash.data.len(), 1);
+        assert!(trash.data[0].deletion_time.is_some());
+        assert_eq!(trashed.status(), 404);
+        let restored: SuccessPayload<Cat> = serde_json::from_slice(restored.body()).unwrap();
+        assert_eq!(restored.data.name, "B");
+        assert!(restored.data.deletion_time.is_none());
+        assert_eq!(restored_again.status(), 404);
     }
 }
@@ -0,0 +1,168 @@
+use std::convert::Infallible;
+
+use common::{etag, request_id};
+use errors::{handle_rejection, AppError, ClientError, Errors};
+use setup::config::{cors_options::CorsOptions, runtime_config::SharedConfig};
+use warp::{
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    reply::{Reply, Response},
+    Filter, Rejection,
+};
+
+const REQUEST_METHOD: &str = "access-control-request-method";
+const REQUEST_HEADERS: &str = "access-control-request-headers";
+
+/// Cross-origin headers of a reply, set for the origins the runtime config allows
+pub struct CorsHeaders(Vec<(HeaderName, HeaderValue)>);
+
+/// Cross-origin headers for the origin of the request, read from the runtime config on each request
+/// (same policy as actix-ws), empty for same-origin requests and disallowed origins
+pub fn start_cors(
+    runtime: SharedConfig,
+) -> impl Filter<Extract = (CorsHeaders,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(move |request_headers: HeaderMap| {
+        let runtime = runtime.get();
+        let policy = &runtime.options.cors;
+        let headers = request_headers
+            .get(header::ORIGIN)
+            .filter(|origin| {
+                origin
+                    .to_str()
+                    .is_ok_and(|origin| policy.allows_origin(origin))
+            })
+            .cloned()
+            .map(|origin| {
+                let mut headers = vec![
+                    (header::ACCESS_CONTROL_ALLOW_ORIGIN, origin),
+                    (header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed_headers()),
+                ];
+                if policy.credentials {
+                    headers.push((
+                        header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                        HeaderValue::from_static("true"),
+                    ));
+                }
+                headers
+            })
+            .unwrap_or_default();
+        CorsHeaders(headers)
+    })
+}
+
+/// Add the cross-origin headers to the reply, which varies by origin
+pub fn finish_cors(headers: CorsHeaders, reply: impl Reply) -> Response {
+    let mut response = reply.into_response();
+    for (name, value) in headers.0 {
+        response.headers_mut().insert(name, value);
+    }
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("origin"));
+    response
+}
+
+/// Answer the preflight requests of the allowed origins, with the allowed methods and headers
+/// Requests that aren't preflights are left to the other routes (not found), denied ones get a 403
+pub fn preflight(
+    runtime: SharedConfig,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::header::optional::<String>("origin"))
+        .and(warp::header::optional::<String>(REQUEST_METHOD))
+        .and(warp::header::optional::<String>(REQUEST_HEADERS))
+        .and_then(
+            move |method: Method,
+                  origin: Option<String>,
+                  request_method: Option<String>,
+                  request_headers: Option<String>| {
+                let runtime = runtime.get();
+                async move {
+                    let (Method::OPTIONS, Some(origin), Some(request_method)) =
+                        (method, origin, request_method)
+                    else {
+                        return Err(warp::reject::not_found());
+                    };
+                    // A denied preflight is answered here, the other routes must not serve it
+                    match preflight_reply(
+                        &runtime.options.cors,
+                        &origin,
+                        &request_method,
+                        request_headers.as_deref().unwrap_or_default(),
+                    ) {
+                        Ok(response) => Ok(response),
+                        Err(err) => match handle_rejection(warp::reject::custom(err)).await {
+                            Ok(reply) => Ok(reply.into_response()),
+                            Err(infallible) => match infallible {},
+                        },
+                    }
+                }
+            },
+        )
+}
+
+/// Response headers readable by the cross-origin scripts
+fn exposed_headers() -> HeaderValue {
+    HeaderValue::from_str(&[request_id::HEADER, etag::ETAG].join(", "))
+        .unwrap_or(HeaderValue::from_static(""))
+}
+
+fn preflight_reply(
+    policy: &CorsOptions,
+    origin: &str,
+    request_method: &str,
+    request_headers: &str,
+) -> Result<Response, AppError> {
+    let forbidden =
+        |reason: String| AppError::new(Errors::Client(ClientError::Forbidden { reason }));
+
+    if !policy.allows_origin(origin) {
+        return Err(forbidden(format!("Origin {origin} is not allowed.")));
+    }
+    if !policy
+        .methods
+        .iter()
+        .any(|method| method.eq_ignore_ascii_case(request_method.trim()))
+    {
+        return Err(forbidden(format!(
+            "Method {request_method} is not allowed."
+        )));
+    }
+    if let Some(header) = request_headers
+        .split(',')
+        .map(str::trim)
+        .filter(|header| !header.is_empty())
+        .find(|header| {
+            !policy
+                .headers
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(header))
+        })
+    {
+        return Err(forbidden(format!("Header {header} is not allowed.")));
+    }
+
+    let mut response = StatusCode::OK.into_response();
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            policy.methods.join(", "),
+        ),
+        (
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            policy.headers.join(", "),
+        ),
+        (
+            header::ACCESS_CONTROL_MAX_AGE,
+            policy.max_age_secs.to_string(),
+        ),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+    Ok(response)
+}
@@ -0,0 +1,29 @@
+use std::sync::LazyLock;
+
+use warp::{http::header::CONTENT_TYPE, reply::Response, Rejection, Reply};
+
+/// Spec of the routes of this server only, derived from their templates
+static OPENAPI_JSON: LazyLock<String> =
+    LazyLock::new(|| docs::openapi_json(&docs::routed_spec(&crate::route_templates())));
+
+/// Serve the OpenAPI spec, of the routes of this server only
+pub async fn openapi_spec() -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::with_header(
+        OPENAPI_JSON.as_str(),
+        CONTENT_TYPE,
+        "application/json",
+    ))
+}
+
+/// Serve the embedded Swagger UI assets
+pub async fn swagger_ui(tail: warp::path::Tail) -> Result<Response, Rejection> {
+    match docs::swagger_ui_file(tail.as_str()) {
+        Some(file) => {
+            Ok(
+                warp::reply::with_header(file.bytes.into_owned(), CONTENT_TYPE, file.content_type)
+                    .into_response(),
+            )
+        }
+        None => Err(warp::reject::not_found()),
+    }
+}
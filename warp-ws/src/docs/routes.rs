@@ -0,0 +1,119 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::handlers;
+
+/// Route templates of the request metrics, Swagger UI assets included
+pub const ROUTE_TEMPLATES: [&str; 3] = ["/api/openapi.json/", "/api/docs/", "/api/docs/{file}/"];
+
+pub fn routes_config() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    get_openapi_spec().or(get_swagger_ui())
+}
+
+pub fn get_openapi_spec() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("openapi.json")
+        .and(warp::get())
+        .and_then(handlers::openapi_spec)
+}
+
+pub fn get_swagger_ui() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path("docs")
+        .and(warp::get())
+        .and(warp::path::tail())
+        .and_then(handlers::swagger_ui)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use domains::data_source::{DataSource, MockSource};
+    use setup::config::runtime_config::SharedConfig;
+    use utoipa::{openapi::PathItem, OpenApi};
+    use warp::http::{header::CONTENT_TYPE, StatusCode};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_openapi_spec() {
+        // Arrange
+        let reply_filter = &get_openapi_spec();
+
+        // Act
+        let res = warp::test::request()
+            .path("/openapi.json")
+            .reply(reply_filter)
+            .await;
+        let spec: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+
+        // Assert
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["paths"].get("/api/cats/").is_some());
+        assert!(spec["paths"].get("/api/cats/trash/").is_some());
+        assert!(spec["paths"].get("/api/cats/trash/{cat_id}/").is_none());
+        assert!(spec["paths"].get("/api/auth/signup/").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_swagger_ui() {
+        // Arrange
+        let reply_filter = &get_swagger_ui();
+
+        // Act
+        let res = warp::test::request()
+            .path("/docs/")
+            .reply(reply_filter)
+            .await;
+
+        // Assert
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/html");
+    }
+
+    /// Documented methods of a path
+    fn methods(item: &PathItem) -> Vec<&'static str> {
+        [
+            ("GET", item.get.is_some()),
+            ("POST", item.post.is_some()),
+            ("PATCH", item.patch.is_some()),
+            ("PUT", item.put.is_some()),
+            ("DELETE", item.delete.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(method, documented)| documented.then_some(method))
+        .collect()
+    }
+
+    /// Every path of the web service is routed by warp exactly when the served spec documents it
+    #[tokio::test]
+    async fn test_documented_paths_are_routed() {
+        // Arrange
+        let data = Arc::new(DataSource::mock(Some(MockSource::new())));
+        let api = warp::path("api").and(crate::api_routes(data, SharedConfig::default()));
+        let documented = ::docs::routed_spec(&crate::route_templates());
+        let all = ::docs::ApiDoc::openapi();
+
+        for (path, item) in all.paths.paths.iter() {
+            let uri = path.replace("{cat_id}", "1");
+            for method in methods(item) {
+                // Act
+                let res = warp::test::request()
+                    .method(method)
+                    .path(&uri)
+                    .reply(&api)
+                    .await;
+
+                // Assert
+                // Unrouted requests are only rejected as not found or method not allowed,
+                // the errors of routed ones are custom rejections (unhandled without recover)
+                let routed = ![StatusCode::NOT_FOUND, StatusCode::METHOD_NOT_ALLOWED]
+                    .contains(&res.status());
+                let is_documented = documented
+                    .paths
+                    .paths
+                    .get(path)
+                    .is_some_and(|item| methods(item).contains(&method));
+                assert_eq!(routed, is_documented, "{method} {uri}");
+            }
+        }
+    }
+}
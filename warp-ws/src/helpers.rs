@@ -1,8 +1,27 @@
-use std::sync::Arc;
+use std::{convert::Infallible, pin::pin, sync::Arc};
 
-use domains::data_source::DataSource;
-use serde::de::DeserializeOwned;
-use warp::Filter;
+use common::{
+    etag,
+    metrics::{self, RequestTimer},
+    request_id, SuccessPayload,
+};
+use domains::{audit::models::AuditContext, data_source::DataSource};
+use errors::{AppError, ClientError, Errors};
+use futures_util::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use setup::config::runtime_config::SharedConfig;
+use warp::{
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    hyper::body::Buf,
+    path::FullPath,
+    reply::{Reply, Response},
+    Filter, Rejection,
+};
+
+use crate::serve::RemoteAddr;
 
 pub fn with_data(
     data: Arc<DataSource>,
@@ -10,7 +29,166 @@ pub fn with_data(
     warp::any().map(move || data.clone())
 }
 
+/// Origin of the audited mutations, anonymous since warp-ws has no authentication
+pub fn with_audit() -> impl Filter<Extract = (AuditContext,), Error = Infallible> + Clone {
+    warp::ext::optional::<RemoteAddr>().map(|remote_addr: Option<RemoteAddr>| {
+        let ip = remote_addr
+            .and_then(|RemoteAddr(addr)| addr)
+            .map(|addr| addr.ip());
+        AuditContext::new(None, ip)
+    })
+}
+
+/// Json payload of `data` tagged `tag`, 304 without body when the `If-None-Match` copy is up to date
+pub fn conditional_json<T: Serialize>(
+    if_none_match: Option<String>,
+    tag: String,
+    data: T,
+) -> Response {
+    let mut response = match if_none_match {
+        Some(header) if etag::none_match_hit(&header, &tag) => {
+            StatusCode::NOT_MODIFIED.into_response()
+        }
+        _ => warp::reply::json(&SuccessPayload { data }).into_response(),
+    };
+    if let Ok(tag) = HeaderValue::from_str(&tag) {
+        response.headers_mut().insert(etag::ETAG, tag);
+    }
+    response
+}
+
+/// Json body of at most the runtime body limit (16KB by default)
+/// The streamed body is counted as it is read, so a chunked body can't get past the limit either
+/// The limit is read on each request, so a config reload applies right away
 pub fn json_body<T: DeserializeOwned + Send>(
+    runtime: SharedConfig,
 ) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
-    warp::body::content_length_limit(1024 * 16).and(warp::body::json::<T>())
+    warp::body::stream().and_then(move |body| {
+        let limit = runtime.get().body_limit();
+        async move {
+            let bytes = read_body(body, limit).await?;
+            serde_json::from_slice::<T>(&bytes).map_err(|_| {
+                warp::reject::custom(AppError::new(Errors::Client(ClientError::InvalidJson)))
+            })
+        }
+    })
+}
+
+async fn read_body(
+    body: impl Stream<Item = Result<impl Buf, warp::Error>>,
+    limit: u64,
+) -> Result<Vec<u8>, Rejection> {
+    let mut body = pin!(body);
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.next().await {
+        let mut chunk = chunk.map_err(|_| {
+            warp::reject::custom(AppError::new(Errors::Client(ClientError::InvalidJson)))
+        })?;
+        if (bytes.len() + chunk.remaining()) as u64 > limit {
+            return Err(warp::reject::custom(AppError::new(Errors::Client(
+                ClientError::PayloadTooLarge { limit },
+            ))));
+        }
+        bytes.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+    Ok(bytes)
+}
+
+/// CORS layer of the configured policy
+/// Reject requests from origins the runtime config doesn't allow, or declaring a body over its limit
+/// The runtime config is read on each request, so a config reload applies right away
+pub fn with_runtime_limits(
+    runtime: SharedConfig,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and(warp::header::optional::<u64>("content-length"))
+        .and_then(move |origin: Option<String>, length: Option<u64>| {
+            let runtime = runtime.get();
+            async move {
+                if let Some(origin) = origin.filter(|origin| !runtime.allows_origin(origin)) {
+                    return Err(warp::reject::custom(AppError::new(Errors::Client(
+                        ClientError::Forbidden {
+                            reason: format!("Origin {origin} is not allowed."),
+                        },
+                    ))));
+                }
+                let limit = runtime.body_limit();
+                if length.is_some_and(|length| length > limit) {
+                    return Err(warp::reject::custom(AppError::new(Errors::Client(
+                        ClientError::PayloadTooLarge { limit },
+                    ))));
+                }
+                Ok(())
+            }
+        })
+        .untuple_one()
+}
+
+/// Metrics of a request being served, see `start_request_metrics`
+pub struct RequestMetrics {
+    timer: RequestTimer,
+    method: String,
+    route: String,
+}
+
+/// Start timing a request, counted in flight until its reply is ready
+/// warp has no route templates, the path is matched against `templates` (e.g /api/cats/{cat_id}/)
+/// The matched template is also recorded on the request span
+pub fn start_request_metrics(
+    templates: Arc<Vec<String>>,
+) -> impl Filter<Extract = (RequestMetrics,), Error = Infallible> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .map(move |method: Method, path: FullPath| {
+            let method = method.to_string();
+            let route = metrics::route_template(path.as_str(), &templates).to_owned();
+            request_id::record_route(&method, &route);
+            RequestMetrics {
+                timer: RequestTimer::start(),
+                method,
+                route,
+            }
+        })
+}
+
+/// Count the request by route template and status
+pub fn finish_request_metrics(request: RequestMetrics, reply: impl Reply) -> Response {
+    let response = reply.into_response();
+    request
+        .timer
+        .finish(&request.method, &request.route, response.status().as_u16());
+    response
+}
+
+/// Security headers of the runtime config for the requested path, the docs UI has its own CSP
+pub fn start_security_headers(
+    runtime: SharedConfig,
+) -> impl Filter<Extract = (Vec<(HeaderName, HeaderValue)>,), Error = Infallible> + Clone {
+    warp::path::full().map(move |path: FullPath| {
+        let docs_ui = path.as_str().starts_with(::docs::DOCS_PATH);
+        runtime
+            .get()
+            .security_headers
+            .headers(docs_ui)
+            .into_iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_static(name),
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect()
+    })
+}
+
+/// Add the security headers to the reply, headers already set by a route are kept
+pub fn finish_security_headers(
+    headers: Vec<(HeaderName, HeaderValue)>,
+    reply: impl Reply,
+) -> Response {
+    let mut response = reply.into_response();
+    for (name, value) in headers {
+        response.headers_mut().entry(name).or_insert(value);
+    }
+    response
 }
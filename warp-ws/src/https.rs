@@ -0,0 +1,58 @@
+use std::net::IpAddr;
+
+use setup::tls;
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+/// Redirect every request to its HTTPS url, the fallback host being the `ip` of the server
+pub fn redirect_routes(
+    ip: IpAddr,
+    https_port: u16,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("host")
+        .and(warp::path::full())
+        .and(
+            warp::query::raw()
+                .map(|query: String| format!("?{query}"))
+                .or(warp::any().map(String::new))
+                .unify(),
+        )
+        .map(
+            move |host: Option<String>, path: warp::path::FullPath, query: String| {
+                let location = tls::https_location(
+                    host.as_deref(),
+                    ip,
+                    https_port,
+                    &format!("{}{query}", path.as_str()),
+                );
+                warp::reply::with_status(
+                    warp::reply::with_header(warp::reply(), "location", location),
+                    StatusCode::PERMANENT_REDIRECT,
+                )
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redirect_to_https() {
+        // Arrange
+        let routes = redirect_routes(IpAddr::from([127, 0, 0, 1]), 3443);
+
+        // Act
+        let response = warp::test::request()
+            .path("/api/cats/?page=2")
+            .header("host", "localhost:8080")
+            .reply(&routes)
+            .await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers()["location"],
+            "https://localhost:3443/api/cats/?page=2"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::{future::Future, io, sync::Arc, time::Duration};
+
+use futures_util::{stream, TryStream};
+use setup::tls::TlsConfig;
+use tokio::{
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+    sync::{mpsc, Semaphore},
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+/// Pending handshakes a slow acceptor can queue before the accept loop waits
+const HANDSHAKE_QUEUE: usize = 64;
+/// Handshakes running at once, further connections wait in the listener backlog
+const MAX_HANDSHAKES: usize = 128;
+/// Time given to a client to complete its handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause after a failed accept (e.g too many open files), instead of spinning
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+// Connection streams of already bound listeners, to be served by `serve_incoming`
+// A failed accept is retried rather than yielded, since it would stop the server
+
+pub fn tcp(
+    listener: std::net::TcpListener,
+) -> io::Result<impl TryStream<Ok = TcpStream, Error = io::Error> + Send> {
+    listener.set_nonblocking(true)?;
+    Ok(accept_loop(TcpListener::from_std(listener)?))
+}
+
+pub fn unix(
+    listener: std::os::unix::net::UnixListener,
+) -> io::Result<impl TryStream<Ok = UnixStream, Error = io::Error> + Send> {
+    listener.set_nonblocking(true)?;
+    Ok(accept_loop(UnixListener::from_std(listener)?))
+}
+
+/// Listener accepting plain connections (TCP or unix socket)
+trait Accept: Send + Sync + 'static {
+    type Stream: Send;
+
+    fn accept(&self) -> impl Future<Output = io::Result<Self::Stream>> + Send;
+}
+
+impl Accept for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<TcpStream> {
+        TcpListener::accept(self).await.map(|(stream, _)| stream)
+    }
+}
+
+impl Accept for UnixListener {
+    type Stream = UnixStream;
+
+    async fn accept(&self) -> io::Result<UnixStream> {
+        UnixListener::accept(self).await.map(|(stream, _)| stream)
+    }
+}
+
+fn accept_loop<L: Accept>(listener: L) -> impl TryStream<Ok = L::Stream, Error = io::Error> + Send {
+    stream::unfold(listener, |listener| async move {
+        loop {
+            match listener.accept().await {
+                Ok(stream) => return Some((Ok(stream), listener)),
+                Err(_) => tokio::time::sleep(ACCEPT_ERROR_DELAY).await,
+            }
+        }
+    })
+}
+
+/// TLS connections of a TCP listener
+/// Handshakes run concurrently so a slow client can't hold the others back, up to `MAX_HANDSHAKES`
+/// Failed handshakes and those not done within `HANDSHAKE_TIMEOUT` are dropped
+/// The accept loop ends once the stream is dropped (server stopped)
+pub fn tls(
+    listener: std::net::TcpListener,
+    tls: &TlsConfig,
+) -> io::Result<impl TryStream<Ok = TlsStream<TcpStream>, Error = io::Error> + Send> {
+    listener.set_nonblocking(true)?;
+    let listener = TcpListener::from_std(listener)?;
+    let acceptor = TlsAcceptor::from(tls.server_config.clone());
+    let (sender, receiver) = mpsc::channel(HANDSHAKE_QUEUE);
+    let handshakes = Arc::new(Semaphore::new(MAX_HANDSHAKES));
+
+    tokio::spawn(async move {
+        loop {
+            let permit = tokio::select! {
+                _ = sender.closed() => break,
+                permit = handshakes.clone().acquire_owned() => match permit {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                },
+            };
+            let stream = tokio::select! {
+                _ = sender.closed() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => {
+                        tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                        continue;
+                    }
+                },
+            };
+            let acceptor = acceptor.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let handshake =
+                    tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await;
+                drop(permit);
+                if let Ok(Ok(stream)) = handshake {
+                    let _ = sender.send(Ok(stream)).await;
+                }
+            });
+        }
+    });
+
+    Ok(stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|stream| (stream, receiver))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, net::SocketAddr};
+
+    use futures_util::{StreamExt, TryStreamExt};
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+    use setup::config::tls_options::TlsOptions;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::{
+        rustls::{self, ClientConfig, RootCertStore, ServerName},
+        TlsConnector,
+    };
+
+    use super::*;
+
+    /// Generated CA signing a localhost server certificate and a client certificate
+    struct TestPki {
+        dir: TempDir,
+        ca: Certificate,
+        client: Certificate,
+    }
+
+    impl TestPki {
+        fn new() -> Self {
+            let mut ca_params = CertificateParams::new(vec![]);
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = Certificate::from_params(ca_params).unwrap();
+            let server =
+                Certificate::from_params(CertificateParams::new(vec!["localhost".into()])).unwrap();
+            let client =
+                Certificate::from_params(CertificateParams::new(vec!["client".into()])).unwrap();
+
+            let dir = TempDir::new().unwrap();
+            for (file, pem) in [
+                ("ca.pem", ca.serialize_pem().unwrap()),
+                ("cert.pem", server.serialize_pem_with_signer(&ca).unwrap()),
+                ("key.pem", server.serialize_private_key_pem()),
+            ] {
+                fs::write(dir.path().join(file), pem).unwrap();
+            }
+            Self { dir, ca, client }
+        }
+
+        /// HTTPS options of the server, requiring client certificates signed by the CA
+        fn tls_options(&self) -> TlsOptions {
+            let path = |file: &str| Some(self.dir.path().join(file).display().to_string());
+            TlsOptions {
+                tls_cert: path("cert.pem"),
+                tls_key: path("key.pem"),
+                tls_client_ca: path("ca.pem"),
+                tls_redirect_port: None,
+            }
+        }
+
+        /// Client trusting the CA, presenting the client certificate when `with_cert`
+        fn connector(&self, with_cert: bool) -> TlsConnector {
+            let mut roots = RootCertStore::empty();
+            roots
+                .add(&rustls::Certificate(self.ca.serialize_der().unwrap()))
+                .unwrap();
+            let builder = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots);
+            let config = match with_cert {
+                true => builder
+                    .with_client_auth_cert(
+                        vec![rustls::Certificate(
+                            self.client.serialize_der_with_signer(&self.ca).unwrap(),
+                        )],
+                        rustls::PrivateKey(self.client.serialize_private_key_der()),
+                    )
+                    .unwrap(),
+                false => builder.with_no_client_auth(),
+            };
+            TlsConnector::from(Arc::new(config))
+        }
+    }
+
+    /// Write `ping` over a new TLS connection and read the answer
+    async fn ping(addr: SocketAddr, connector: &TlsConnector) -> io::Result<[u8; 4]> {
+        let stream = TcpStream::connect(addr).await?;
+        let domain = ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(domain, stream).await?;
+        stream.write_all(b"ping").await?;
+        let mut answer = [0; 4];
+        stream.read_exact(&mut answer).await?;
+        Ok(answer)
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_handshake() {
+        // Arrange
+        let pki = TestPki::new();
+        let config = TlsConfig::load(&pki.tls_options()).unwrap().unwrap();
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = tls(listener, &config).unwrap();
+        tokio::spawn(async move {
+            let mut incoming = Box::pin(incoming.into_stream());
+            while let Some(Ok(mut stream)) = incoming.next().await {
+                let mut request = [0; 4];
+                if stream.read_exact(&mut request).await.is_ok() {
+                    let _ = stream.write_all(b"pong").await;
+                }
+            }
+        });
+        // A client never starting its handshake doesn't hold the others back
+        let _stalled = TcpStream::connect(addr).await.unwrap();
+
+        // Act
+        let authenticated = ping(addr, &pki.connector(true)).await;
+        let anonymous = ping(addr, &pki.connector(false)).await;
+
+        // Assert
+        assert_eq!(&authenticated.unwrap(), b"pong");
+        assert!(anonymous.is_err());
+    }
+}
@@ -1,38 +1,488 @@
-use std::{net::SocketAddrV4, sync::Arc};
+use std::{future::Future, pin::Pin, sync::Arc};
 
 use domains::data_source::DataSource;
 use errors::handle_rejection;
-use warp::{http::Method, Filter};
+use futures_util::future;
+use setup::{
+    config::runtime_config::SharedConfig, listener::Listener, shutdown::Shutdown, tls::TlsConfig,
+};
+use warp::Filter;
 
 mod base;
 mod cat;
+mod cors;
+mod docs;
 mod helpers;
+mod https;
+mod incoming;
+mod serve;
+
+/// Start HTTP server on every listener, HTTPS when `tls` is set
+pub async fn start(
+    data_source: DataSource,
+    runtime: SharedConfig,
+    shutdown: Shutdown,
+    listeners: Vec<Listener>,
+    tls: Option<TlsConfig>,
+) -> Result<(), std::io::Error> {
+    for listener in &listeners {
+        println!("🚀 Server listening on: {}", listener.url(tls.is_some()));
+    }
+    if let Some(port) = tls.as_ref().and_then(|tls| tls.redirect_port) {
+        println!("↪️ Redirecting HTTP to HTTPS from port: {}", port);
+    }
+
+    server(data_source, runtime, shutdown, listeners, tls)?.await;
+
+    Ok(())
+}
+
+/// Routes of the `/api` scope, documented by the `docs::ApiDoc` paths of `route_templates`
+pub(crate) fn api_routes(
+    data: Arc<DataSource>,
+    runtime: SharedConfig,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let base_api = base::routes::routes_config(data.clone());
+    let docs_api = docs::routes::routes_config();
+    let cat_api = cat::routes::routes_config(data, runtime);
+
+    base_api.or(docs_api).or(cat_api)
+}
+
+/// Route templates of the request metrics and of the served spec,
+/// listed next to the routes since warp doesn't expose them
+fn route_templates() -> Vec<String> {
+    [
+        base::routes::ROUTE_TEMPLATES.as_slice(),
+        &docs::routes::ROUTE_TEMPLATES,
+        &cat::routes::ROUTE_TEMPLATES,
+    ]
+    .concat()
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Build the HTTP server on already bound listeners (see `setup::listener::bind`), the returned future runs it
+/// The server stops once `shutdown` is triggered: readiness is cleared, the listeners are closed after
+/// the shutdown delay, in-flight requests get the grace period to finish, then the database pool is closed
+/// While running, the trash is purged of the cats kept past their retention
+/// CORS origins and body limit are read from the runtime config on each request (reloadable),
+/// the rest of the CORS policy when the server is built
+/// With `tls`, TCP listeners serve HTTPS and a second server may redirect plain HTTP to it
+/// (on the same ips), unix sockets stay plain HTTP
+pub fn server(
+    data_source: DataSource,
+    runtime: SharedConfig,
+    shutdown: Shutdown,
+    listeners: Vec<Listener>,
+    tls: Option<TlsConfig>,
+) -> Result<impl Future<Output = ()>, std::io::Error> {
+    let delay = runtime.get().options.shutdown_delay();
+    let grace = runtime.get().options.shutdown_grace();
 
-/// Start HTTP server
-pub async fn start(data_source: DataSource, addr: &str) -> Result<(), std::io::Error> {
     // Wrap our data into an Arc for multithread concurrency
     let data = Arc::new(data_source);
+    let purge_runtime = runtime.clone();
 
-    println!("🚀 Server listening on: {}", &addr);
+    let root_scope = warp::path("api");
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_header("content-type")
-        .allow_methods(&[Method::PUT, Method::DELETE, Method::GET, Method::POST]);
+    let api = api_routes(data.clone(), runtime.clone());
 
-    let root_scope = warp::path("api");
+    let routes = helpers::start_request_metrics(Arc::new(route_templates()))
+        .and(
+            helpers::start_security_headers(runtime.clone())
+                .and(
+                    base::routes::get_metrics(data.clone()).or(cors::start_cors(runtime.clone())
+                        .and(
+                            root_scope
+                                .and(
+                                    cors::preflight(runtime.clone())
+                                        .or(helpers::with_runtime_limits(runtime).and(api)),
+                                )
+                                .recover(handle_rejection),
+                        )
+                        .map(cors::finish_cors)),
+                )
+                .map(helpers::finish_security_headers),
+        )
+        .map(helpers::finish_request_metrics);
 
-    let base_api = base::routes::routes_config(data.clone());
-    let cat_api = cat::routes::routes_config(data.clone());
+    let signal = || {
+        let stopping = data.clone();
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown.wait().await;
+            stopping.stop_serving();
+            // Readiness fails while the listeners are still open
+            tokio::time::sleep(delay).await;
+        }
+    };
+    let tcp_addrs = listeners
+        .iter()
+        .filter_map(|listener| match listener {
+            Listener::Tcp(listener) => listener.local_addr().ok(),
+            Listener::Unix(..) => None,
+        })
+        .collect::<Vec<_>>();
 
-    let api = base_api.or(cat_api).with(cors).with(warp::log("info"));
+    // warp's own `tls()` can't reload the certificate, so TLS streams come from a rustls acceptor
+    let mut servers: Vec<Pin<Box<dyn Future<Output = ()> + Send>>> = Vec::new();
+    let mut socket_files = Vec::new();
+    for listener in listeners {
+        let service = warp::service(routes.clone());
+        servers.push(match (listener, &tls) {
+            (Listener::Tcp(listener), Some(tls)) => Box::pin(serve::serve_incoming(
+                service,
+                incoming::tls(listener, tls)?,
+                signal(),
+            )),
+            (Listener::Tcp(listener), None) => Box::pin(serve::serve_incoming(
+                service,
+                incoming::tcp(listener)?,
+                signal(),
+            )),
+            (Listener::Unix(listener, file), _) => {
+                socket_files.push(file);
+                Box::pin(serve::serve_incoming(
+                    service,
+                    incoming::unix(listener)?,
+                    signal(),
+                ))
+            }
+        });
+    }
 
-    let routes = root_scope.and(api).recover(handle_rejection);
+    let redirect_port = tls.and_then(|tls| tls.redirect_port);
+    if let (Some(port), Some(https_addr)) = (redirect_port, tcp_addrs.first()) {
+        let mut ips = tcp_addrs.iter().map(|addr| addr.ip()).collect::<Vec<_>>();
+        ips.sort();
+        ips.dedup();
+        for ip in ips {
+            let shutdown = shutdown.clone();
+            let (_, redirect) = warp::serve(https::redirect_routes(ip, https_addr.port()))
+                .try_bind_with_graceful_shutdown((ip, port), async move { shutdown.wait().await })
+                .map_err(std::io::Error::other)?;
+            servers.push(Box::pin(redirect));
+        }
+    }
 
-    let socket = addr
-        .parse::<SocketAddrV4>()
-        .expect("Can't parse addr into v4");
-    warp::serve(routes).run(socket).await;
+    let server = async move {
+        let purge = data.clone().spawn_trash_purge(purge_runtime);
+        tokio::select! {
+            _ = future::join_all(servers) => {}
+            _ = async {
+                shutdown.wait().await;
+                tokio::time::sleep(delay + grace).await;
+            } => eprintln!("⚠️ Shutdown grace period elapsed, dropping remaining connections"),
+        }
+        purge.abort();
+        data.close().await;
+        drop(socket_files);
+    };
 
-    Ok(())
+    Ok(server)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use domains::data_source::SourceType;
+    use setup::{
+        config::{
+            app_config::EnvMode,
+            cors_options::{self, CorsOptions},
+            db_config::DbConfig,
+            db_options::DbOptions,
+            listen_options::ListenAddr,
+            runtime_config::RuntimeConfig,
+            security_options,
+            server_config::ServerConfig,
+        },
+        listener,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        task::JoinHandle,
+        time,
+    };
+
+    use super::*;
+
+    /// Server on a random local port, with the `shutdown_delay_secs` pre-stop delay
+    fn spawn_server(
+        data_source: DataSource,
+        shutdown_delay_secs: u64,
+        shutdown: &Shutdown,
+    ) -> (SocketAddr, JoinHandle<()>) {
+        let listeners =
+            listener::bind(&[ListenAddr::Tcp(([127, 0, 0, 1], 0).into())], 0o600).unwrap();
+        let addr = match &listeners[0] {
+            Listener::Tcp(listener) => listener.local_addr().unwrap(),
+            Listener::Unix(..) => unreachable!(),
+        };
+        let mut config = RuntimeConfig::default();
+        config.options.shutdown_delay_secs = shutdown_delay_secs;
+        let server = server(
+            data_source,
+            SharedConfig::new(config),
+            shutdown.clone(),
+            listeners,
+            None,
+        )
+        .unwrap();
+        (addr, tokio::spawn(server))
+    }
+
+    /// Raw HTTP/1.1 exchange on a new connection, closed by the server once answered
+    async fn exchange(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// Every route template of the metrics is routed by warp
+    #[tokio::test]
+    async fn test_route_templates_are_routed() {
+        // Arrange
+        let data = Arc::new(DataSource::mock(None));
+        let routes = warp::path("api")
+            .and(api_routes(data.clone(), SharedConfig::default()))
+            .or(base::routes::get_metrics(data));
+
+        for template in route_templates() {
+            let path = template
+                .replace("{cat_id}", "1")
+                .replace("{file}", "index.css");
+            for method in ["GET", "POST"] {
+                // Act
+                let res = warp::test::request()
+                    .method(method)
+                    .path(&path)
+                    .reply(&routes)
+                    .await;
+
+                // Assert
+                assert_ne!(res.status(), 404, "{method} {path} isn't routed");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_follows_policy() {
+        // Arrange
+        let mut config = RuntimeConfig::default();
+        config.options.cors = CorsOptions::from_lookup(|key| match key {
+            cors_options::ORIGINS => Some("https://*.cats.dev".into()),
+            _ => None,
+        })
+        .unwrap();
+        let runtime = SharedConfig::new(config);
+        let filter = cors::start_cors(runtime.clone())
+            .and(
+                cors::preflight(runtime.clone())
+                    .or(helpers::with_runtime_limits(runtime).map(warp::reply))
+                    .recover(handle_rejection),
+            )
+            .map(cors::finish_cors);
+        let request = |method: &str, origin: &str| {
+            warp::test::request()
+                .method(method)
+                .header("origin", origin)
+                .header("access-control-request-method", "PATCH")
+                .header("access-control-request-headers", "authorization")
+        };
+
+        // Act
+        let preflight = request("OPTIONS", "https://a.cats.dev")
+            .reply(&filter)
+            .await;
+        let denied = request("PATCH", "https://cats.dev").reply(&filter).await;
+        let denied_preflight = request("OPTIONS", "https://cats.dev").reply(&filter).await;
+        let denied_method = warp::test::request()
+            .method("OPTIONS")
+            .header("origin", "https://a.cats.dev")
+            .header("access-control-request-method", "TRACE")
+            .reply(&filter)
+            .await;
+        let allowed = request("PATCH", "https://a.cats.dev").reply(&filter).await;
+
+        // Assert
+        assert!(preflight.status().is_success());
+        let headers = preflight.headers();
+        assert_eq!(headers["access-control-allow-origin"], "https://a.cats.dev");
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-max-age"], "3600");
+        assert!(headers["access-control-allow-methods"]
+            .to_str()
+            .unwrap()
+            .contains("PATCH"));
+        assert_eq!(denied.status(), 403);
+        assert_eq!(denied_preflight.status(), 403);
+        assert!(!denied_preflight
+            .headers()
+            .contains_key("access-control-allow-origin"));
+        assert_eq!(denied_method.status(), 403);
+        assert_eq!(
+            allowed.headers()["access-control-allow-origin"],
+            "https://a.cats.dev"
+        );
+        assert_eq!(allowed.headers()["vary"], "origin");
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_by_route() {
+        // Arrange
+        let runtime = SharedConfig::new(RuntimeConfig::new(
+            &ServerConfig::default(),
+            EnvMode::Production,
+        ));
+        let filter = helpers::start_security_headers(runtime)
+            .and(
+                warp::path("api")
+                    .and(docs::routes::routes_config())
+                    .recover(handle_rejection),
+            )
+            .map(helpers::finish_security_headers);
+
+        // Act
+        let spec = warp::test::request()
+            .path(::docs::OPENAPI_PATH)
+            .reply(&filter)
+            .await;
+        let ui = warp::test::request()
+            .path("/api/docs/")
+            .reply(&filter)
+            .await;
+        let unknown = warp::test::request()
+            .path("/api/unknown")
+            .reply(&filter)
+            .await;
+
+        // Assert
+        assert_eq!(spec.headers()["x-content-type-options"], "nosniff");
+        assert_eq!(spec.headers()["x-frame-options"], "DENY");
+        assert_eq!(
+            spec.headers()["content-security-policy"],
+            security_options::DEFAULT_CSP
+        );
+        assert_eq!(
+            spec.headers()["strict-transport-security"],
+            security_options::DEFAULT_HSTS
+        );
+        assert_eq!(
+            ui.headers()["content-security-policy"],
+            security_options::DEFAULT_DOCS_CSP
+        );
+        assert_eq!(unknown.status(), 404);
+        assert_eq!(unknown.headers()["x-content-type-options"], "nosniff");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_trigger_stops_server() {
+        // Arrange
+        let listeners =
+            listener::bind(&[ListenAddr::Tcp(([127, 0, 0, 1], 0).into())], 0o600).unwrap();
+        let shutdown = Shutdown::new();
+        let server = server(
+            DataSource::mock(None),
+            SharedConfig::default(),
+            shutdown.clone(),
+            listeners,
+            None,
+        )
+        .unwrap();
+        let server = tokio::spawn(server);
+
+        // Act
+        shutdown.trigger();
+
+        // Assert
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("Server stopped")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_requests() {
+        // Arrange
+        let shutdown = Shutdown::new();
+        let (addr, server) = spawn_server(DataSource::mock(None), 1, &shutdown);
+        let body = r#"{"name": "Nala", "age": 2}"#;
+        let mut in_flight = TcpStream::connect(addr).await.unwrap();
+        in_flight
+            .write_all(
+                format!(
+                    "POST /api/cats/ HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    &body[..10]
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let ready =
+            "GET /api/health/ready/ HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        let serving = exchange(addr, ready).await;
+
+        // Act
+        shutdown.trigger();
+        time::sleep(Duration::from_millis(200)).await;
+        let stopping = exchange(addr, ready).await;
+        // Past the delay, the listeners are closed
+        time::sleep(Duration::from_millis(1300)).await;
+        in_flight.write_all(&body.as_bytes()[10..]).await.unwrap();
+        let mut response = String::new();
+        in_flight.read_to_string(&mut response).await.unwrap();
+
+        // Assert
+        assert!(serving.starts_with("HTTP/1.1 200"), "{serving}");
+        assert!(stopping.starts_with("HTTP/1.1 503"), "{stopping}");
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(TcpStream::connect(addr).await.is_err());
+        time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("Server stopped")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_pool() {
+        // Arrange
+        let config = DbConfig {
+            host: "127.0.0.1".into(),
+            port: 1,
+            options: DbOptions {
+                connect_retries: 1,
+                retry_delay_ms: 10,
+                acquire_timeout_secs: 1,
+                degraded_start: true,
+                ..DbOptions::default()
+            },
+            ..DbConfig::default()
+        };
+        let data_source = DataSource::db(&config).await.unwrap();
+        let pool = match &data_source.source {
+            SourceType::DB(source) => source.db.connection.clone(),
+            SourceType::Mock(_) => unreachable!(),
+        };
+        let shutdown = Shutdown::new();
+        let (_, server) = spawn_server(data_source, 0, &shutdown);
+
+        // Act
+        shutdown.trigger();
+        time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("Server stopped")
+            .unwrap();
+
+        // Assert
+        assert!(pool.is_closed());
+    }
 }
@@ -0,0 +1,251 @@
+use std::{convert::Infallible, error::Error as StdError, net::SocketAddr};
+
+use common::request_id::{self, RequestTrace};
+use futures_util::{future, Future, TryStream, TryStreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpStream, UnixStream},
+};
+use tokio_rustls::server::TlsStream;
+use warp::{
+    http::HeaderValue,
+    hyper::{
+        server::accept,
+        service::{make_service_fn, service_fn, Service},
+        Body, Request, Response, Server,
+    },
+};
+
+/// Peer address of the connection a request came from, None on unix sockets
+/// `warp::addr::remote` is only set by warp's own server, filters read this one from the request
+/// extensions instead (see `warp::ext::optional`)
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteAddr(pub Option<SocketAddr>);
+
+/// Connection served by `serve_incoming`
+pub trait Connection {
+    fn remote_addr(&self) -> Option<SocketAddr>;
+}
+
+impl Connection for TcpStream {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr().ok()
+    }
+}
+
+impl Connection for TlsStream<TcpStream> {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.get_ref().0.peer_addr().ok()
+    }
+}
+
+impl Connection for UnixStream {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+}
+
+/// Serve the connections of `incoming` until `signal`, same as warp's `serve_incoming_with_graceful_shutdown`
+/// Filters can't wrap the rest of the chain in a span, so each request is traced around the
+/// whole filtered `service` (see `warp::service`)
+pub fn serve_incoming<S, I>(
+    service: S,
+    incoming: I,
+    signal: impl Future<Output = ()> + Send + 'static,
+) -> impl Future<Output = ()>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    I: TryStream + Send,
+    I::Ok: AsyncRead + AsyncWrite + Connection + Send + Unpin + 'static,
+    I::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    let make_service = make_service_fn(move |connection: &I::Ok| {
+        let service = service.clone();
+        let remote_addr = RemoteAddr(connection.remote_addr());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |mut request: Request<Body>| {
+                request.extensions_mut().insert(remote_addr);
+                trace_request(service.clone(), request)
+            }))
+        }
+    });
+
+    async move {
+        let server = Server::builder(accept::from_stream(incoming.into_stream()))
+            .serve(make_service)
+            .with_graceful_shutdown(signal);
+        if let Err(err) = server.await {
+            tracing::error!("Server error: {err}");
+        }
+    }
+}
+
+/// Serve the request within its trace (span and request id), the id being echoed in the `X-Request-Id` header
+async fn trace_request<S>(
+    mut service: S,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+{
+    let trace = RequestTrace::start(
+        |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        },
+        request.method().as_str(),
+        request.uri().path(),
+    );
+
+    future::poll_fn(|cx| service.poll_ready(cx)).await?;
+    // Filters partly run when called, so the call is made within the scope too
+    let mut response = trace.scope(async { service.call(request).await }).await?;
+
+    trace.finish(response.status().as_u16());
+    response.headers_mut().insert(
+        request_id::HEADER,
+        HeaderValue::from_str(&trace.id).expect("Request ids are visible ascii"),
+    );
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::ErrorPayload;
+    use opentelemetry_proto::tonic::{
+        collector::trace::v1::ExportTraceServiceRequest, common::v1::any_value,
+    };
+    use prost::Message;
+    use setup::{config::telemetry_options::TelemetryOptions, telemetry};
+    use tokio::sync::mpsc;
+    use tracing_subscriber::layer::SubscriberExt;
+    use warp::{hyper::body::Bytes, Filter};
+
+    use super::*;
+
+    /// In-process stand-in of an OTLP/HTTP collector, forwarding the export requests it receives
+    fn collector() -> (String, mpsc::UnboundedReceiver<Bytes>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let traces = warp::post()
+            .and(warp::path!("v1" / "traces"))
+            .and(warp::body::bytes())
+            .map(move |body: Bytes| {
+                let _ = sender.send(body);
+                warp::reply()
+            });
+        let (addr, server) = warp::serve(traces).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (format!("http://{addr}"), receiver)
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed() {
+        // Arrange
+        let service = service_fn(|_request| async {
+            let payload = ErrorPayload::new(vec!["Not found"]);
+            Ok::<_, Infallible>(Response::new(Body::from(
+                serde_json::to_string(&payload).unwrap(),
+            )))
+        });
+        let request = Request::builder()
+            .header(request_id::HEADER, "abc-123")
+            .body(Body::empty())
+            .unwrap();
+
+        // Act
+        let response = trace_request(service, request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.headers()[request_id::HEADER], "abc-123");
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["request_id"], "abc-123");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_are_exported_in_the_caller_trace() {
+        // Arrange
+        let (endpoint, mut exports) = collector();
+        let options = TelemetryOptions {
+            otlp_endpoint: Some(endpoint),
+            ..Default::default()
+        };
+        let provider = telemetry::tracer_provider(&options).unwrap().unwrap();
+        telemetry::propagate_trace_context();
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(telemetry::otel_layer(&provider)),
+        );
+        let service = service_fn(|_request| async {
+            request_id::record_route("GET", "/api/cats/{cat_id}/");
+            tracing::info_span!("select_one").in_scope(|| {});
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        });
+        let request = Request::builder()
+            .uri("/api/cats/1/")
+            .header(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        // Act
+        trace_request(service, request).await.unwrap();
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Assert
+        let export = ExportTraceServiceRequest::decode(exports.recv().await.unwrap()).unwrap();
+        let spans: Vec<_> = export
+            .resource_spans
+            .into_iter()
+            .flat_map(|resource| resource.scope_spans)
+            .flat_map(|scope| scope.spans)
+            .collect();
+        let request = spans
+            .iter()
+            .find(|span| span.name == "GET /api/cats/{cat_id}/")
+            .expect("Request span exported");
+        let controller = spans
+            .iter()
+            .find(|span| span.name == "select_one")
+            .expect("Controller span exported");
+
+        let trace_id = request
+            .trace_id
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        assert_eq!(trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(
+            request.parent_span_id,
+            [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]
+        );
+        assert_eq!(controller.parent_span_id, request.span_id);
+        let attribute = |key: &str| {
+            request
+                .attributes
+                .iter()
+                .find(|attribute| attribute.key == key)
+                .and_then(|attribute| attribute.value.clone()?.value)
+        };
+        assert_eq!(
+            attribute("http.route"),
+            Some(any_value::Value::StringValue("/api/cats/{cat_id}/".into()))
+        );
+        assert_eq!(
+            attribute("http.response.status_code"),
+            Some(any_value::Value::IntValue(200))
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::{io, process::ExitCode};
+
+use clap::Parser;
+use domains::data_source::DataSource;
+use errors::{ClientError, Errors};
+use setup::config::app_config::{AppConfig, DataMode};
+use wsctl::{run, Cli};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Load .env file from the current directory
+    dotenv::dotenv().ok();
+
+    let cli = Cli::parse();
+
+    let app_config = match AppConfig::from_env(&cli.config_env()) {
+        Ok(app_config) => app_config,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Data source selection
+    let data_source = match &app_config.data_mode {
+        DataMode::File => {
+            eprintln!("📄 Data source set to: File (changes are not persisted)");
+            DataSource::mock(None)
+        }
+        DataMode::Database => match DataSource::db(&app_config.database).await {
+            Ok(data_source) => data_source,
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    match run(cli, &data_source, &mut io::stdin().lock()).await {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            match err.error {
+                Errors::Client(ClientError::InvalidFields { errors }) => eprintln!("{errors}"),
+                error => eprintln!("{error}"),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use setup::config::config_env::ConfigEnv;
+
+#[derive(Debug, Parser)]
+#[clap(author, version, long_about = None)]
+/// Administration of the web service data (accounts and cats)
+/// Uses the same configuration and data source as the servers
+pub struct Cli {
+    /// Print the output as json
+    #[clap(long, global = true)]
+    pub json: bool,
+    /// Config file path (toml, yaml or json), overrides CONFIG_FILE
+    #[clap(long, global = true)]
+    pub config: Option<String>,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// Config environment of the process, the `--config` flag taking precedence over CONFIG_FILE
+    /// The wsctl arguments are not forwarded, they are not server config flags
+    pub fn config_env(&self) -> ConfigEnv {
+        let env = ConfigEnv::from_process().with_args(&["wsctl"]);
+        match &self.config {
+            Some(path) => env.with_var("CONFIG_FILE", path),
+            None => env,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage accounts
+    #[clap(subcommand)]
+    Account(AccountCommand),
+    /// Manage cats
+    #[clap(subcommand)]
+    Cat(CatCommand),
+    /// Manage the database schema (database data mode only)
+    #[clap(subcommand)]
+    Migrate(MigrateCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AccountCommand {
+    /// List all accounts
+    List,
+    /// Show one account
+    Show(AccountRef),
+    /// Create an account
+    Create {
+        #[clap(long)]
+        email: String,
+        #[clap(flatten)]
+        password: PasswordInput,
+        #[clap(long, value_enum, default_value_t = Role::Member)]
+        role: Role,
+        /// Mark the email as verified
+        #[clap(long)]
+        verified: bool,
+    },
+    /// Change the role of an account
+    SetRole {
+        #[clap(flatten)]
+        account: AccountRef,
+        #[clap(value_enum)]
+        role: Role,
+    },
+    /// Promote an account to admin
+    Promote(AccountRef),
+    /// Mark the account email as verified
+    Verify(AccountRef),
+    /// Set a new password
+    ResetPassword {
+        #[clap(flatten)]
+        account: AccountRef,
+        #[clap(flatten)]
+        password: PasswordInput,
+    },
+    /// Delete an account
+    Delete(AccountRef),
+}
+
+/// Account designated by its id or its email
+#[derive(Debug, Args)]
+pub struct AccountRef {
+    /// Account id or email
+    pub account: String,
+}
+
+/// Password source, never an argument (visible in the process list and the shell history)
+/// Without `--password-stdin`, read from the WSCTL_PASSWORD env variable when set, prompted for otherwise
+#[derive(Debug, Args)]
+pub struct PasswordInput {
+    /// Read the password from the first line of the standard input
+    #[clap(long)]
+    pub password_stdin: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Role {
+    Member,
+    Admin,
+}
+
+impl From<Role> for String {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Member => "member".into(),
+            Role::Admin => "admin".into(),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CatCommand {
+    /// List all cats
+    List,
+    /// Show one cat
+    Show { id: i32 },
+    /// Create a cat
+    Create {
+        #[clap(long)]
+        name: String,
+        #[clap(long)]
+        age: i16,
+        #[clap(long)]
+        weight: Option<f32>,
+    },
+    /// Modify the provided fields of a cat
+    Update {
+        id: i32,
+        #[clap(long)]
+        name: Option<String>,
+        #[clap(long)]
+        age: Option<i16>,
+        #[clap(long)]
+        weight: Option<f32>,
+    },
+    /// Move a cat to the trash
+    Delete { id: i32 },
+    /// List the trashed cats
+    Trash,
+    /// Restore a trashed cat
+    Restore { id: i32 },
+    /// Delete a trashed cat for good
+    Purge { id: i32 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the last applied migration, or down to a target version
+    Down {
+        /// Version to revert to (this version stays applied)
+        #[clap(long)]
+        target: Option<i64>,
+    },
+    /// Show applied and pending migrations
+    Status,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_flag_sets_config_file() {
+        // Arrange
+        let cli = Cli::parse_from(["wsctl", "cat", "list", "--config", "ws.toml"]);
+        // Act
+        let env = cli.config_env();
+        // Assert
+        assert_eq!(env.config_path().as_deref(), Some("ws.toml"));
+        assert_eq!(env.args(), ["wsctl"]);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod account;
+pub mod cat;
+pub mod migrate;
@@ -0,0 +1,345 @@
+use domains::{
+    account::{
+        controller_db, controller_mock,
+        models::{Account, NewAccount, ResetPassword, UpdateAccount},
+    },
+    audit::models::AuditContext,
+    data_source::DataSource,
+};
+use std::{env, io::BufRead};
+
+use errors::{AppError, ConfigError};
+use validator::Validate;
+
+use crate::{AccountCommand, AccountRef, Output, PasswordInput, Role};
+
+/// Env variable the password is read from, unless read from the standard input
+const PASSWORD_VAR: &str = "WSCTL_PASSWORD";
+
+pub async fn run(
+    command: AccountCommand,
+    data: &DataSource,
+    stdin: &mut impl BufRead,
+) -> Result<Output, AppError> {
+    let output = match command {
+        AccountCommand::List => {
+            let accounts = data
+                .exec_controller(
+                    |data_source| Box::pin(controller_mock::select_all(data_source)),
+                    |data_source| Box::pin(controller_db::select_all(data_source)),
+                )
+                .await?;
+            Output::Accounts(accounts.into_iter().map(Account::secure).collect())
+        }
+        AccountCommand::Show(account) => Output::Account(find(&account, data).await?.secure()),
+        AccountCommand::Create {
+            email,
+            password,
+            role,
+            verified,
+        } => {
+            // Same validation as the sign up route
+            let new_account = NewAccount {
+                email,
+                password: read_password(&password, stdin)?,
+                role: role.into(),
+                verified,
+            };
+            new_account.validate()?;
+
+            let account = data
+                .exec_controller(
+                    |data_source| {
+                        Box::pin(controller_mock::create_one(
+                            new_account.clone(),
+                            data_source,
+                        ))
+                    },
+                    |data_source| {
+                        Box::pin(controller_db::create_one(new_account.clone(), data_source))
+                    },
+                )
+                .await?;
+            Output::Account(account.secure())
+        }
+        AccountCommand::SetRole { account, role } => {
+            update(&account, role_update(role), data).await?
+        }
+        AccountCommand::Promote(account) => {
+            update(&account, role_update(Role::Admin), data).await?
+        }
+        AccountCommand::Verify(account) => {
+            let update_account = UpdateAccount {
+                verified: Some(true),
+                ..Default::default()
+            };
+            update(&account, update_account, data).await?
+        }
+        AccountCommand::ResetPassword { account, password } => {
+            let reset = ResetPassword {
+                password: read_password(&password, stdin)?,
+            };
+            reset.validate()?;
+
+            let id = find(&account, data).await?.id.0;
+            let account = data
+                .exec_controller(
+                    |data_source| {
+                        Box::pin(controller_mock::reset_password(
+                            id,
+                            reset.password.clone(),
+                            data_source,
+                        ))
+                    },
+                    |data_source| {
+                        Box::pin(controller_db::reset_password(
+                            id,
+                            reset.password.clone(),
+                            data_source,
+                        ))
+                    },
+                )
+                .await?;
+            Output::Account(account.secure())
+        }
+        AccountCommand::Delete(account) => {
+            let id = find(&account, data).await?.id.0;
+            let message = data
+                .exec_controller(
+                    |data_source| Box::pin(controller_mock::delete_one(id, data_source)),
+                    |data_source| Box::pin(controller_db::delete_one(id, data_source)),
+                )
+                .await?;
+            Output::Message(message)
+        }
+    };
+
+    Ok(output)
+}
+
+/// Password from the standard input, the WSCTL_PASSWORD env variable or a prompt without echo
+fn read_password(input: &PasswordInput, stdin: &mut impl BufRead) -> Result<String, AppError> {
+    let password = match (input.password_stdin, env::var(PASSWORD_VAR)) {
+        (true, _) => {
+            let mut line = String::new();
+            stdin
+                .read_line(&mut line)
+                .map(|_| line.trim_end_matches(['\r', '\n']).to_owned())
+        }
+        (false, Ok(password)) => Ok(password),
+        (false, Err(_)) => rpassword::prompt_password("Password: "),
+    };
+
+    password.map_err(|err| {
+        ConfigError::InvalidArguments {
+            reason: format!("Can't read the password. {err}"),
+        }
+        .into()
+    })
+}
+
+fn role_update(role: Role) -> UpdateAccount {
+    UpdateAccount {
+        role: Some(role.into()),
+        ..Default::default()
+    }
+}
+
+/// Find the account by id, or by email when the reference isn't an uuid
+async fn find(account: &AccountRef, data: &DataSource) -> Result<Account, AppError> {
+    match uuid::Uuid::try_parse(&account.account) {
+        Ok(id) => {
+            data.exec_controller(
+                |data_source| Box::pin(controller_mock::select_one(id, data_source)),
+                |data_source| Box::pin(controller_db::select_one(id, data_source)),
+            )
+            .await
+        }
+        Err(_) => {
+            let email = account.account.clone();
+            data.exec_controller(
+                |data_source| {
+                    Box::pin(controller_mock::select_by_email(email.clone(), data_source))
+                },
+                |data_source| Box::pin(controller_db::select_by_email(email.clone(), data_source)),
+            )
+            .await
+        }
+    }
+}
+
+async fn update(
+    account: &AccountRef,
+    update_account: UpdateAccount,
+    data: &DataSource,
+) -> Result<Output, AppError> {
+    let id = find(account, data).await?.id.0;
+    // Role changes made from the command line are audited without actor nor request
+    let context = AuditContext::default();
+    let account = data
+        .exec_controller(
+            |data_source| {
+                Box::pin(controller_mock::update_one(
+                    id,
+                    update_account.clone(),
+                    &context,
+                    data_source,
+                ))
+            },
+            |data_source| {
+                Box::pin(controller_db::update_one(
+                    id,
+                    update_account.clone(),
+                    &context,
+                    data_source,
+                ))
+            },
+        )
+        .await?
+        .secure();
+
+    Ok(Output::Account(account))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use domains::data_source::{MockSource, SourceType};
+    use errors::{ClientError, Errors};
+
+    use crate::{run, Cli};
+
+    use super::*;
+
+    fn test_data_mock() -> DataSource {
+        DataSource::mock(Some(MockSource::new()))
+    }
+
+    /// Run the command with `stdin` as standard input
+    async fn exec(args: &[&str], stdin: &str, data: &DataSource) -> Result<String, AppError> {
+        let cli = Cli::parse_from([&["wsctl", "--json"], args].concat());
+        run(cli, data, &mut stdin.as_bytes()).await
+    }
+
+    #[tokio::test]
+    async fn test_create_and_promote() {
+        // Arrange
+        let data = test_data_mock();
+
+        // Act
+        exec(
+            &[
+                "account",
+                "create",
+                "--email",
+                "admin@mail.com",
+                "--password-stdin",
+            ],
+            "Pass:12345\n",
+            &data,
+        )
+        .await
+        .unwrap();
+        let promoted = exec(&["account", "promote", "admin@mail.com"], "", &data)
+            .await
+            .unwrap();
+        let accounts = exec(&["account", "list"], "", &data).await.unwrap();
+
+        // Assert
+        let promoted: serde_json::Value = serde_json::from_str(&promoted).unwrap();
+        let accounts: serde_json::Value = serde_json::from_str(&accounts).unwrap();
+        assert_eq!(promoted["role"], "admin");
+        assert!(promoted.get("password").is_none());
+        assert_eq!(accounts.as_array().unwrap().len(), 2);
+        let audit_log = match &data.source {
+            SourceType::Mock(data_source) => data_source.audit_log.read().await.clone(),
+            SourceType::DB(_) => unreachable!(),
+        };
+        assert_eq!(audit_log.len(), 1);
+        assert_eq!(audit_log[0].action, "role_change");
+        assert_eq!(audit_log[0].diff["after"]["role"], "admin");
+    }
+
+    #[tokio::test]
+    async fn test_create_weak_password_fail() {
+        // Arrange
+        let data = test_data_mock();
+
+        // Act
+        let result = exec(
+            &[
+                "account",
+                "create",
+                "--email",
+                "member@mail.com",
+                "--password-stdin",
+            ],
+            "password\n",
+            &data,
+        )
+        .await;
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(AppError {
+                error: Errors::Client(ClientError::InvalidFields { .. })
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reset_password() {
+        // Arrange
+        let data = test_data_mock();
+
+        // Act
+        exec(
+            &[
+                "account",
+                "reset-password",
+                "test@mail.com",
+                "--password-stdin",
+            ],
+            "New:pass99\r\n",
+            &data,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let account = data
+            .exec_controller(
+                |data_source| {
+                    Box::pin(controller_mock::select_by_email(
+                        "test@mail.com".into(),
+                        data_source,
+                    ))
+                },
+                |_data_source| unimplemented!(),
+            )
+            .await
+            .unwrap();
+        assert!(common::crypto::verify_password(&account.password, "New:pass99".into()).is_ok());
+    }
+
+    #[test]
+    fn test_password_argument_rejected() {
+        // Arrange
+        let args = [
+            "wsctl",
+            "account",
+            "create",
+            "--email",
+            "member@mail.com",
+            "--password",
+            "Pass:12345",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args);
+
+        // Assert
+        assert!(cli.is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use domains::{
+    audit::models::AuditContext,
+    cat::{
+        controller_db, controller_mock,
+        models::{Cat, NewCat, UpdateCat},
+    },
+    data_source::DataSource,
+};
+use errors::AppError;
+
+use crate::{CatCommand, Output};
+
+pub async fn run(command: CatCommand, data: &DataSource) -> Result<Output, AppError> {
+    // Mutations made from the command line are audited without actor nor request
+    let context = AuditContext::default();
+    let output = match command {
+        CatCommand::List => Output::Cats(
+            data.exec_controller(
+                |data_source| Box::pin(controller_mock::select_all(data_source)),
+                |data_source| Box::pin(controller_db::select_all(data_source)),
+            )
+            .await?,
+        ),
+        CatCommand::Show { id } => Output::Cat(select_one(id, data).await?),
+        CatCommand::Create { name, age, weight } => {
+            let new_cat = NewCat { name, age, weight };
+            let cat = data
+                .exec_controller(
+                    |data_source| {
+                        Box::pin(controller_mock::create_one(
+                            new_cat.clone(),
+                            &context,
+                            data_source,
+                        ))
+                    },
+                    |data_source| {
+                        Box::pin(controller_db::create_one(
+                            new_cat.clone(),
+                            &context,
+                            data_source,
+                        ))
+                    },
+                )
+                .await?;
+            Output::Cat(cat)
+        }
+        CatCommand::Update {
+            id,
+            name,
+            age,
+            weight,
+        } => {
+            let update_cat = UpdateCat {
+                name,
+                age,
+                weight,
+                version: None,
+            };
+            let cat = data
+                .exec_controller(
+                    |data_source| {
+                        Box::pin(controller_mock::update_one(
+                            id,
+                            update_cat.clone(),
+                            &context,
+                            data_source,
+                        ))
+                    },
+                    |data_source| {
+                        Box::pin(controller_db::update_one(
+                            id,
+                            update_cat.clone(),
+                            &context,
+                            data_source,
+                        ))
+                    },
+                )
+                .await?;
+            Output::Cat(cat)
+        }
+        CatCommand::Delete { id } => {
+            let message = data
+                .exec_controller(
+                    |data_source| {
+                        Box::pin(controller_mock::delete_one(id, None, &context, data_source))
+                    },
+                    |data_source| {
+                        Box::pin(controller_db::delete_one(id, None, &context, data_source))
+                    },
+                )
+                .await?;
+            Output::Message(message)
+        }
+        CatCommand::Trash => Output::Cats(
+            data.exec_controller(
+                |data_source| Box::pin(controller_mock::select_trash(data_source)),
+                |data_source| Box::pin(controller_db::select_trash(data_source)),
+            )
+            .await?,
+        ),
+        CatCommand::Restore { id } => {
+            let cat = data
+                .exec_controller(
+                    |data_source| Box::pin(controller_mock::restore_one(id, &context, data_source)),
+                    |data_source| Box::pin(controller_db::restore_one(id, &context, data_source)),
+                )
+                .await?;
+            Output::Cat(cat)
+        }
+        CatCommand::Purge { id } => {
+            let message = data
+                .exec_controller(
+                    |data_source| Box::pin(controller_mock::purge_one(id, &context, data_source)),
+                    |data_source| Box::pin(controller_db::purge_one(id, &context, data_source)),
+                )
+                .await?;
+            Output::Message(message)
+        }
+    };
+
+    Ok(output)
+}
+
+async fn select_one(id: i32, data: &DataSource) -> Result<Cat, AppError> {
+    data.exec_controller(
+        |data_source| Box::pin(controller_mock::select_one(id, data_source)),
+        |data_source| Box::pin(controller_db::select_one(id, data_source)),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use clap::Parser;
+    use domains::data_source::MockSource;
+
+    use crate::{run, Cli};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_delete() {
+        // Arrange
+        let data = DataSource::mock(Some(MockSource::new()));
+        let create = Cli::parse_from(["wsctl", "cat", "create", "--name", "Nala", "--age", "2"]);
+        let delete = Cli::parse_from(["wsctl", "cat", "delete", "2"]);
+        let list = Cli::parse_from(["wsctl", "--json", "cat", "list"]);
+
+        // Act
+        let created = run(create, &data, &mut io::empty()).await.unwrap();
+        run(delete, &data, &mut io::empty()).await.unwrap();
+        let cats: serde_json::Value =
+            serde_json::from_str(&run(list, &data, &mut io::empty()).await.unwrap()).unwrap();
+
+        // Assert
+        assert!(created.contains("Nala"));
+        assert_eq!(cats.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trash_restore_and_purge() {
+        // Arrange
+        let data = DataSource::mock(Some(MockSource::new()));
+        let command = |args: &[&str]| Cli::parse_from([&["wsctl"], args].concat());
+        run(command(&["cat", "delete", "1"]), &data, &mut io::empty())
+            .await
+            .unwrap();
+
+        // Act
+        let trash = run(command(&["cat", "trash"]), &data, &mut io::empty())
+            .await
+            .unwrap();
+        let restored = run(command(&["cat", "restore", "1"]), &data, &mut io::empty())
+            .await
+            .unwrap();
+        run(command(&["cat", "delete", "1"]), &data, &mut io::empty())
+            .await
+            .unwrap();
+        run(command(&["cat", "purge", "1"]), &data, &mut io::empty())
+            .await
+            .unwrap();
+        let purged_again = run(command(&["cat", "purge", "1"]), &data, &mut io::empty()).await;
+        let cats: serde_json::Value = serde_json::from_str(
+            &run(command(&["--json", "cat", "list"]), &data, &mut io::empty())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        // Assert
+        assert!(trash.contains("deleted="));
+        assert!(!restored.contains("deleted="));
+        assert!(purged_again.is_err());
+        assert!(cats.as_array().unwrap().is_empty());
+    }
+}
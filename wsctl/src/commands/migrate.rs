@@ -0,0 +1,26 @@
+use domains::{
+    data_source::{DataSource, SourceType},
+    migration,
+};
+use errors::{AppError, Errors, ServerError};
+
+use crate::{MigrateCommand, Output};
+
+pub async fn run(command: MigrateCommand, data: &DataSource) -> Result<Output, AppError> {
+    let pool = match &data.source {
+        SourceType::DB(data_source) => &data_source.db.connection,
+        SourceType::Mock(_) => {
+            return Err(AppError::new(Errors::Server(ServerError::Migration {
+                reason: "Migrations require the database data mode (DATA_MODE=database).".into(),
+            })))
+        }
+    };
+
+    match command {
+        MigrateCommand::Up => migration::migrate_up(pool).await?,
+        MigrateCommand::Down { target } => migration::migrate_down(pool, target).await?,
+        MigrateCommand::Status => {}
+    };
+
+    Ok(Output::Migrations(migration::status(pool).await?))
+}
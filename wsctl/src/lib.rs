@@ -0,0 +1,27 @@
+use std::io::BufRead;
+
+use domains::data_source::DataSource;
+use errors::AppError;
+
+mod cli;
+mod commands;
+mod output;
+
+pub use cli::*;
+pub use output::Output;
+
+/// Execute the command against the data source and return the rendered output
+/// Passwords read with `--password-stdin` come from `stdin`
+pub async fn run(
+    cli: Cli,
+    data_source: &DataSource,
+    stdin: &mut impl BufRead,
+) -> Result<String, AppError> {
+    let output = match cli.command {
+        Command::Account(command) => commands::account::run(command, data_source, stdin).await?,
+        Command::Cat(command) => commands::cat::run(command, data_source).await?,
+        Command::Migrate(command) => commands::migrate::run(command, data_source).await?,
+    };
+
+    Ok(output.render(cli.json))
+}
@@ -0,0 +1,88 @@
+use domains::{account::models::SecureAccount, cat::models::Cat, migration::MigrationStatus};
+use serde_json::json;
+
+/// Result of a command
+pub enum Output {
+    Accounts(Vec<SecureAccount>),
+    Account(SecureAccount),
+    Cats(Vec<Cat>),
+    Cat(Cat),
+    Migrations(Vec<MigrationStatus>),
+    Message(String),
+}
+
+impl Output {
+    pub fn render(&self, as_json: bool) -> String {
+        if as_json {
+            let value = match self {
+                Output::Accounts(accounts) => json!(accounts),
+                Output::Account(account) => json!(account),
+                Output::Cats(cats) => json!(cats),
+                Output::Cat(cat) => json!(cat),
+                Output::Migrations(migrations) => json!(migrations),
+                Output::Message(message) => json!({ "message": message }),
+            };
+            return serde_json::to_string_pretty(&value).expect("Error serializing output");
+        }
+
+        match self {
+            Output::Accounts(accounts) => accounts
+                .iter()
+                .map(account_line)
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Output::Account(account) => account_line(account),
+            Output::Cats(cats) => cats
+                .iter()
+                .map(cat_line)
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Output::Cat(cat) => cat_line(cat),
+            Output::Migrations(migrations) => migrations
+                .iter()
+                .map(migration_line)
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Output::Message(message) => message.to_owned(),
+        }
+    }
+}
+
+fn account_line(account: &SecureAccount) -> String {
+    format!(
+        "{}  {}  role={}  verified={}  created={}",
+        account.id.0,
+        account.email,
+        account.role,
+        account.verified,
+        account.creation_time.to_rfc3339()
+    )
+}
+
+fn cat_line(cat: &Cat) -> String {
+    let line = format!(
+        "{}  {}  age={}  weight={}  created={}",
+        cat.id.0,
+        cat.name,
+        cat.age,
+        cat.weight
+            .map_or_else(|| "-".to_string(), |weight| weight.to_string()),
+        cat.creation_time.to_rfc3339()
+    );
+    match cat.deletion_time {
+        Some(deletion_time) => format!("{line}  deleted={}", deletion_time.to_rfc3339()),
+        None => line,
+    }
+}
+
+fn migration_line(migration: &MigrationStatus) -> String {
+    let state = match (migration.applied, migration.modified) {
+        (true, false) => "applied",
+        (true, true) => "applied (modified since)",
+        (false, _) => "pending",
+    };
+    format!(
+        "{}  {}  {}",
+        migration.version, migration.description, state
+    )
+}